@@ -0,0 +1,13 @@
+CREATE TABLE goals (
+    id UUID PRIMARY KEY,
+    title TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    grade TEXT,
+    exercise TEXT,
+    target REAL,
+    bodyweight_lb REAL,
+    deadline DATE,
+    progress REAL NOT NULL DEFAULT 0,
+    achieved_date DATE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,26 @@
+CREATE TABLE users (
+    id UUID PRIMARY KEY,
+    username TEXT NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE api_tokens (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash TEXT NOT NULL UNIQUE,
+    name TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_used_at TIMESTAMPTZ
+);
+
+-- Rows logged before accounts existed keep a NULL owner and are not visible through the API
+ALTER TABLE climbing_sessions ADD COLUMN user_id UUID REFERENCES users(id) ON DELETE CASCADE;
+ALTER TABLE workout_sessions ADD COLUMN user_id UUID REFERENCES users(id) ON DELETE CASCADE;
+ALTER TABLE climbing_metrics ADD COLUMN user_id UUID REFERENCES users(id) ON DELETE CASCADE;
+ALTER TABLE goals ADD COLUMN user_id UUID REFERENCES users(id) ON DELETE CASCADE;
+
+CREATE INDEX climbing_sessions_user_id_idx ON climbing_sessions (user_id);
+CREATE INDEX workout_sessions_user_id_idx ON workout_sessions (user_id);
+CREATE INDEX climbing_metrics_user_id_idx ON climbing_metrics (user_id);
+CREATE INDEX goals_user_id_idx ON goals (user_id);
@@ -0,0 +1,12 @@
+-- Coaches invite users by username, and a user only joins a team by accepting
+CREATE TABLE team_invitations (
+    id UUID PRIMARY KEY,
+    team_id UUID NOT NULL REFERENCES teams(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role TEXT NOT NULL CHECK (role IN ('coach', 'athlete')),
+    invited_by UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (team_id, user_id)
+);
+
+CREATE INDEX team_invitations_user_id_idx ON team_invitations (user_id);
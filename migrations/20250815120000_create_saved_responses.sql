@@ -0,0 +1,12 @@
+-- What each save of a client-supplied log id sent and got back, so a retry is answered
+-- the same way and a different log reusing the id is refused
+CREATE TABLE saved_responses (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    log_id UUID NOT NULL,
+    endpoint TEXT NOT NULL,
+    request JSONB NOT NULL,
+    status SMALLINT NOT NULL,
+    response JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, log_id)
+);
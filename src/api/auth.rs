@@ -0,0 +1,229 @@
+use crate::api::events::EVENTS_PATH;
+use crate::db::queries::{fetch_token_user_db, fetch_user_by_username_db, insert_api_token_db, insert_user_db};
+use argon2::password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use axum::{
+    extract::State,
+    http::{header::AUTHORIZATION, HeaderMap, Request, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::postgres::PgPool;
+use std::sync::LazyLock;
+use tracing::{error, info};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+pub const TOKEN_PREFIX: &str = "rp_";
+
+// Checked when the username doesn't exist, so an unknown name takes as long as a wrong password
+static DUMMY_HASH: LazyLock<String> = LazyLock::new(|| hash_password("no such user").expect("hashing a fixed password"));
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthUser {
+    pub id: Uuid,
+    pub username: String,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct Credentials {
+    #[validate(length(min = 3, max = 50))]
+    pub username: String,
+    #[validate(length(min = 8, max = 200))]
+    pub password: String,
+    #[validate(length(min = 0, max = 100))]
+    pub token_name: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenResponse {
+    pub user_id: Uuid,
+    pub token: String,
+}
+
+pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(Argon2::default().hash_password(password.as_bytes(), &salt)?.to_string())
+}
+
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .is_ok_and(|parsed| Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+}
+
+pub fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}{}", TOKEN_PREFIX, hex)
+}
+
+// Tokens are long and random, so a fast hash is enough to keep them out of the database
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+}
+
+// Browsers' EventSource can't set headers, so the event stream also takes `?access_token=`
+pub fn query_token(uri: &Uri) -> Option<&str> {
+    if uri.path() != EVENTS_PATH {
+        return None;
+    }
+    uri.query()?
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("access_token="))
+        .filter(|t| !t.is_empty())
+}
+
+async fn issue_token(pool: &PgPool, user_id: Uuid, name: Option<&str>) -> Result<String, (StatusCode, String)> {
+    let token = new_token();
+    insert_api_token_db(pool, user_id, &hash_token(&token), name).await.map_err(|e| {
+        error!("Error saving token with {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Could not create token".to_string())
+    })?;
+    Ok(token)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/register",
+    tag = "auth",
+    request_body = Credentials,
+    responses(
+        (status = 201, description = "User created", body = TokenResponse),
+        (status = 409, description = "Username is taken"),
+        (status = 422, description = "Invalid username or password"),
+    ),
+    security(())
+)]
+pub async fn register(
+    State(pool): State<PgPool>,
+    Json(credentials): Json<Credentials>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    if let Err(e) = credentials.validate() {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, e.to_string()));
+    }
+    let password_hash = hash_password(&credentials.password).map_err(|e| {
+        error!("Error hashing password with {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Could not create user".to_string())
+    })?;
+    let user_id = match insert_user_db(&pool, &credentials.username, &password_hash).await {
+        Ok(id) => id,
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            return Err((StatusCode::CONFLICT, "Username is taken".to_string()));
+        }
+        Err(e) => {
+            error!("Error creating user with {}", e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, "Could not create user".to_string()));
+        }
+    };
+    info!("Registered user {}", credentials.username);
+    let token = issue_token(&pool, user_id, credentials.token_name.as_deref()).await?;
+    Ok((StatusCode::CREATED, Json(TokenResponse { user_id, token })))
+}
+
+// Each login issues a new token, so every device or script gets its own
+#[utoipa::path(
+    post,
+    path = "/api/auth/login",
+    tag = "auth",
+    request_body = Credentials,
+    responses(
+        (status = 201, description = "Token issued", body = TokenResponse),
+        (status = 401, description = "Invalid username or password"),
+    ),
+    security(())
+)]
+pub async fn login(
+    State(pool): State<PgPool>,
+    Json(credentials): Json<Credentials>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user = fetch_user_by_username_db(&pool, &credentials.username).await.map_err(|e| {
+        error!("Error loading user with {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Could not log in".to_string())
+    })?;
+    let hash = user.as_ref().map_or(DUMMY_HASH.as_str(), |(_, hash)| hash.as_str());
+    let verified = verify_password(&credentials.password, hash);
+    let Some((user_id, _)) = user.filter(|_| verified) else {
+        return Err((StatusCode::UNAUTHORIZED, "Invalid username or password".to_string()));
+    };
+    let token = issue_token(&pool, user_id, credentials.token_name.as_deref()).await?;
+    Ok((StatusCode::CREATED, Json(TokenResponse { user_id, token })))
+}
+
+pub async fn require_auth<B>(
+    State(pool): State<PgPool>,
+    mut request: Request<B>,
+    next: Next<B>,
+) -> Result<Response, StatusCode> {
+    let Some(token) = bearer_token(request.headers()).or_else(|| query_token(request.uri())) else {
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+    match fetch_token_user_db(&pool, &hash_token(token)).await {
+        Ok(Some((id, username))) => {
+            request.extensions_mut().insert(AuthUser { id, username });
+            Ok(next.run(request).await)
+        }
+        Ok(None) => Err(StatusCode::UNAUTHORIZED),
+        Err(e) => {
+            error!("Error checking token with {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_password_hash_roundtrip() {
+        let hash = hash_password("correct horse").unwrap();
+        assert!(verify_password("correct horse", &hash));
+        assert!(!verify_password("wrong horse", &hash));
+        assert!(!verify_password("correct horse", "not a hash"));
+        assert!(PasswordHash::new(&DUMMY_HASH).is_ok());
+        assert!(!verify_password("correct horse", &DUMMY_HASH));
+    }
+
+    #[test]
+    fn test_tokens() {
+        let token = new_token();
+        assert!(token.starts_with(TOKEN_PREFIX));
+        assert_eq!(token.len(), TOKEN_PREFIX.len() + 64);
+        assert_ne!(token, new_token());
+        assert_eq!(hash_token(&token), hash_token(&token));
+        assert_eq!(hash_token(&token).len(), 64);
+    }
+
+    #[test]
+    fn test_bearer_token() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(bearer_token(&headers), None);
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer rp_abc"));
+        assert_eq!(bearer_token(&headers), Some("rp_abc"));
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Basic abc"));
+        assert_eq!(bearer_token(&headers), None);
+
+        let events: Uri = "/api/events?log_type=climb&access_token=rp_abc".parse().unwrap();
+        assert_eq!(query_token(&events), Some("rp_abc"));
+        let other: Uri = "/api/goals?access_token=rp_abc".parse().unwrap();
+        assert_eq!(query_token(&other), None);
+    }
+}
@@ -0,0 +1,235 @@
+use crate::api::auth::AuthUser;
+use crate::api::teams::can_view_session;
+use crate::db::queries::fetch_coached_athletes_db;
+use axum::{
+    extract::{Extension, Query, State},
+    http::StatusCode,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPool;
+use std::collections::HashSet;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream};
+use tokio_stream::{Stream, StreamExt};
+use tracing::{error, warn};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+pub const EVENTS_PATH: &str = "/api/events";
+
+// Slow subscribers that fall this far behind get a `lagged` event and should refetch
+pub const EVENT_BUFFER: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LogType {
+    Climb,
+    Workout,
+    Metrics,
+}
+
+impl LogType {
+    // Matches the names `infer_log_type` gives log files
+    pub fn name(&self) -> &'static str {
+        match self {
+            LogType::Climb => "climb",
+            LogType::Workout => "workout",
+            LogType::Metrics => "metrics",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<LogType> {
+        match name {
+            "climb" => Some(LogType::Climb),
+            "workout" => Some(LogType::Workout),
+            "metrics" => Some(LogType::Metrics),
+            _ => None,
+        }
+    }
+}
+
+// Only saves made through the server are seen. The API doesn't delete logs, and ones
+// removed by the CLI, lint fixes or the trash never pass through the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Created,
+    Updated,
+    // A session was made private or shared again. Coaches get these even for private
+    // sessions, so they can drop one they can no longer read.
+    Visibility,
+}
+
+impl ChangeKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChangeKind::Created => "created",
+            ChangeKind::Updated => "updated",
+            ChangeKind::Visibility => "visibility",
+        }
+    }
+}
+
+// Events say what changed, not the log itself, so dashboards refetch through the
+// endpoints that already check access
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEvent {
+    pub change: ChangeKind,
+    pub log_type: LogType,
+    pub user_id: Uuid,
+    pub username: String,
+    pub date: String,
+    // Only set for database sessions; file logs are keyed by type and date
+    pub session_id: Option<Uuid>,
+    pub private: bool,
+}
+
+impl LogEvent {
+    pub fn new(change: ChangeKind, log_type: LogType, user: &AuthUser, date: &str) -> Self {
+        LogEvent {
+            change,
+            log_type,
+            user_id: user.id,
+            username: user.username.clone(),
+            date: date.to_string(),
+            session_id: None,
+            private: false,
+        }
+    }
+
+    pub fn session(mut self, session_id: Uuid) -> Self {
+        self.session_id = Some(session_id);
+        self
+    }
+
+    pub fn private(mut self, private: bool) -> Self {
+        self.private = private;
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Events {
+    sender: broadcast::Sender<LogEvent>,
+}
+
+impl Default for Events {
+    fn default() -> Self {
+        Events { sender: broadcast::channel(EVENT_BUFFER).0 }
+    }
+}
+
+impl Events {
+    // Having no one listening is not an error
+    pub fn publish(&self, event: LogEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<LogEvent> {
+        self.sender.subscribe()
+    }
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EventParams {
+    pub user_id: Option<Uuid>,
+    pub log_type: Option<LogType>,
+}
+
+// Same rule as reading a session: your own logs, plus the non-private logs of athletes you
+// coach. Visibility changes carry no contents, so coaches see those either way.
+pub fn can_see_event(viewer: Uuid, coached: &HashSet<Uuid>, event: &LogEvent) -> bool {
+    let coaches_owner = coached.contains(&event.user_id);
+    (coaches_owner && event.change == ChangeKind::Visibility) || can_view_session(viewer, event.user_id, event.private, coaches_owner)
+}
+
+pub fn matches_params(params: &EventParams, event: &LogEvent) -> bool {
+    params.user_id.is_none_or(|id| id == event.user_id) && params.log_type.is_none_or(|t| t == event.log_type)
+}
+
+// Athletes who join a team after the stream opens show up on the next reconnect
+#[utoipa::path(
+    get,
+    path = "/api/events",
+    tag = "events",
+    params(EventParams, ("access_token" = Option<String>, Query, description = "For EventSource clients, which can't send an Authorization header")),
+    responses(
+        (status = 200, description = "Server-sent events named created, updated or visibility, with a LogEvent as data", content_type = "text/event-stream", body = LogEvent),
+        (status = 404, description = "Unknown user, or one whose logs the caller can't see"),
+    )
+)]
+pub async fn stream_events(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Extension(events): Extension<Events>,
+    Query(params): Query<EventParams>,
+) -> Result<Sse<impl Stream<Item = Result<Event, serde_json::Error>>>, (StatusCode, String)> {
+    let coached: HashSet<Uuid> = fetch_coached_athletes_db(&pool, user.id)
+        .await
+        .map_err(|e| {
+            error!("Error loading coached athletes with {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Could not subscribe to events".to_string())
+        })?
+        .into_iter()
+        .collect();
+    if params.user_id.is_some_and(|id| id != user.id && !coached.contains(&id)) {
+        return Err((StatusCode::NOT_FOUND, "User not found".to_string()));
+    }
+
+    let stream = BroadcastStream::new(events.subscribe()).filter_map(move |received| match received {
+        Ok(event) if can_see_event(user.id, &coached, &event) && matches_params(&params, &event) => {
+            Some(Event::default().event(event.change.name()).json_data(&event))
+        }
+        Ok(_) => None,
+        Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+            warn!("Event subscriber {} skipped {} events", user.username, skipped);
+            Some(Ok(Event::default().event("lagged").data(skipped.to_string())))
+        }
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(name: &str) -> AuthUser {
+        AuthUser { id: Uuid::new_v4(), username: name.to_string() }
+    }
+
+    #[test]
+    fn test_event_visibility_and_filters() {
+        let (coach, athlete, stranger) = (user("coach"), user("athlete"), user("stranger"));
+        let coached = HashSet::from([athlete.id]);
+        let event = LogEvent::new(ChangeKind::Created, LogType::Climb, &athlete, "2024-04-01");
+        assert!(can_see_event(coach.id, &coached, &event));
+        assert!(can_see_event(athlete.id, &HashSet::new(), &event));
+        assert!(!can_see_event(stranger.id, &HashSet::new(), &event));
+        assert!(!can_see_event(coach.id, &coached, &event.clone().private(true)));
+        let hidden = LogEvent::new(ChangeKind::Visibility, LogType::Climb, &athlete, "2024-04-01").private(true);
+        assert!(can_see_event(coach.id, &coached, &hidden));
+        assert!(!can_see_event(stranger.id, &HashSet::new(), &hidden));
+
+        assert!(matches_params(&EventParams::default(), &event));
+        let climbs = EventParams { user_id: Some(athlete.id), log_type: Some(LogType::Climb) };
+        assert!(matches_params(&climbs, &event));
+        let workouts = EventParams { user_id: None, log_type: Some(LogType::Workout) };
+        assert!(!matches_params(&workouts, &event));
+    }
+
+    #[tokio::test]
+    async fn test_publish_reaches_subscribers() {
+        let events = Events::default();
+        events.publish(LogEvent::new(ChangeKind::Created, LogType::Climb, &user("nobody"), "2024-04-01"));
+        let mut receiver = events.subscribe();
+        let event = LogEvent::new(ChangeKind::Updated, LogType::Metrics, &user("athlete"), "2024-04-02");
+        events.publish(event.clone());
+        assert_eq!(receiver.recv().await.unwrap(), event);
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["logType"], "metrics");
+        assert_eq!(json["change"], "updated");
+    }
+}
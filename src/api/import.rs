@@ -0,0 +1,236 @@
+use crate::api::auth::AuthUser;
+use crate::api::events::{ChangeKind, Events, LogEvent, LogType};
+use crate::api::server::idempotency_id;
+use crate::climblib::io::parse_records;
+use crate::climblib::models::{content_log_type, LogEntry};
+use crate::db::queries::{
+    fetch_saved_responses_in, insert_climb_in, insert_metrics_in, insert_saved_response_in, insert_workout_in, SaveOutcome,
+    SavedResponse,
+};
+use axum::{
+    extract::{Extension, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::postgres::PgPool;
+use tracing::{error, info};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+pub const MAX_IMPORT_RECORDS: usize = 1000;
+pub const TYPE_FIELD: &str = "logType";
+pub const IMPORT_PATH: &str = "/api/import";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportStatus {
+    Imported,
+    // Already saved by an earlier request with the same id; nothing was written
+    Replayed,
+    Rejected,
+}
+
+#[derive(Debug, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportResult {
+    // Position of the record in the request
+    pub index: usize,
+    pub status: ImportStatus,
+    pub log_type: Option<LogType>,
+    pub date: Option<String>,
+    pub id: Option<Uuid>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub imported: usize,
+    pub replayed: usize,
+    pub rejected: usize,
+    pub results: Vec<ImportResult>,
+}
+
+// The log as a single save would send it, to compare with earlier saves of its id
+pub fn record_request(log: &LogEntry) -> Value {
+    let request = match log {
+        LogEntry::Climbing(session) => serde_json::to_value(session),
+        LogEntry::Workout(workout) => serde_json::to_value(workout),
+        LogEntry::Metrics(entry) => serde_json::to_value(entry),
+    };
+    request.unwrap_or(Value::Null)
+}
+
+pub fn log_type(log: &LogEntry) -> LogType {
+    match log {
+        LogEntry::Climbing(_) => LogType::Climb,
+        LogEntry::Workout(_) => LogType::Workout,
+        LogEntry::Metrics(_) => LogType::Metrics,
+    }
+}
+
+// The type comes from `logType` when the client sends it, otherwise from the record's fields
+pub fn record_log_type(value: &Value) -> Result<Option<LogType>, String> {
+    match value.get(TYPE_FIELD) {
+        Some(tag) => serde_json::from_value::<LogType>(tag.clone())
+            .map(Some)
+            .map_err(|_| format!("{} must be climb, workout or metrics", TYPE_FIELD)),
+        None => Ok(content_log_type(value).and_then(LogType::from_name)),
+    }
+}
+
+pub fn check_record(mut value: Value) -> Result<LogEntry, Vec<String>> {
+    let log_type = record_log_type(&value).map_err(|e| vec![e])?;
+    let Some(kind) = log_type.map(|t| t.name()) else {
+        return Err(vec![format!("can't tell whether this is a climb, workout or metrics log, set {}", TYPE_FIELD)]);
+    };
+    if let Some(object) = value.as_object_mut() {
+        object.remove(TYPE_FIELD);
+    }
+    let log = LogEntry::parse(kind, value).map_err(|e| vec![format!("not a valid {} log: {}", kind, e)])?;
+    let errors = log.validation_errors();
+    if errors.is_empty() { Ok(log) } else { Err(errors) }
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    error!("Error importing logs with {}", e);
+    (StatusCode::INTERNAL_SERVER_ERROR, "Could not import logs, nothing was saved".to_string())
+}
+
+// Invalid records are reported and skipped. The valid ones are saved in one transaction,
+// so a failed import can be retried as a whole, and records that were already saved come
+// back as replayed instead of being inserted twice. A record reusing the id of a different
+// log is rejected.
+#[utoipa::path(
+    post,
+    path = "/api/import",
+    tag = "db",
+    request_body(
+        content = String,
+        description = "A JSON array or NDJSON of climb, workout and metrics logs, each optionally tagged with logType",
+        content_type = "application/x-ndjson"
+    ),
+    params(("idempotency-key" = Option<String>, Header, description = "Gives records without an id a stable one, based on their position")),
+    responses(
+        (status = 200, description = "One result per record, in request order", body = ImportReport),
+        (status = 400, description = "Body is not JSON or NDJSON"),
+        (status = 413, description = "Too many records"),
+        (status = 500, description = "Nothing was saved"),
+    )
+)]
+pub async fn import_logs(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Extension(events): Extension<Events>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let records = parse_records(&body).map_err(|e| (StatusCode::BAD_REQUEST, format!("Could not parse records: {}", e)))?;
+    if records.len() > MAX_IMPORT_RECORDS {
+        return Err((StatusCode::PAYLOAD_TOO_LARGE, format!("At most {} records per import", MAX_IMPORT_RECORDS)));
+    }
+
+    let mut results = Vec::new();
+    let mut valid = Vec::new();
+    for (index, record) in records.into_iter().enumerate() {
+        // Kept for the report, so a rejected record can still be matched to the client's copy
+        let log_type = record_log_type(&record).ok().flatten();
+        let date = record.get("date").and_then(Value::as_str).map(str::to_string);
+        match check_record(record) {
+            Ok(log) => valid.push((index, log)),
+            Err(errors) => results.push(ImportResult { index, status: ImportStatus::Rejected, log_type, date, id: None, errors }),
+        }
+    }
+
+    // Records without an id get one from the batch's Idempotency-Key and their position
+    let base_id = idempotency_id(&headers, &user);
+    let mut logs = Vec::new();
+    for (index, mut log) in valid {
+        let id = log.id_mut();
+        *id = id.or(base_id.map(|base| Uuid::new_v5(&base, index.to_string().as_bytes())));
+        logs.push((index, log));
+    }
+    let ids: Vec<Uuid> = logs.iter().filter_map(|(_, log)| log.id()).collect();
+    let mut tx = pool.begin().await.map_err(db_error)?;
+    let mut earlier = fetch_saved_responses_in(&mut tx, user.id, &ids).await.map_err(db_error)?;
+    let mut saved = Vec::new();
+    for (index, log) in logs {
+        let (id, request) = (log.id(), record_request(&log));
+        let private = matches!(&log, LogEntry::Climbing(s) if s.private);
+        let event = LogEvent::new(ChangeKind::Created, log_type(&log), &user, log.date()).private(private);
+        // An id saved before with a different log is refused rather than reported as replayed
+        if let Some(id) = id.filter(|id| earlier.get(id).is_some_and(|saved| saved.request != request)) {
+            let errors = vec![format!("id {} was already saved with a different log", id)];
+            results.push(ImportResult { index, status: ImportStatus::Rejected, log_type: Some(event.log_type), date: Some(event.date), id: None, errors });
+            continue;
+        }
+        let outcome = match log {
+            LogEntry::Climbing(session) => insert_climb_in(&mut tx, user.id, session).await,
+            LogEntry::Workout(workout) => insert_workout_in(&mut tx, user.id, workout).await,
+            LogEntry::Metrics(entry) => insert_metrics_in(&mut tx, user.id, entry).await,
+        }
+        .map_err(db_error)?;
+        if let (SaveOutcome::Created(_), Some(id)) = (outcome, id) {
+            let result = ImportResult {
+                index,
+                status: ImportStatus::Imported,
+                log_type: Some(event.log_type),
+                date: Some(event.date.clone()),
+                id: Some(id),
+                errors: vec![],
+            };
+            let response = serde_json::to_value(result).unwrap_or(Value::Null);
+            let kept = SavedResponse { endpoint: IMPORT_PATH.to_string(), request, status: StatusCode::OK.as_u16(), response };
+            insert_saved_response_in(&mut tx, user.id, id, &kept).await.map_err(db_error)?;
+            earlier.insert(id, kept);
+        }
+        saved.push((index, event, outcome));
+    }
+    tx.commit().await.map_err(db_error)?;
+
+    for (index, event, outcome) in saved {
+        let (status, id, errors) = match outcome {
+            SaveOutcome::Created(id) => (ImportStatus::Imported, Some(id), vec![]),
+            SaveOutcome::Replayed(id) => (ImportStatus::Replayed, Some(id), vec![]),
+            SaveOutcome::Taken(id) => (ImportStatus::Rejected, None, vec![format!("id {} belongs to another log", id)]),
+        };
+        results.push(ImportResult { index, status, log_type: Some(event.log_type), date: Some(event.date.clone()), id, errors });
+        if let SaveOutcome::Created(id) = outcome {
+            events.publish(event.session(id));
+        }
+    }
+    results.sort_by_key(|r| r.index);
+    let count = |status: ImportStatus| results.iter().filter(|r| r.status == status).count();
+    let (imported, replayed, rejected) = (count(ImportStatus::Imported), count(ImportStatus::Replayed), count(ImportStatus::Rejected));
+    info!("Imported {} logs for {}, replayed {}, rejected {}", imported, user.username, replayed, rejected);
+    Ok(Json(ImportReport { imported, replayed, rejected, results }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_check_record() {
+        let climb = json!({ "date": "2024-04-01", "location": "Gym", "style": "boulder", "climbs": [] });
+        assert!(matches!(check_record(climb.clone()), Ok(LogEntry::Climbing(_))));
+
+        let tagged = json!({ "logType": "metrics", "date": "2024-04-01", "notes": "rest day" });
+        assert!(matches!(check_record(tagged), Ok(LogEntry::Metrics(_))));
+        let untagged = json!({ "date": "2024-04-01", "notes": "rest day" });
+        assert!(check_record(untagged).unwrap_err()[0].starts_with("can't tell"));
+        assert_eq!(check_record(json!({ "logType": "yoga" })).unwrap_err(), vec!["logType must be climb, workout or metrics"]);
+        assert_eq!(record_log_type(&json!({ "date": "2024-04-01", "exercises": "squats" })), Ok(Some(LogType::Workout)));
+
+        let mut invalid = climb;
+        invalid["climbs"] = json!([{ "grade": "v4", "attempts": 200, "sent": true, "reachedTop": true, "lead": false }]);
+        invalid["date"] = json!("04/01/2024");
+        assert_eq!(check_record(invalid).unwrap_err().len(), 2);
+        let wrong_shape = json!({ "logType": "workout", "date": "2024-04-01", "exercises": "squats" });
+        assert!(check_record(wrong_shape).unwrap_err()[0].starts_with("not a valid workout log"));
+    }
+}
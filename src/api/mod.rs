@@ -1 +1,6 @@
-pub mod server;
\ No newline at end of file
+pub mod auth;
+pub mod events;
+pub mod import;
+pub mod openapi;
+pub mod server;
+pub mod teams;
@@ -0,0 +1,198 @@
+use crate::api::{auth, events, import, server, teams};
+use crate::climblib::calendar::{CalendarStats, DayActivity, HeatmapDay, RestGap, Streak, WeekDays};
+use crate::climblib::compare::{Comparison, ComparisonRow};
+use crate::climblib::lifting::{E1rm, LiftRecord, LiftSummary, PersonalRecord};
+use crate::climblib::models::{
+    BoulderGrade, ClimbEntry, ClimbMetricsEntry, ClimbStyle, ClimbingSession, ExerciseEntry, Goal, GoalStatus, GoalTarget, Grade,
+    RopeGrade, SessionComment, TeamInvitation, TeamMember, TeamMembership, TeamRole, WorkoutSession,
+};
+use crate::climblib::progression::{DisciplineProgression, Milestone, Progression, ProgressionPoint};
+use crate::climblib::pyramid::{Pyramid, PyramidRow};
+use crate::climblib::recommend::{DisciplineRecommendation, GradeRange, Recommendations};
+use crate::climblib::sends::{GradeComparison, GradeStats, SendRateReport};
+use crate::climblib::summary::{GradeSendRate, LiftVolume, MetricChange, Summary};
+use crate::climblib::team::{AthleteStats, TeamStats};
+use crate::climblib::trends::{Correlation, CorrelationWindow, MetricTrend, Regression, TrendReport};
+use crate::climblib::utils::Period;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+pub const OPENAPI_PATH: &str = "/api/openapi.json";
+pub const DOCS_PATH: &str = "/api/docs";
+
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "bearer",
+                SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+            );
+        }
+    }
+}
+
+// Every route needs a bearer token unless its path sets `security(())`
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Redpoint API"),
+    paths(
+        auth::register,
+        auth::login,
+        server::create_climb,
+        server::create_workout,
+        server::create_metrics,
+        server::get_climb,
+        server::create_climb_db_handler,
+        server::create_workout_db_handler,
+        server::create_metrics_db_handler,
+        import::import_logs,
+        server::get_pyramid,
+        server::get_progression,
+        server::get_send_rates,
+        server::get_trends,
+        server::get_calendar,
+        server::get_heatmap,
+        server::get_comparison,
+        server::get_goals,
+        server::create_goal,
+        server::get_recommendations,
+        server::get_lifts,
+        server::get_lift_history,
+        teams::get_own_sessions,
+        teams::set_session_visibility,
+        teams::get_comments,
+        teams::create_comment,
+        teams::get_teams,
+        teams::create_team,
+        teams::get_team_members,
+        teams::invite_team_member,
+        teams::get_team_stats,
+        teams::get_athlete_sessions,
+        teams::get_invitations,
+        teams::accept_invitation,
+        teams::decline_invitation,
+        events::stream_events,
+    ),
+    components(schemas(
+        ClimbStyle, RopeGrade, BoulderGrade, Grade, ClimbEntry, ClimbingSession, ClimbMetricsEntry,
+        ExerciseEntry, WorkoutSession, GoalTarget, Goal, GoalStatus, TeamRole, TeamMembership, TeamMember, TeamInvitation, SessionComment,
+        Period,
+        auth::Credentials, auth::TokenResponse,
+        teams::NewTeam, teams::NewInvitation, teams::NewComment, teams::Visibility,
+        events::LogEvent, events::ChangeKind, events::LogType,
+        import::ImportReport, import::ImportResult, import::ImportStatus,
+        Pyramid, PyramidRow,
+        Progression, DisciplineProgression, ProgressionPoint, Milestone,
+        SendRateReport, GradeStats, GradeComparison,
+        TrendReport, MetricTrend, Regression, Correlation, CorrelationWindow,
+        CalendarStats, WeekDays, Streak, RestGap, HeatmapDay, DayActivity,
+        Comparison, ComparisonRow, Summary, GradeSendRate, LiftVolume, MetricChange,
+        Recommendations, DisciplineRecommendation, GradeRange,
+        LiftSummary, LiftRecord, E1rm, PersonalRecord,
+        TeamStats, AthleteStats,
+    )),
+    modifiers(&BearerAuth),
+    security(("bearer" = [])),
+    tags(
+        (name = "auth", description = "Accounts and API tokens"),
+        (name = "logs", description = "Per-user JSON log files"),
+        (name = "db", description = "Database-backed logs"),
+        (name = "stats", description = "Climbing and training analytics"),
+        (name = "goals", description = "Goals and progress"),
+        (name = "lifts", description = "Lifting estimates and history"),
+        (name = "sessions", description = "Session visibility and comments"),
+        (name = "teams", description = "Teams, coaches and shared stats"),
+        (name = "events", description = "Live feed of log changes"),
+    )
+)]
+pub struct ApiDoc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn spec() -> Value {
+        serde_json::to_value(ApiDoc::openapi()).unwrap()
+    }
+
+    #[test]
+    fn test_spec_lists_routes_with_auth() {
+        let spec = spec();
+        let paths = spec["paths"].as_object().unwrap();
+        assert!(paths.contains_key("/api/stats/pyramid"));
+        assert!(paths.contains_key("/api/teams/{id}/athletes/{athlete_id}/sessions"));
+        assert_eq!(spec["security"][0]["bearer"], Value::Array(vec![]));
+        assert_eq!(paths["/api/auth/login"]["post"]["security"], serde_json::json!([{}]));
+        assert!(spec["components"]["securitySchemes"]["bearer"].is_object());
+    }
+
+    // Interfaces and string unions exported from the hand-written types.ts, keyed by name
+    fn ts_types() -> Vec<(String, Vec<(String, bool)>)> {
+        let source = include_str!("../../ts/climb-log/src/types/types.ts");
+        let mut types = Vec::new();
+        let mut current: Option<(String, Vec<(String, bool)>)> = None;
+        for line in source.lines().map(str::trim).filter(|line| !line.starts_with("//")) {
+            if let Some(name) = line.strip_prefix("export interface ") {
+                current = Some((name.trim_end_matches(" {").to_string(), Vec::new()));
+            } else if let Some(union) = line.strip_prefix("export type ").and_then(|t| t.split_once(" = \"")) {
+                let variants = union.1.trim_end_matches(';').split(" | ").map(|v| (v.trim_matches('"').to_string(), true));
+                types.push((union.0.to_string(), variants.collect()));
+            } else if line == "}" {
+                types.extend(current.take());
+            } else if let (Some((_, fields)), Some((field, _))) = (current.as_mut(), line.split_once(':')) {
+                fields.push((field.trim_end_matches('?').to_string(), !field.ends_with('?')));
+            }
+        }
+        types
+    }
+
+    // types.ts is written by hand, so every type in it must agree with the schema of the same name
+    #[test]
+    fn test_typescript_types_match_spec() {
+        let spec = spec();
+        let schemas = &spec["components"]["schemas"];
+        let types = ts_types();
+        assert!(types.iter().any(|(name, _)| name == "Credentials"));
+        for (name, fields) in types {
+            let schema = &schemas[&name];
+            assert!(schema.is_object(), "types.ts has {name} but the spec doesn't");
+            if let Some(variants) = schema["enum"].as_array() {
+                let mut expected: Vec<&str> = variants.iter().filter_map(Value::as_str).collect();
+                let mut actual: Vec<&str> = fields.iter().map(|(v, _)| v.as_str()).collect();
+                expected.sort();
+                actual.sort();
+                assert_eq!(actual, expected, "variants of {name}");
+                continue;
+            }
+            let properties = schema["properties"].as_object().unwrap();
+            let required: Vec<&str> = schema["required"].as_array().into_iter().flatten().filter_map(Value::as_str).collect();
+            let mut expected: Vec<(String, bool)> =
+                properties.keys().map(|field| (field.clone(), required.contains(&field.as_str()))).collect();
+            let mut actual = fields;
+            expected.sort();
+            actual.sort();
+            assert_eq!(actual, expected, "fields of {name}");
+        }
+    }
+
+    // Field names in the spec must match what serde actually reads and writes
+    #[test]
+    fn test_schemas_follow_serde_names() {
+        let spec = spec();
+        let schemas = &spec["components"]["schemas"];
+        let metrics = schemas["ClimbMetricsEntry"]["properties"].as_object().unwrap();
+        assert!(metrics.contains_key("fingerStrengthPercentBw"));
+        assert!(metrics.contains_key("maxPullupPercentBw"));
+        assert!(schemas["ClimbEntry"]["properties"]["reachedTop"].is_object());
+        assert!(schemas["ClimbingSession"]["properties"]["private"].is_object());
+        assert!(schemas["GoalTarget"]["oneOf"][1]["properties"]["percentBw"].is_object());
+
+        let entry: ClimbMetricsEntry = serde_json::from_str(
+            r#"{"date":"2024-04-01","fingerStrengthPercentBw":150,"maxPullupPercentBw":140}"#
+        ).unwrap();
+        assert_eq!(entry.finger_strength_percent_bw, Some(150.0));
+    }
+}
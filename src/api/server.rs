@@ -1,104 +1,756 @@
-use crate::climblib::models::{ClimbingSession, WorkoutSession, ClimbMetricsEntry};
-use crate::climblib::io::{save_log, log_index};
-use crate::db::queries::{insert_climb_db, insert_workout_db, insert_metrics_db};
+use crate::climblib::models::{ClimbingSession, WorkoutSession, ClimbMetricsEntry, Goal};
+use crate::climblib::goals::{check_goals, goal_status};
+use crate::climblib::recommend::build_recommendations;
+use crate::climblib::compare::{build_comparison, comparison_ranges};
+use crate::climblib::io::{save_log_in, log_index_in, load_logs_in, user_log_dir};
+use crate::climblib::lifting::{detect_prs, exercise_history, main_lifts};
+use crate::climblib::pyramid::build_pyramid;
+use crate::climblib::sends::build_send_rates;
+use crate::climblib::progression::{build_progression, DEFAULT_TOP_N, DEFAULT_WINDOW};
+use crate::climblib::trends::build_trends;
+use crate::climblib::calendar::{build_calendar_stats, daily_activity, year_heatmap};
+use crate::climblib::utils::{is_climb, is_workout, is_metrics, DateRange, Period};
+use crate::api::auth::{login, register, require_auth, AuthUser};
+use crate::api::events::{stream_events, ChangeKind, Events, LogEvent, LogType, EVENTS_PATH};
+use crate::api::import::{import_logs, IMPORT_PATH};
+use crate::api::openapi::{ApiDoc, DOCS_PATH, OPENAPI_PATH};
+use crate::api::teams::{
+    accept_invitation, create_comment, create_team, decline_invitation, get_athlete_sessions, get_comments,
+    get_invitations, get_own_sessions, get_team_members, get_team_stats, get_teams, invite_team_member,
+    set_session_visibility,
+};
+use crate::db::queries::{
+    insert_climb_db, insert_workout_db, insert_metrics_db,
+    fetch_climbing_sessions_db, fetch_workouts_db, fetch_metrics_db,
+    insert_goal_db, fetch_goals_db, update_goal_progress_db, fetch_saved_response_db, insert_saved_response_db,
+    SaveOutcome, SavedResponse,
+};
 use axum::{
-    extract::State,
-    http::StatusCode,
-    response::{IntoResponse},
+    extract::{Extension, Path, Query, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
+    middleware,
+    response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
-use serde_json::json;
+use chrono::{Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use validator::Validate;
 use sqlx::postgres::{PgPool, PgPoolOptions};
 use tracing::{info, error};
-use tower_http::cors::{CorsLayer, Any};
+use tower_http::cors::CorsLayer;
+use utoipa::{IntoParams, OpenApi};
+use uuid::Uuid;
+use utoipa_swagger_ui::SwaggerUi;
+
+fn today() -> NaiveDate {
+    Utc::now().date_naive()
+}
+
+// The logs goals are checked against, either the caller's log files or their database rows
+struct GoalLogs {
+    sessions: Vec<ClimbingSession>,
+    workouts: Vec<WorkoutSession>,
+    metrics: Vec<ClimbMetricsEntry>,
+}
+
+fn file_goal_logs(user: &AuthUser) -> GoalLogs {
+    let dir = user_log_dir(user.id);
+    GoalLogs {
+        sessions: load_logs_in(&dir, is_climb),
+        workouts: load_logs_in(&dir, is_workout),
+        metrics: load_logs_in(&dir, is_metrics),
+    }
+}
+
+async fn db_goal_logs(pool: &PgPool, user: &AuthUser) -> Result<GoalLogs, sqlx::Error> {
+    Ok(GoalLogs {
+        sessions: fetch_climbing_sessions_db(pool, user.id).await?,
+        workouts: fetch_workouts_db(pool, user.id).await?,
+        metrics: fetch_metrics_db(pool, user.id).await?,
+    })
+}
+
+// Goals are checked after every save against the logs from the same source. A failed check
+// is logged rather than failing the save.
+async fn refresh_goals(pool: &PgPool, user: &AuthUser, logs: &GoalLogs) -> Vec<Goal> {
+    let mut goals = match fetch_goals_db(pool, user.id).await {
+        Ok(goals) => goals,
+        Err(e) => {
+            error!("Error loading goals with {}", e);
+            return vec![];
+        }
+    };
+    let achieved = check_goals(&mut goals, &logs.sessions, &logs.workouts, &logs.metrics, today());
+    for goal in &goals {
+        if let Err(e) = update_goal_progress_db(pool, goal).await {
+            error!("Error updating goal {} with {}", goal.title, e);
+        }
+    }
+    for goal in &achieved {
+        info!("Goal achieved: {}", goal.title);
+    }
+    achieved
+}
+
+// Only new database rows are checked, as a replay changes nothing
+async fn refresh_db_goals(pool: &PgPool, user: &AuthUser, outcome: &SaveOutcome) -> Vec<Goal> {
+    if !matches!(outcome, SaveOutcome::Created(_)) {
+        return vec![];
+    }
+    match db_goal_logs(pool, user).await {
+        Ok(logs) => refresh_goals(pool, user, &logs).await,
+        Err(e) => {
+            error!("Error loading logs for goals with {}", e);
+            vec![]
+        }
+    }
+}
+
+// Saving over an existing file for the same date is an update
+fn file_change(dir: &std::path::Path, filename: &str) -> ChangeKind {
+    if dir.join(filename).exists() { ChangeKind::Updated } else { ChangeKind::Created }
+}
+
+pub const IDEMPOTENCY_KEY: &str = "idempotency-key";
+pub const IDEMPOTENT_REPLAYED: &str = "idempotent-replayed";
+
+// Retries that repeat an Idempotency-Key get the same log id. Keys are namespaced by
+// user, so two users picking the same key never collide.
+pub fn idempotency_id(headers: &HeaderMap, user: &AuthUser) -> Option<Uuid> {
+    let key = headers.get(IDEMPOTENCY_KEY)?.to_str().ok()?.trim();
+    (!key.is_empty()).then(|| Uuid::new_v5(&user.id, key.as_bytes()))
+}
+
+#[derive(Debug, PartialEq)]
+enum Replay {
+    New,
+    Answer(StatusCode, Value),
+    Conflict,
+}
+
+// Retries to the endpoint that first saved the id get its answer back. Any save that
+// reuses the id for a different log is refused, so an edited retry is never dropped silently.
+fn check_replay(saved: Option<&SavedResponse>, endpoint: &str, request: &Value) -> Replay {
+    match saved {
+        Some(saved) if saved.request != *request => Replay::Conflict,
+        Some(saved) if saved.endpoint == endpoint => {
+            Replay::Answer(StatusCode::from_u16(saved.status).unwrap_or(StatusCode::OK), saved.response.clone())
+        }
+        _ => Replay::New,
+    }
+}
+
+// A save that carries a log id, from the body or an Idempotency-Key
+pub struct SaveRequest {
+    endpoint: &'static str,
+    user_id: Uuid,
+    id: Option<Uuid>,
+    request: Value,
+}
+
+impl SaveRequest {
+    pub fn new(endpoint: &'static str, user: &AuthUser, id: Option<Uuid>, payload: &impl Serialize) -> Self {
+        let request = serde_json::to_value(payload).unwrap_or(Value::Null);
+        SaveRequest { endpoint, user_id: user.id, id, request }
+    }
+
+    // The response to send instead of saving, if this id was saved before
+    async fn replay(&self, pool: &PgPool) -> Option<Response> {
+        let id = self.id?;
+        let saved = match fetch_saved_response_db(pool, self.user_id, id).await {
+            Ok(saved) => saved,
+            Err(e) => {
+                error!("Error loading the saved response for {} with {}", id, e);
+                return Some((StatusCode::INTERNAL_SERVER_ERROR, "Could not check for an earlier save".to_string()).into_response());
+            }
+        };
+        match check_replay(saved.as_ref(), self.endpoint, &self.request) {
+            Replay::New => None,
+            Replay::Answer(status, body) => {
+                info!("Replayed {} for {}", self.endpoint, id);
+                Some(replayed((status, Json(body))))
+            }
+            Replay::Conflict => Some((StatusCode::CONFLICT, format!("Id {} was already saved with a different log", id)).into_response()),
+        }
+    }
 
-async fn create_climb(Json(session): Json<ClimbingSession>) -> Result<impl IntoResponse, (StatusCode, String)> {
+    // Kept when the save has an id, so a retry gets the same answer
+    async fn respond(self, pool: &PgPool, status: StatusCode, body: Value) -> Response {
+        if let Some(id) = self.id {
+            let saved = SavedResponse { endpoint: self.endpoint.to_string(), request: self.request, status: status.as_u16(), response: body.clone() };
+            if let Err(e) = insert_saved_response_db(pool, self.user_id, id, &saved).await {
+                error!("Error saving the response for {} with {}", id, e);
+            }
+        }
+        (status, Json(body)).into_response()
+    }
+}
+
+// Replays answer like the original save, and say so in a header
+fn replayed(response: impl IntoResponse) -> Response {
+    let mut response = response.into_response();
+    response.headers_mut().insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+    response
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/logs/climb",
+    tag = "logs",
+    request_body = ClimbingSession,
+    responses(
+        (status = 201, description = "Saved to the caller's log directory; includes goals achieved by this log. Retries with the same id get the first response back."),
+        (status = 409, description = "The id was already saved with a different log"),
+        (status = 500, description = "Could not save log"),
+    ),
+    params(("idempotency-key" = Option<String>, Header, description = "Gives the log a stable id when the body has none")),
+)]
+async fn create_climb(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Extension(events): Extension<Events>,
+    headers: HeaderMap,
+    Json(mut session): Json<ClimbingSession>,
+) -> Result<Response, (StatusCode, String)> {
     println!("{:?}", session);
+    session.id = session.id.or(idempotency_id(&headers, &user));
+    let save = SaveRequest::new("/api/logs/climb", &user, session.id, &session);
+    if let Some(response) = save.replay(&pool).await {
+        return Ok(response);
+    }
     let filename = "climb-".to_owned() + &session.date + ".json";
-    match save_log(&session, &filename){
+    let change = file_change(&user_log_dir(user.id), &filename);
+    match save_log_in(&user_log_dir(user.id), &session, &filename){
         Ok(_) => { info!("Saved {}", &filename);
-        return Ok((StatusCode::CREATED, Json(json!({ "status": "ok" }))));
+        events.publish(LogEvent::new(change, LogType::Climb, &user, &session.date).private(session.private));
+        let goals = refresh_goals(&pool, &user, &file_goal_logs(&user)).await;
+        Ok(save.respond(&pool, StatusCode::CREATED, json!({ "status": "ok", "goals": goals })).await)
         } ,
         Err(e) => { error!("Error saving {} with {}", &filename, e);
-        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Could not save log".to_string()));
+        Err((StatusCode::INTERNAL_SERVER_ERROR, "Could not save log".to_string()))
         },
-    };
+    }
 }
 
-async fn get_climb() -> Result<impl IntoResponse, (StatusCode, Json<Vec<String>>)> {
-    match log_index() {
+#[utoipa::path(
+    get,
+    path = "/api/logs/get-climb",
+    tag = "logs",
+    responses((status = 200, description = "Paths of the caller's log files", body = [String]))
+)]
+async fn get_climb(Extension(user): Extension<AuthUser>) -> Result<impl IntoResponse, (StatusCode, Json<Vec<String>>)> {
+    match log_index_in(&user_log_dir(user.id)) {
         Ok(paths) => { info!("got paths");
         let string_paths: Vec<String> = paths.into_iter()
         .filter_map(|p| p.to_str().map(|s| s.to_string()))
         .collect();
-        return Ok((StatusCode::OK, Json(string_paths)));
+        Ok((StatusCode::OK, Json(string_paths)))
         } ,
         Err(e) => { error!("Error finding logs with {}", e);
-        return Err((StatusCode::NOT_FOUND, Json(vec![] as Vec<String>)));
+        Err((StatusCode::NOT_FOUND, Json(vec![] as Vec<String>)))
         },
-    };
+    }
 }
 
-async fn create_workout(Json(session): Json<WorkoutSession>) -> Result<impl IntoResponse, (StatusCode, String)> {
+#[utoipa::path(
+    post,
+    path = "/api/logs/workout",
+    tag = "logs",
+    request_body = WorkoutSession,
+    responses(
+        (status = 201, description = "Saved to the caller's log directory; includes new PRs and goals achieved by this log. Retries with the same id get the first response back."),
+        (status = 409, description = "The id was already saved with a different log"),
+        (status = 500, description = "Could not save log"),
+    ),
+    params(("idempotency-key" = Option<String>, Header, description = "Gives the log a stable id when the body has none")),
+)]
+async fn create_workout(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Extension(events): Extension<Events>,
+    headers: HeaderMap,
+    Json(mut session): Json<WorkoutSession>,
+) -> Result<Response, (StatusCode, String)> {
+    session.id = session.id.or(idempotency_id(&headers, &user));
+    let save = SaveRequest::new("/api/logs/workout", &user, session.id, &session);
+    if let Some(response) = save.replay(&pool).await {
+        return Ok(response);
+    }
     let filename = "workout-".to_owned() + &session.date + ".json";
-    match save_log(&session, &filename){
+    let change = file_change(&user_log_dir(user.id), &filename);
+    // Saving overwrites the log for this date, so it is left out of the PR history
+    let history: Vec<WorkoutSession> = load_logs_in::<WorkoutSession>(&user_log_dir(user.id), is_workout)
+        .into_iter()
+        .filter(|w| w.date != session.date)
+        .collect();
+    let prs = detect_prs(&history, &session);
+    match save_log_in(&user_log_dir(user.id), &session, &filename){
         Ok(_) => { info!("Saved {}", &filename);
-        return Ok((StatusCode::CREATED, Json(json!({ "status": "ok" }))));
+        events.publish(LogEvent::new(change, LogType::Workout, &user, &session.date));
+        let goals = refresh_goals(&pool, &user, &file_goal_logs(&user)).await;
+        Ok(save.respond(&pool, StatusCode::CREATED, json!({ "status": "ok", "prs": prs, "goals": goals })).await)
         } ,
         Err(e) => { error!("Error saving {} with {}", &filename, e);
-        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Could not save log".to_string()));
+        Err((StatusCode::INTERNAL_SERVER_ERROR, "Could not save log".to_string()))
         },
-    };
+    }
 }
 
-async fn create_metrics(Json(session): Json<ClimbMetricsEntry>) -> Result<impl IntoResponse, (StatusCode, String)> {
+#[utoipa::path(
+    post,
+    path = "/api/logs/metrics",
+    tag = "logs",
+    request_body = ClimbMetricsEntry,
+    responses(
+        (status = 201, description = "Saved to the caller's log directory; includes goals achieved by this log. Retries with the same id get the first response back."),
+        (status = 409, description = "The id was already saved with a different log"),
+        (status = 500, description = "Could not save log"),
+    ),
+    params(("idempotency-key" = Option<String>, Header, description = "Gives the log a stable id when the body has none")),
+)]
+async fn create_metrics(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Extension(events): Extension<Events>,
+    headers: HeaderMap,
+    Json(mut session): Json<ClimbMetricsEntry>,
+) -> Result<Response, (StatusCode, String)> {
+    session.id = session.id.or(idempotency_id(&headers, &user));
+    let save = SaveRequest::new("/api/logs/metrics", &user, session.id, &session);
+    if let Some(response) = save.replay(&pool).await {
+        return Ok(response);
+    }
     let filename = "metrics-".to_owned() + &session.date + ".json";
-    match save_log(&session, &filename){
+    let change = file_change(&user_log_dir(user.id), &filename);
+    match save_log_in(&user_log_dir(user.id), &session, &filename){
         Ok(_) => { info!("Saved {}", &filename);
-        return Ok((StatusCode::CREATED, Json(json!({ "status": "ok" }))));
+        events.publish(LogEvent::new(change, LogType::Metrics, &user, &session.date));
+        let goals = refresh_goals(&pool, &user, &file_goal_logs(&user)).await;
+        Ok(save.respond(&pool, StatusCode::CREATED, json!({ "status": "ok", "goals": goals })).await)
         } ,
         Err(e) => { error!("Error saving {} with {}", &filename, e);
-        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Could not save log".to_string()));
+        Err((StatusCode::INTERNAL_SERVER_ERROR, "Could not save log".to_string()))
         },
-    };
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/goals",
+    tag = "goals",
+    request_body = Goal,
+    responses(
+        (status = 201, description = "Goal saved with its current progress", body = Goal),
+        (status = 422, description = "Invalid goal"),
+    )
+)]
+async fn create_goal(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Json(mut goal): Json<Goal>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    if let Err(e) = goal.validate() {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, e.to_string()));
+    }
+    goal.achieved_date = None;
+    let logs = file_goal_logs(&user);
+    check_goals(std::slice::from_mut(&mut goal), &logs.sessions, &logs.workouts, &logs.metrics, today());
+    match insert_goal_db(&pool, user.id, &goal).await {
+        Ok(id) => {
+            goal.id = Some(id);
+            Ok((StatusCode::CREATED, Json(goal)))
+        }
+        Err(e) => {
+            error!("Error saving goal {} with {}", goal.title, e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Could not save goal".to_string()))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/goals",
+    tag = "goals",
+    responses((status = 200, description = "The caller's goals", body = [Goal]))
+)]
+async fn get_goals(State(pool): State<PgPool>, Extension(user): Extension<AuthUser>) -> Result<impl IntoResponse, (StatusCode, String)> {
+    fetch_goals_db(&pool, user.id).await.map(|mut goals| {
+        for goal in &mut goals {
+            goal.status = goal_status(goal, today());
+        }
+        Json(goals)
+    }).map_err(|e| {
+        error!("Error loading goals with {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Could not load goals".to_string())
+    })
+}
+
+// Only new logs are announced. A row saved before its response was kept answers like a
+// fresh insert.
+async fn db_save_response(pool: &PgPool, save: SaveRequest, outcome: SaveOutcome, event: LogEvent, events: &Events, body: Value) -> Response {
+    match outcome {
+        SaveOutcome::Created(id) => {
+            events.publish(event.session(id));
+            save.respond(pool, StatusCode::OK, body).await
+        }
+        SaveOutcome::Replayed(_) => replayed((StatusCode::OK, Json(body))),
+        SaveOutcome::Taken(id) => (StatusCode::CONFLICT, format!("Id {} belongs to another log", id)).into_response(),
+    }
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/db/climb",
+    tag = "db",
+    request_body = ClimbingSession,
+    params(("idempotency-key" = Option<String>, Header, description = "Gives the session a stable id when the body has none")),
+    responses(
+        (status = 200, description = "Climb session inserted, or already inserted with this id; includes goals achieved by this session"),
+        (status = 409, description = "The id belongs to another user's log, or was saved with a different one"),
+    )
+)]
 pub async fn create_climb_db_handler(
     State(pool): State<PgPool>,
-    Json(payload): Json<ClimbingSession>,
-) -> (StatusCode, String) {
-    if let Err(e) = insert_climb_db(&pool, payload).await {
-        return (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to insert climb: {}", e));
+    Extension(user): Extension<AuthUser>,
+    Extension(events): Extension<Events>,
+    headers: HeaderMap,
+    Json(mut payload): Json<ClimbingSession>,
+) -> Response {
+    payload.id = payload.id.or(idempotency_id(&headers, &user));
+    let save = SaveRequest::new("/api/db/climb", &user, payload.id, &payload);
+    if let Some(response) = save.replay(&pool).await {
+        return response;
+    }
+    let event = LogEvent::new(ChangeKind::Created, LogType::Climb, &user, &payload.date).private(payload.private);
+    match insert_climb_db(&pool, user.id, payload).await {
+        Ok(outcome) => {
+            let goals = refresh_db_goals(&pool, &user, &outcome).await;
+            let body = json!({ "status": "Climb session inserted into database", "goals": goals });
+            db_save_response(&pool, save, outcome, event, &events, body).await
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to insert climb: {}", e)).into_response(),
     }
-    (StatusCode::OK, "Climb session inserted into database".to_string())
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/db/workout",
+    tag = "db",
+    request_body = WorkoutSession,
+    params(("idempotency-key" = Option<String>, Header, description = "Gives the session a stable id when the body has none")),
+    responses(
+        (status = 200, description = "Workout inserted, or already inserted with this id; includes new PRs and goals achieved by this workout"),
+        (status = 409, description = "The id belongs to another user's log, or was saved with a different one"),
+    )
+)]
 pub async fn create_workout_db_handler(
     State(pool): State<PgPool>,
-    Json(payload): Json<WorkoutSession>,
-) -> (StatusCode, String) {
-    if let Err(e) = insert_workout_db(&pool, payload).await {
-        return (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to insert workout: {}", e));
+    Extension(user): Extension<AuthUser>,
+    Extension(events): Extension<Events>,
+    headers: HeaderMap,
+    Json(mut payload): Json<WorkoutSession>,
+) -> Response {
+    payload.id = payload.id.or(idempotency_id(&headers, &user));
+    let save = SaveRequest::new("/api/db/workout", &user, payload.id, &payload);
+    if let Some(response) = save.replay(&pool).await {
+        return response;
+    }
+    // A replayed workout is left out of its own history so it reports the same PRs again
+    let prs = match fetch_workouts_db(&pool, user.id).await {
+        Ok(history) => {
+            let history: Vec<WorkoutSession> = history.into_iter().filter(|w| payload.id.is_none() || w.id != payload.id).collect();
+            detect_prs(&history, &payload)
+        }
+        Err(e) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": format!("Failed to load workouts: {}", e) }))).into_response();
+        }
+    };
+    let event = LogEvent::new(ChangeKind::Created, LogType::Workout, &user, &payload.date);
+    match insert_workout_db(&pool, user.id, payload).await {
+        Ok(outcome) => {
+            let goals = refresh_db_goals(&pool, &user, &outcome).await;
+            let body = json!({ "status": "Workout session inserted into database", "prs": prs, "goals": goals });
+            db_save_response(&pool, save, outcome, event, &events, body).await
+        }
+        Err(e) => {
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": format!("Failed to insert workout: {}", e) }))).into_response()
+        }
     }
-    (StatusCode::OK, "Workout session inserted into database".to_string())
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/db/metrics",
+    tag = "db",
+    request_body = ClimbMetricsEntry,
+    params(("idempotency-key" = Option<String>, Header, description = "Gives the entry a stable id when the body has none")),
+    responses(
+        (status = 200, description = "Climb metrics inserted, or already inserted with this id; includes goals achieved by these metrics"),
+        (status = 409, description = "The id belongs to another user's log, or was saved with a different one"),
+    )
+)]
 pub async fn create_metrics_db_handler(
     State(pool): State<PgPool>,
-    Json(payload): Json<ClimbMetricsEntry>,
-) -> (StatusCode, String) {
-    if let Err(e) = insert_metrics_db(&pool, payload).await {
-        return (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to insert metrics: {}", e));
+    Extension(user): Extension<AuthUser>,
+    Extension(events): Extension<Events>,
+    headers: HeaderMap,
+    Json(mut payload): Json<ClimbMetricsEntry>,
+) -> Response {
+    payload.id = payload.id.or(idempotency_id(&headers, &user));
+    let save = SaveRequest::new("/api/db/metrics", &user, payload.id, &payload);
+    if let Some(response) = save.replay(&pool).await {
+        return response;
+    }
+    let event = LogEvent::new(ChangeKind::Created, LogType::Metrics, &user, &payload.date);
+    match insert_metrics_db(&pool, user.id, payload).await {
+        Ok(outcome) => {
+            let goals = refresh_db_goals(&pool, &user, &outcome).await;
+            let body = json!({ "status": "Climb metrics inserted into database", "goals": goals });
+            db_save_response(&pool, save, outcome, event, &events, body).await
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to insert metrics: {}", e)).into_response(),
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StatsParams {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub source: Option<String>,
+    pub period: Option<Period>,
+    pub year: Option<i32>,
+    pub against_from: Option<NaiveDate>,
+    pub against_to: Option<NaiveDate>,
+}
+
+impl StatsParams {
+    fn range(&self) -> DateRange {
+        DateRange::new(self.from, self.to)
+    }
+
+    fn use_db(&self) -> bool {
+        self.source.as_deref() == Some("db")
+    }
+}
+
+async fn load_climbing_sessions(pool: &PgPool, user: &AuthUser, params: &StatsParams) -> Result<Vec<ClimbingSession>, (StatusCode, String)> {
+    if params.use_db() {
+        fetch_climbing_sessions_db(pool, user.id).await.map_err(|e| {
+            error!("Error loading climbing sessions with {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Could not load climbing sessions".to_string())
+        })
+    } else {
+        Ok(load_logs_in(&user_log_dir(user.id), is_climb))
+    }
+}
+
+async fn load_workouts(pool: &PgPool, user: &AuthUser, params: &StatsParams) -> Result<Vec<WorkoutSession>, (StatusCode, String)> {
+    if params.use_db() {
+        fetch_workouts_db(pool, user.id).await.map_err(|e| {
+            error!("Error loading workouts with {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Could not load workouts".to_string())
+        })
+    } else {
+        Ok(load_logs_in(&user_log_dir(user.id), is_workout))
     }
-    (StatusCode::OK, "Climb metrics inserted into database".to_string())
 }
 
-pub async fn start_server(db_connection_str: &str) {
+async fn load_metrics(pool: &PgPool, user: &AuthUser, params: &StatsParams) -> Result<Vec<ClimbMetricsEntry>, (StatusCode, String)> {
+    if params.use_db() {
+        fetch_metrics_db(pool, user.id).await.map_err(|e| {
+            error!("Error loading metrics with {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Could not load metrics".to_string())
+        })
+    } else {
+        Ok(load_logs_in(&user_log_dir(user.id), is_metrics))
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/stats/pyramid",
+    tag = "stats",
+    params(StatsParams),
+    responses((status = 200, description = "Sends, attempts and flashes per grade", body = Pyramid))
+)]
+async fn get_pyramid(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Query(params): Query<StatsParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let sessions = load_climbing_sessions(&pool, &user, &params).await?;
+    Ok(Json(build_pyramid(&sessions, &params.range())))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/stats/progression",
+    tag = "stats",
+    params(StatsParams),
+    responses((status = 200, description = "Rolling top grades and first sends per grade", body = Progression))
+)]
+async fn get_progression(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Query(params): Query<StatsParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let sessions = load_climbing_sessions(&pool, &user, &params).await?;
+    let period = params.period.unwrap_or(Period::Month);
+    Ok(Json(build_progression(&sessions, &params.range(), period, DEFAULT_WINDOW, DEFAULT_TOP_N)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/stats/send-rates",
+    tag = "stats",
+    params(StatsParams),
+    responses((status = 200, description = "Send rates per grade, current against previous period", body = SendRateReport))
+)]
+async fn get_send_rates(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Query(params): Query<StatsParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let sessions = load_climbing_sessions(&pool, &user, &params).await?;
+    Ok(Json(build_send_rates(&sessions, &params.range(), chrono::Utc::now().date_naive())))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/stats/trends",
+    tag = "stats",
+    params(StatsParams),
+    responses((status = 200, description = "Metric regressions and their correlation with climbing", body = TrendReport))
+)]
+async fn get_trends(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Query(params): Query<StatsParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let metrics = load_metrics(&pool, &user, &params).await?;
+    let sessions = load_climbing_sessions(&pool, &user, &params).await?;
+    let period = params.period.unwrap_or(Period::Month);
+    Ok(Json(build_trends(&metrics, &sessions, &params.range(), period)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/stats/calendar",
+    tag = "stats",
+    params(StatsParams),
+    responses((status = 200, description = "Days per week, streaks and rest gaps", body = CalendarStats))
+)]
+async fn get_calendar(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Query(params): Query<StatsParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let sessions = load_climbing_sessions(&pool, &user, &params).await?;
+    let workouts = load_workouts(&pool, &user, &params).await?;
+    Ok(Json(build_calendar_stats(&sessions, &workouts, &params.range())))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/stats/heatmap",
+    tag = "stats",
+    params(StatsParams),
+    responses((status = 200, description = "Activity for every day of the year", body = [HeatmapDay]))
+)]
+async fn get_heatmap(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Query(params): Query<StatsParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let sessions = load_climbing_sessions(&pool, &user, &params).await?;
+    let workouts = load_workouts(&pool, &user, &params).await?;
+    let metrics = load_metrics(&pool, &user, &params).await?;
+    let year = params.year.unwrap_or_else(|| chrono::Utc::now().year());
+    Ok(Json(year_heatmap(&daily_activity(&sessions, &workouts, &metrics), year)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/stats/compare",
+    tag = "stats",
+    params(StatsParams),
+    responses((status = 200, description = "Summaries of two periods side by side", body = Comparison))
+)]
+async fn get_comparison(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Query(params): Query<StatsParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let sessions = load_climbing_sessions(&pool, &user, &params).await?;
+    let workouts = load_workouts(&pool, &user, &params).await?;
+    let metrics = load_metrics(&pool, &user, &params).await?;
+    let against = DateRange::new(params.against_from, params.against_to);
+    let (first, second) = comparison_ranges(&params.range(), &against, chrono::Utc::now().date_naive());
+    Ok(Json(build_comparison(&sessions, &workouts, &metrics, &first, &second)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/recommendations",
+    tag = "stats",
+    params(StatsParams),
+    responses((status = 200, description = "Project and volume grade ranges", body = Recommendations))
+)]
+async fn get_recommendations(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Query(params): Query<StatsParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let sessions = load_climbing_sessions(&pool, &user, &params).await?;
+    let today = params.to.unwrap_or_else(|| chrono::Utc::now().date_naive());
+    Ok(Json(build_recommendations(&sessions, today)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/lifts",
+    tag = "lifts",
+    params(StatsParams),
+    responses((status = 200, description = "Best estimated 1RM per main lift", body = [LiftSummary]))
+)]
+async fn get_lifts(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Query(params): Query<StatsParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let workouts = load_workouts(&pool, &user, &params).await?;
+    Ok(Json(main_lifts(&workouts)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/lifts/{name}/history",
+    tag = "lifts",
+    params(("name" = String, Path, description = "Exercise name"), StatsParams),
+    responses((status = 200, description = "Estimated 1RM per workout", body = [LiftRecord]))
+)]
+async fn get_lift_history(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Path(name): Path<String>,
+    Query(params): Query<StatsParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let workouts: Vec<WorkoutSession> = load_workouts(&pool, &user, &params)
+        .await?
+        .into_iter()
+        .filter(|w| params.range().contains(&w.date))
+        .collect();
+    Ok(Json(exercise_history(&workouts, &name)))
+}
+
+pub async fn start_server(db_connection_str: &str, cors_origin: &str) {
     let pool = PgPoolOptions::new()
     .max_connections(5)
     .acquire_timeout(tokio::time::Duration::from_secs(3))
-    .connect(&db_connection_str)
+    .connect(db_connection_str)
     .await
     .expect("can't connect to database");
 
@@ -107,12 +759,12 @@ pub async fn start_server(db_connection_str: &str) {
     }
 
     let cors = CorsLayer::new()
-    .allow_origin(Any)
-    .allow_methods([axum::http::Method::POST])
-    .allow_methods([axum::http::Method::GET])
-    .allow_headers([axum::http::header::CONTENT_TYPE]);
+    .allow_origin(cors_origin.parse::<HeaderValue>().expect("invalid CORS origin"))
+    .allow_methods([Method::GET, Method::POST])
+    .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION, HeaderName::from_static(IDEMPOTENCY_KEY)])
+    .expose_headers([HeaderName::from_static(IDEMPOTENT_REPLAYED)]);
 
-    let app = Router::new()
+    let protected = Router::new()
     .route("/api/logs/climb", post(create_climb))
     .route("/api/logs/workout", post(create_workout))
     .route("/api/logs/metrics", post(create_metrics))
@@ -120,6 +772,38 @@ pub async fn start_server(db_connection_str: &str) {
     .route("/api/db/climb", post(create_climb_db_handler))
     .route("/api/db/workout", post(create_workout_db_handler))
     .route("/api/db/metrics", post(create_metrics_db_handler))
+    .route(IMPORT_PATH, post(import_logs))
+    .route("/api/stats/pyramid", get(get_pyramid))
+    .route("/api/stats/progression", get(get_progression))
+    .route("/api/stats/send-rates", get(get_send_rates))
+    .route("/api/stats/trends", get(get_trends))
+    .route("/api/stats/calendar", get(get_calendar))
+    .route("/api/stats/heatmap", get(get_heatmap))
+    .route("/api/stats/compare", get(get_comparison))
+    .route("/api/goals", get(get_goals).post(create_goal))
+    .route("/api/recommendations", get(get_recommendations))
+    .route("/api/lifts", get(get_lifts))
+    .route("/api/lifts/:name/history", get(get_lift_history))
+    .route("/api/sessions", get(get_own_sessions))
+    .route("/api/sessions/:id/visibility", post(set_session_visibility))
+    .route("/api/sessions/:id/comments", get(get_comments).post(create_comment))
+    .route("/api/teams", get(get_teams).post(create_team))
+    .route("/api/teams/:id/members", get(get_team_members))
+    .route("/api/teams/:id/invitations", post(invite_team_member))
+    .route("/api/teams/:id/stats", get(get_team_stats))
+    .route("/api/teams/:id/athletes/:athlete_id/sessions", get(get_athlete_sessions))
+    .route("/api/invitations", get(get_invitations))
+    .route("/api/invitations/:id/accept", post(accept_invitation))
+    .route("/api/invitations/:id/decline", post(decline_invitation))
+    .route(EVENTS_PATH, get(stream_events))
+    .route_layer(middleware::from_fn_with_state(pool.clone(), require_auth));
+
+    let app = Router::new()
+    .route("/api/auth/register", post(register))
+    .route("/api/auth/login", post(login))
+    .merge(SwaggerUi::new(DOCS_PATH).url(OPENAPI_PATH, ApiDoc::openapi()))
+    .merge(protected)
+    .layer(Extension(Events::default()))
     .layer(cors)
     .with_state(pool.clone());
 
@@ -127,4 +811,49 @@ pub async fn start_server(db_connection_str: &str) {
     .serve(app.into_make_service())
     .await
     .unwrap();
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(name: &str) -> AuthUser {
+        AuthUser { id: Uuid::new_v4(), username: name.to_string() }
+    }
+
+    #[test]
+    fn test_idempotency_id() {
+        let (alice, bob) = (user("alice"), user("bob"));
+        let mut headers = HeaderMap::new();
+        assert_eq!(idempotency_id(&headers, &alice), None);
+        headers.insert(IDEMPOTENCY_KEY, HeaderValue::from_static(" "));
+        assert_eq!(idempotency_id(&headers, &alice), None);
+
+        headers.insert(IDEMPOTENCY_KEY, HeaderValue::from_static("queue-42"));
+        let id = idempotency_id(&headers, &alice).unwrap();
+        assert_eq!(idempotency_id(&headers, &alice), Some(id));
+        assert_ne!(idempotency_id(&headers, &bob), Some(id));
+    }
+
+    #[test]
+    fn test_check_replay() {
+        let request = json!({ "id": Uuid::nil(), "date": "2024-04-01", "exercises": [] });
+        let saved = SavedResponse {
+            endpoint: "/api/logs/workout".to_string(),
+            request: request.clone(),
+            status: 201,
+            response: json!({ "status": "ok", "prs": [], "goals": [{ "title": "Log a workout" }] }),
+        };
+        assert_eq!(check_replay(None, "/api/logs/workout", &request), Replay::New);
+        assert_eq!(
+            check_replay(Some(&saved), "/api/logs/workout", &request),
+            Replay::Answer(StatusCode::CREATED, saved.response.clone())
+        );
+        assert_eq!(check_replay(Some(&saved), "/api/db/workout", &request), Replay::New);
+
+        let mut edited = request;
+        edited["date"] = json!("2024-04-02");
+        assert_eq!(check_replay(Some(&saved), "/api/logs/workout", &edited), Replay::Conflict);
+        assert_eq!(check_replay(Some(&saved), "/api/db/workout", &edited), Replay::Conflict);
+    }
+}
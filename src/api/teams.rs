@@ -0,0 +1,462 @@
+use crate::api::auth::AuthUser;
+use crate::api::events::{ChangeKind, Events, LogEvent, LogType};
+use crate::climblib::io::{load_logs_in, user_log_dir};
+use crate::climblib::models::{ClimbingSession, TeamMembership, TeamRole};
+use crate::climblib::team::{build_team_stats, AthleteLogs};
+use crate::climblib::utils::{is_climb, is_metrics, is_workout, DateRange};
+use crate::db::queries::{
+    accept_invitation_db, decline_invitation_db, fetch_climbing_sessions_db, fetch_comments_db, fetch_invitations_db,
+    fetch_metrics_db, fetch_session_owner_db, fetch_team_members_db, fetch_team_role_db, fetch_teams_db,
+    fetch_workouts_db, insert_comment_db, insert_team_db, insert_team_invitation_db, is_coach_of_db,
+    set_session_private_db, InviteOutcome,
+};
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgPool;
+use tracing::{error, info};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use validator::Validate;
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct NewTeam {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct NewInvitation {
+    #[validate(length(min = 3, max = 50))]
+    pub username: String,
+    pub role: TeamRole,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct NewComment {
+    #[validate(length(min = 1, max = 1000))]
+    pub body: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Visibility {
+    pub private: bool,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TeamStatsParams {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AthleteSessionsParams {
+    pub source: Option<String>,
+}
+
+// Like the athletes' own stats, coaches see their log files unless source=db
+fn use_db(source: &Option<String>) -> bool {
+    source.as_deref() == Some("db")
+}
+
+// Only coaches manage a team's roster and see its athletes' logs
+pub fn can_manage_team(role: Option<TeamRole>) -> bool {
+    role == Some(TeamRole::Coach)
+}
+
+// Any member can see who else is on the team
+pub fn can_view_team(role: Option<TeamRole>) -> bool {
+    role.is_some()
+}
+
+// Owners always see their own sessions; coaches see an athlete's sessions unless marked private.
+// Commenting follows the same rule as reading.
+pub fn can_view_session(viewer: Uuid, owner: Uuid, private: bool, coaches_owner: bool) -> bool {
+    viewer == owner || (coaches_owner && !private)
+}
+
+fn db_error(what: &str, e: sqlx::Error) -> (StatusCode, String) {
+    error!("Error with {} with {}", what, e);
+    (StatusCode::INTERNAL_SERVER_ERROR, format!("Could not {}", what))
+}
+
+async fn team_role(pool: &PgPool, team_id: Uuid, user: &AuthUser) -> Result<Option<TeamRole>, (StatusCode, String)> {
+    fetch_team_role_db(pool, team_id, user.id).await.map_err(|e| db_error("load team role", e))
+}
+
+async fn require_coach(pool: &PgPool, team_id: Uuid, user: &AuthUser) -> Result<(), (StatusCode, String)> {
+    if can_manage_team(team_role(pool, team_id, user).await?) {
+        Ok(())
+    } else {
+        Err((StatusCode::FORBIDDEN, "Only coaches of this team can do that".to_string()))
+    }
+}
+
+// Sessions the user isn't allowed to see are reported as missing rather than forbidden
+async fn require_session_access(pool: &PgPool, session_id: Uuid, user: &AuthUser) -> Result<(), (StatusCode, String)> {
+    let not_found = || (StatusCode::NOT_FOUND, "Session not found".to_string());
+    let (owner, private) = fetch_session_owner_db(pool, session_id)
+        .await
+        .map_err(|e| db_error("load session", e))?
+        .ok_or_else(not_found)?;
+    let coaches_owner = owner != user.id
+        && is_coach_of_db(pool, user.id, owner).await.map_err(|e| db_error("check team roles", e))?;
+    if can_view_session(user.id, owner, private, coaches_owner) {
+        Ok(())
+    } else {
+        Err(not_found())
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/teams",
+    tag = "teams",
+    request_body = NewTeam,
+    responses((status = 201, description = "Team created with the caller as coach", body = TeamMembership))
+)]
+pub async fn create_team(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Json(team): Json<NewTeam>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    if let Err(e) = team.validate() {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, e.to_string()));
+    }
+    let team_id = insert_team_db(&pool, &team.name, user.id).await.map_err(|e| db_error("create team", e))?;
+    info!("{} created team {}", user.username, team.name);
+    Ok((StatusCode::CREATED, Json(TeamMembership { team_id, name: team.name, role: TeamRole::Coach })))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/teams",
+    tag = "teams",
+    responses((status = 200, description = "Teams the caller belongs to", body = [TeamMembership]))
+)]
+pub async fn get_teams(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    fetch_teams_db(&pool, user.id).await.map(Json).map_err(|e| db_error("load teams", e))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/teams/{id}/members",
+    tag = "teams",
+    params(("id" = Uuid, Path, description = "Team id")),
+    responses(
+        (status = 200, description = "Team roster", body = [TeamMember]),
+        (status = 404, description = "Caller is not on this team"),
+    )
+)]
+pub async fn get_team_members(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Path(team_id): Path<Uuid>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    if !can_view_team(team_role(&pool, team_id, &user).await?) {
+        return Err((StatusCode::NOT_FOUND, "Team not found".to_string()));
+    }
+    fetch_team_members_db(&pool, team_id).await.map(Json).map_err(|e| db_error("load team members", e))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/teams/{id}/invitations",
+    tag = "teams",
+    params(("id" = Uuid, Path, description = "Team id")),
+    request_body = NewInvitation,
+    responses(
+        (status = 201, description = "Invitation sent, or the pending one updated", body = TeamInvitation),
+        (status = 403, description = "Caller is not a coach of this team"),
+        (status = 404, description = "No such user"),
+        (status = 409, description = "Already on the team"),
+    )
+)]
+pub async fn invite_team_member(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Path(team_id): Path<Uuid>,
+    Json(invitation): Json<NewInvitation>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    if let Err(e) = invitation.validate() {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, e.to_string()));
+    }
+    require_coach(&pool, team_id, &user).await?;
+    match insert_team_invitation_db(&pool, team_id, &invitation.username, invitation.role, user.id).await {
+        Ok(InviteOutcome::Invited(sent)) => {
+            info!("{} invited {} to team {} as {}", user.username, invitation.username, team_id, invitation.role);
+            Ok((StatusCode::CREATED, Json(sent)))
+        }
+        Ok(InviteOutcome::NoSuchUser) => Err((StatusCode::NOT_FOUND, format!("No user named {}", invitation.username))),
+        Ok(InviteOutcome::AlreadyMember) => {
+            Err((StatusCode::CONFLICT, format!("{} is already on this team", invitation.username)))
+        }
+        Err(e) => Err(db_error("invite team member", e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/invitations",
+    tag = "teams",
+    responses((status = 200, description = "Team invitations waiting for the caller", body = [TeamInvitation]))
+)]
+pub async fn get_invitations(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    fetch_invitations_db(&pool, user.id).await.map(Json).map_err(|e| db_error("load invitations", e))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/invitations/{id}/accept",
+    tag = "teams",
+    params(("id" = Uuid, Path, description = "Invitation id")),
+    responses(
+        (status = 201, description = "Joined the team", body = TeamMembership),
+        (status = 404, description = "No such invitation for the caller"),
+    )
+)]
+pub async fn accept_invitation(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Path(invitation_id): Path<Uuid>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    match accept_invitation_db(&pool, invitation_id, user.id).await {
+        Ok(Some(membership)) => {
+            info!("{} joined team {} as {}", user.username, membership.team_id, membership.role);
+            Ok((StatusCode::CREATED, Json(membership)))
+        }
+        Ok(None) => Err((StatusCode::NOT_FOUND, "Invitation not found".to_string())),
+        Err(e) => Err(db_error("accept invitation", e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/invitations/{id}/decline",
+    tag = "teams",
+    params(("id" = Uuid, Path, description = "Invitation id")),
+    responses(
+        (status = 204, description = "Invitation declined"),
+        (status = 404, description = "No such invitation for the caller"),
+    )
+)]
+pub async fn decline_invitation(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Path(invitation_id): Path<Uuid>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    match decline_invitation_db(&pool, invitation_id, user.id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err((StatusCode::NOT_FOUND, "Invitation not found".to_string())),
+        Err(e) => Err(db_error("decline invitation", e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/teams/{id}/stats",
+    tag = "teams",
+    params(("id" = Uuid, Path, description = "Team id"), TeamStatsParams),
+    responses(
+        (status = 200, description = "Per-athlete and team summaries, excluding private sessions", body = TeamStats),
+        (status = 403, description = "Caller is not a coach of this team"),
+    )
+)]
+pub async fn get_team_stats(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Path(team_id): Path<Uuid>,
+    Query(params): Query<TeamStatsParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    require_coach(&pool, team_id, &user).await?;
+    let members = fetch_team_members_db(&pool, team_id).await.map_err(|e| db_error("load team members", e))?;
+    let mut athletes = Vec::new();
+    for member in members.into_iter().filter(|m| m.role == TeamRole::Athlete) {
+        let logs = if use_db(&params.source) {
+            let sessions = fetch_climbing_sessions_db(&pool, member.user_id)
+                .await
+                .map_err(|e| db_error("load climbing sessions", e))?;
+            let workouts = fetch_workouts_db(&pool, member.user_id).await.map_err(|e| db_error("load workouts", e))?;
+            let metrics = fetch_metrics_db(&pool, member.user_id).await.map_err(|e| db_error("load metrics", e))?;
+            AthleteLogs { member, sessions, workouts, metrics }
+        } else {
+            let dir = user_log_dir(member.user_id);
+            AthleteLogs {
+                member,
+                sessions: load_logs_in(&dir, is_climb),
+                workouts: load_logs_in(&dir, is_workout),
+                metrics: load_logs_in(&dir, is_metrics),
+            }
+        };
+        athletes.push(logs);
+    }
+    Ok(Json(build_team_stats(athletes, &DateRange::new(params.from, params.to))))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/teams/{id}/athletes/{athlete_id}/sessions",
+    tag = "teams",
+    params(
+        ("id" = Uuid, Path, description = "Team id"),
+        ("athlete_id" = Uuid, Path, description = "Athlete user id"),
+        AthleteSessionsParams,
+    ),
+    responses(
+        (status = 200, description = "The athlete's non-private climbing sessions", body = [ClimbingSession]),
+        (status = 403, description = "Caller is not a coach of this team"),
+        (status = 404, description = "Not an athlete on this team"),
+    )
+)]
+pub async fn get_athlete_sessions(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Path((team_id, athlete_id)): Path<(Uuid, Uuid)>,
+    Query(params): Query<AthleteSessionsParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    require_coach(&pool, team_id, &user).await?;
+    let role = fetch_team_role_db(&pool, team_id, athlete_id).await.map_err(|e| db_error("load team role", e))?;
+    if role != Some(TeamRole::Athlete) {
+        return Err((StatusCode::NOT_FOUND, "Athlete not found".to_string()));
+    }
+    let sessions = if use_db(&params.source) {
+        fetch_climbing_sessions_db(&pool, athlete_id)
+            .await
+            .map_err(|e| db_error("load climbing sessions", e))?
+    } else {
+        load_logs_in(&user_log_dir(athlete_id), is_climb)
+    };
+    let shared: Vec<ClimbingSession> = sessions
+        .into_iter()
+        .filter(|s| can_view_session(user.id, athlete_id, s.private, true))
+        .collect();
+    Ok(Json(shared))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sessions",
+    tag = "sessions",
+    responses((status = 200, description = "The caller's climbing sessions with their ids", body = [ClimbingSession]))
+)]
+pub async fn get_own_sessions(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let sessions = fetch_climbing_sessions_db(&pool, user.id)
+        .await
+        .map_err(|e| db_error("load climbing sessions", e))?;
+    Ok(Json(sessions))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/sessions/{id}/visibility",
+    tag = "sessions",
+    params(("id" = Uuid, Path, description = "Climbing session id")),
+    request_body = Visibility,
+    responses(
+        (status = 200, description = "Visibility updated", body = Visibility),
+        (status = 404, description = "Session not found"),
+    )
+)]
+pub async fn set_session_visibility(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Extension(events): Extension<Events>,
+    Path(session_id): Path<Uuid>,
+    Json(visibility): Json<Visibility>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    match set_session_private_db(&pool, session_id, user.id, visibility.private).await {
+        Ok(Some(date)) => {
+            let event = LogEvent::new(ChangeKind::Visibility, LogType::Climb, &user, &date.to_string());
+            events.publish(event.session(session_id).private(visibility.private));
+            Ok(Json(visibility))
+        }
+        Ok(None) => Err((StatusCode::NOT_FOUND, "Session not found".to_string())),
+        Err(e) => Err(db_error("update session", e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sessions/{id}/comments",
+    tag = "sessions",
+    params(("id" = Uuid, Path, description = "Climbing session id")),
+    responses(
+        (status = 200, description = "Comments, oldest first", body = [SessionComment]),
+        (status = 404, description = "Session not found or not visible"),
+    )
+)]
+pub async fn get_comments(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Path(session_id): Path<Uuid>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    require_session_access(&pool, session_id, &user).await?;
+    fetch_comments_db(&pool, session_id).await.map(Json).map_err(|e| db_error("load comments", e))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/sessions/{id}/comments",
+    tag = "sessions",
+    params(("id" = Uuid, Path, description = "Climbing session id")),
+    request_body = NewComment,
+    responses(
+        (status = 201, description = "Comment saved", body = SessionComment),
+        (status = 404, description = "Session not found or not visible"),
+    )
+)]
+pub async fn create_comment(
+    State(pool): State<PgPool>,
+    Extension(user): Extension<AuthUser>,
+    Path(session_id): Path<Uuid>,
+    Json(comment): Json<NewComment>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    if let Err(e) = comment.validate() {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, e.to_string()));
+    }
+    require_session_access(&pool, session_id, &user).await?;
+    let comment = insert_comment_db(&pool, session_id, user.id, &comment.body)
+        .await
+        .map_err(|e| db_error("save comment", e))?;
+    Ok((StatusCode::CREATED, Json(comment)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_team_permissions() {
+        assert!(can_manage_team(Some(TeamRole::Coach)));
+        assert!(!can_manage_team(Some(TeamRole::Athlete)));
+        assert!(!can_manage_team(None));
+        assert!(can_view_team(Some(TeamRole::Athlete)));
+        assert!(!can_view_team(None));
+    }
+
+    #[test]
+    fn test_session_visibility() {
+        let (athlete, coach) = (Uuid::new_v4(), Uuid::new_v4());
+        assert!(can_view_session(athlete, athlete, true, false));
+        assert!(can_view_session(coach, athlete, false, true));
+        assert!(!can_view_session(coach, athlete, true, true));
+        assert!(!can_view_session(coach, athlete, false, false));
+    }
+}
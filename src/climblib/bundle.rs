@@ -0,0 +1,118 @@
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, BufRead, BufReader, Write};
+
+pub const BUNDLE_INDEX_KEY: &str = "bundles/index.json";
+pub const UNDATED_BUNDLE: &str = "undated";
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct BundleIndex {
+    pub bundles: BTreeMap<String, BundleEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct BundleEntry {
+    pub key: String,
+    pub files: BTreeSet<String>,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct BundledLog {
+    pub filename: String,
+    pub log: Value,
+}
+
+pub fn bundle_key(month: &str) -> String {
+    format!("bundles/{}.jsonl.gz", month)
+}
+
+// Bundles are grouped by the month of the log's `date` field, which every log type carries.
+pub fn log_month(log: &Value) -> String {
+    log.get("date")
+        .and_then(|d| d.as_str())
+        .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .map(|d| d.format("%Y-%m").to_string())
+        .unwrap_or_else(|| UNDATED_BUNDLE.to_string())
+}
+
+pub fn group_by_month(logs: Vec<BundledLog>) -> BTreeMap<String, Vec<BundledLog>> {
+    let mut months: BTreeMap<String, Vec<BundledLog>> = BTreeMap::new();
+    for log in logs {
+        months.entry(log_month(&log.log)).or_default().push(log);
+    }
+    months
+}
+
+pub fn pack_bundle(logs: &[BundledLog]) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    for log in logs {
+        let line = serde_json::to_string(log).map_err(io::Error::other)?;
+        encoder.write_all(line.as_bytes())?;
+        encoder.write_all(b"\n")?;
+    }
+    encoder.finish()
+}
+
+pub fn unpack_bundle(data: &[u8]) -> io::Result<Vec<BundledLog>> {
+    let reader = BufReader::new(GzDecoder::new(data));
+    let mut logs = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let log = serde_json::from_str(&line)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        logs.push(log);
+    }
+    Ok(logs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn bundled(filename: &str, date: &str) -> BundledLog {
+        BundledLog {
+            filename: filename.to_string(),
+            log: json!({ "date": date, "notes": null }),
+        }
+    }
+
+    #[test]
+    fn test_log_month() {
+        assert_eq!(log_month(&json!({ "date": "2024-04-01" })), "2024-04");
+        assert_eq!(log_month(&json!({ "date": "04-06-2025" })), UNDATED_BUNDLE);
+        assert_eq!(log_month(&json!({ "notes": "no date" })), UNDATED_BUNDLE);
+    }
+
+    #[test]
+    fn test_group_by_month() {
+        let months = group_by_month(vec![
+            bundled("climb-2024-04-01.json", "2024-04-01"),
+            bundled("workout-2024-04-20.json", "2024-04-20"),
+            bundled("metrics-2024-05-02.json", "2024-05-02"),
+        ]);
+
+        assert_eq!(months.len(), 2);
+        assert_eq!(months["2024-04"].len(), 2);
+        assert_eq!(months["2024-05"][0].filename, "metrics-2024-05-02.json");
+    }
+
+    #[test]
+    fn test_pack_and_unpack_bundle_roundtrip() {
+        let logs = vec![
+            bundled("climb-2024-04-01.json", "2024-04-01"),
+            bundled("workout-2024-04-20.json", "2024-04-20"),
+        ];
+
+        let packed = pack_bundle(&logs).expect("Failed to pack");
+        let unpacked = unpack_bundle(&packed).expect("Failed to unpack");
+
+        assert_eq!(logs, unpacked);
+    }
+}
@@ -0,0 +1,268 @@
+use super::io::load_logs;
+use super::models::{ClimbMetricsEntry, ClimbingSession, WorkoutSession};
+use super::utils::{is_climb, is_workout, parse_date, DateRange, Period};
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tracing::error;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Default, Clone, Copy, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DayActivity {
+    pub climbing: u32,
+    pub workouts: u32,
+    pub metrics: u32,
+}
+
+impl DayActivity {
+    pub fn total(&self) -> u32 {
+        self.climbing + self.workouts + self.metrics
+    }
+
+    pub fn trained(&self) -> bool {
+        self.climbing + self.workouts > 0
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HeatmapDay {
+    pub date: NaiveDate,
+    #[serde(flatten)]
+    pub activity: DayActivity,
+    pub total: u32,
+}
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WeekDays {
+    pub week: String,
+    pub days_climbed: usize,
+}
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Streak {
+    pub weeks: usize,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RestGap {
+    pub rest_days: i64,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarStats {
+    pub days_climbed_per_week: Vec<WeekDays>,
+    pub avg_days_climbed_per_week: Option<f64>,
+    pub longest_streak: Option<Streak>,
+    pub rest_days: Vec<RestGap>,
+    pub sessions_per_location: BTreeMap<String, usize>,
+}
+
+pub fn daily_activity(
+    sessions: &[ClimbingSession],
+    workouts: &[WorkoutSession],
+    metrics: &[ClimbMetricsEntry]) -> BTreeMap<NaiveDate, DayActivity> {
+
+    let mut days: BTreeMap<NaiveDate, DayActivity> = BTreeMap::new();
+    for date in sessions.iter().filter_map(|s| parse_date(&s.date)) {
+        days.entry(date).or_default().climbing += 1;
+    }
+    for date in workouts.iter().filter_map(|w| parse_date(&w.date)) {
+        days.entry(date).or_default().workouts += 1;
+    }
+    for date in metrics.iter().filter_map(|m| parse_date(&m.date)) {
+        days.entry(date).or_default().metrics += 1;
+    }
+    days
+}
+
+// Every day of the year is present, including rest days, so clients can lay out the grid directly
+pub fn year_heatmap(activity: &BTreeMap<NaiveDate, DayActivity>, year: i32) -> Vec<HeatmapDay> {
+    let (Some(first), Some(next)) = (NaiveDate::from_yo_opt(year, 1), NaiveDate::from_yo_opt(year + 1, 1)) else {
+        return vec![];
+    };
+    first
+        .iter_days()
+        .take_while(|d| *d < next)
+        .map(|date| {
+            let activity = activity.get(&date).copied().unwrap_or_default();
+            HeatmapDay { date, activity, total: activity.total() }
+        })
+        .collect()
+}
+
+fn weeks_between(first: NaiveDate, last: NaiveDate) -> impl Iterator<Item = NaiveDate> {
+    let last = Period::Week.start(last);
+    std::iter::successors(Some(Period::Week.start(first)), move |w| Some(Period::Week.next(*w)))
+        .take_while(move |w| *w <= last)
+}
+
+pub fn build_calendar_stats(
+    sessions: &[ClimbingSession],
+    workouts: &[WorkoutSession],
+    range: &DateRange) -> CalendarStats {
+
+    let sessions: Vec<&ClimbingSession> = sessions.iter().filter(|s| range.contains(&s.date)).collect();
+    let workouts: Vec<&WorkoutSession> = workouts.iter().filter(|w| range.contains(&w.date)).collect();
+
+    let climbing_days: Vec<NaiveDate> = {
+        let mut days: Vec<NaiveDate> = sessions.iter().filter_map(|s| parse_date(&s.date)).collect();
+        days.sort();
+        days.dedup();
+        days
+    };
+    let mut training_days: Vec<NaiveDate> = climbing_days
+        .iter()
+        .copied()
+        .chain(workouts.iter().filter_map(|w| parse_date(&w.date)))
+        .collect();
+    training_days.sort();
+    training_days.dedup();
+
+    let mut per_week: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+    for day in &climbing_days {
+        *per_week.entry(Period::Week.start(*day)).or_default() += 1;
+    }
+    let days_climbed_per_week: Vec<WeekDays> = match (training_days.first(), training_days.last()) {
+        (Some(first), Some(last)) => weeks_between(*first, *last)
+            .map(|w| WeekDays { week: Period::Week.label(w), days_climbed: per_week.get(&w).copied().unwrap_or(0) })
+            .collect(),
+        _ => vec![],
+    };
+    let avg_days_climbed_per_week = (!days_climbed_per_week.is_empty()).then(|| {
+        days_climbed_per_week.iter().map(|w| w.days_climbed).sum::<usize>() as f64 / days_climbed_per_week.len() as f64
+    });
+
+    // A training week has at least one climbing session or workout
+    let mut longest_streak: Option<Streak> = None;
+    let mut current: Option<(NaiveDate, NaiveDate, usize)> = None;
+    let mut training_weeks: Vec<NaiveDate> = training_days.iter().map(|d| Period::Week.start(*d)).collect();
+    training_weeks.dedup();
+    for week in training_weeks {
+        current = match current {
+            Some((from, to, weeks)) if Period::Week.next(to) == week => Some((from, week, weeks + 1)),
+            _ => Some((week, week, 1)),
+        };
+        if let Some((from, to, weeks)) = current
+            && longest_streak.as_ref().is_none_or(|s| weeks > s.weeks)
+        {
+            longest_streak = Some(Streak { weeks, from, to: to + Duration::days(6) });
+        }
+    }
+
+    let mut gaps: BTreeMap<i64, usize> = BTreeMap::new();
+    for pair in training_days.windows(2) {
+        *gaps.entry((pair[1] - pair[0]).num_days() - 1).or_default() += 1;
+    }
+
+    let mut sessions_per_location: BTreeMap<String, usize> = BTreeMap::new();
+    for session in &sessions {
+        *sessions_per_location.entry(session.location.trim().to_string()).or_default() += 1;
+    }
+
+    CalendarStats {
+        days_climbed_per_week,
+        avg_days_climbed_per_week,
+        longest_streak,
+        rest_days: gaps.into_iter().map(|(rest_days, count)| RestGap { rest_days, count }).collect(),
+        sessions_per_location,
+    }
+}
+
+pub fn format_calendar_stats(stats: &CalendarStats) -> String {
+    let mut out = String::new();
+    match stats.avg_days_climbed_per_week {
+        Some(avg) => out.push_str(&format!("Average days climbed per week: {:.1}\n", avg)),
+        None => out.push_str("No training logged in this range\n"),
+    }
+    if let Some(streak) = &stats.longest_streak {
+        out.push_str(&format!("Longest streak: {} training weeks ({} to {})\n", streak.weeks, streak.from, streak.to));
+    }
+    if !stats.rest_days.is_empty() {
+        out.push_str(&format!("{:<10} {:>6}\n", "REST DAYS", "COUNT"));
+        for gap in &stats.rest_days {
+            out.push_str(&format!("{:<10} {:>6}\n", gap.rest_days, gap.count));
+        }
+    }
+    if !stats.sessions_per_location.is_empty() {
+        out.push_str(&format!("{:<24} {:>8}\n", "LOCATION", "SESSIONS"));
+        for (location, count) in &stats.sessions_per_location {
+            out.push_str(&format!("{:<24} {:>8}\n", location, count));
+        }
+    }
+    out
+}
+
+pub fn print_calendar_stats(user: Option<Uuid>, range: &DateRange, json: bool) {
+    let sessions: Vec<ClimbingSession> = load_logs(user, is_climb);
+    let workouts: Vec<WorkoutSession> = load_logs(user, is_workout);
+    let stats = build_calendar_stats(&sessions, &workouts, range);
+    if json {
+        match serde_json::to_string_pretty(&stats) {
+            Ok(out) => println!("{}", out),
+            Err(e) => error!("error {e} serializing calendar stats"),
+        }
+    } else {
+        print!("{}", format_calendar_stats(&stats));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::climblib::test_support::{session, workout};
+    use crate::climblib::models::ClimbStyle;
+
+    fn date(m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_year_heatmap() {
+        let sessions = vec![session("2024-04-01", ClimbStyle::Boulder, vec![])];
+        let workouts = vec![workout("2024-04-01", vec![]), workout("2023-12-31", vec![])];
+        let activity = daily_activity(&sessions, &workouts, &[]);
+
+        let heatmap = year_heatmap(&activity, 2024);
+
+        assert_eq!(heatmap.len(), 366);
+        let day = heatmap.iter().find(|d| d.date == date(4, 1)).unwrap();
+        assert_eq!((day.activity.climbing, day.activity.workouts, day.total), (1, 1, 2));
+        assert_eq!(heatmap.iter().map(|d| d.total).sum::<u32>(), 2);
+    }
+
+    #[test]
+    fn test_build_calendar_stats() {
+        let mut home = session("2024-04-03", ClimbStyle::Rope, vec![]);
+        home.location = "Home wall".to_string();
+        let sessions = vec![
+            session("2024-04-01", ClimbStyle::Boulder, vec![]),
+            home,
+            session("2024-04-15", ClimbStyle::Boulder, vec![]),
+            session("2024-04-22", ClimbStyle::Boulder, vec![]),
+        ];
+        let workouts = vec![workout("2024-04-02", vec![])];
+
+        let stats = build_calendar_stats(&sessions, &workouts, &DateRange::default());
+
+        assert_eq!(stats.days_climbed_per_week.len(), 4);
+        assert_eq!(stats.days_climbed_per_week[1].days_climbed, 0);
+        assert_eq!(stats.avg_days_climbed_per_week, Some(1.0));
+        assert_eq!(stats.longest_streak, Some(Streak { weeks: 2, from: date(4, 15), to: date(4, 28) }));
+        assert_eq!(stats.rest_days, vec![
+            RestGap { rest_days: 0, count: 2 },
+            RestGap { rest_days: 6, count: 1 },
+            RestGap { rest_days: 11, count: 1 },
+        ]);
+        assert_eq!(stats.sessions_per_location.get("Movement"), Some(&3));
+    }
+}
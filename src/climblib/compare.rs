@@ -0,0 +1,217 @@
+use super::io::load_logs;
+use super::models::{ClimbMetricsEntry, ClimbingSession, WorkoutSession};
+use super::sends::comparison_periods;
+use super::summary::{build_summary, Summary};
+use super::utils::{is_climb, is_metrics, is_workout, DateRange};
+use chrono::{NaiveDate, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tracing::error;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ComparisonRow {
+    pub section: String,
+    pub name: String,
+    pub first: Option<f64>,
+    pub second: Option<f64>,
+    pub change: Option<f64>,
+}
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Comparison {
+    pub first: Summary,
+    pub second: Summary,
+    pub rows: Vec<ComparisonRow>,
+}
+
+fn row(section: &str, name: &str, first: Option<f64>, second: Option<f64>) -> ComparisonRow {
+    ComparisonRow {
+        section: section.to_string(),
+        name: name.to_string(),
+        first,
+        second,
+        change: first.zip(second).map(|(a, b)| b - a),
+    }
+}
+
+// Rows keyed by name, in first-seen order across both summaries
+fn paired_rows(section: &str, first: Vec<(String, f64)>, second: Vec<(String, f64)>) -> Vec<ComparisonRow> {
+    let mut names: Vec<String> = Vec::new();
+    for (name, _) in first.iter().chain(&second) {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
+    }
+    let first: BTreeMap<String, f64> = first.into_iter().collect();
+    let second: BTreeMap<String, f64> = second.into_iter().collect();
+    names
+        .iter()
+        .map(|name| row(section, name, first.get(name).copied(), second.get(name).copied()))
+        .collect()
+}
+
+pub fn compare_summaries(first: Summary, second: Summary) -> Comparison {
+    let count = |f: fn(&Summary) -> usize| (Some(f(&first) as f64), Some(f(&second) as f64));
+    let mut rows = Vec::new();
+    for (name, (a, b)) in [
+        ("Climbing sessions", count(|s| s.climbing_sessions)),
+        ("Climbs", count(|s| s.climbs)),
+        ("Sends", count(|s| s.sends)),
+        ("Workouts", count(|s| s.workouts)),
+        ("Metric tests", count(|s| s.metric_tests)),
+    ] {
+        rows.push(row("sessions", name, a, b));
+    }
+    rows.extend(paired_rows(
+        "send rate",
+        first.send_rates.iter().map(|r| (r.grade.clone(), r.send_rate)).collect(),
+        second.send_rates.iter().map(|r| (r.grade.clone(), r.send_rate)).collect(),
+    ));
+    rows.push(row("lifting volume", "Total", Some(first.total_volume_lb), Some(second.total_volume_lb)));
+    rows.extend(paired_rows(
+        "lifting volume",
+        first.lifting_volume.iter().map(|v| (v.exercise.to_lowercase(), v.volume_lb)).collect(),
+        second.lifting_volume.iter().map(|v| (v.exercise.to_lowercase(), v.volume_lb)).collect(),
+    ));
+    rows.extend(paired_rows(
+        "metric change",
+        first.metric_changes.iter().map(|m| (m.metric.clone(), m.change)).collect(),
+        second.metric_changes.iter().map(|m| (m.metric.clone(), m.change)).collect(),
+    ));
+    Comparison { first, second, rows }
+}
+
+pub fn build_comparison(
+    sessions: &[ClimbingSession],
+    workouts: &[WorkoutSession],
+    metrics: &[ClimbMetricsEntry],
+    first: &DateRange,
+    second: &DateRange) -> Comparison {
+
+    compare_summaries(
+        build_summary(sessions, workouts, metrics, first),
+        build_summary(sessions, workouts, metrics, second),
+    )
+}
+
+// Without an explicit baseline, `range` is compared against the same number of days just before it
+pub fn comparison_ranges(range: &DateRange, against: &DateRange, today: NaiveDate) -> (DateRange, DateRange) {
+    let (current, previous) = comparison_periods(range, today);
+    if against.from.is_some() || against.to.is_some() {
+        (*against, current)
+    } else {
+        (previous, current)
+    }
+}
+
+fn value(v: Option<f64>, section: &str) -> String {
+    match v {
+        Some(v) if section == "send rate" => format!("{:.0}%", v * 100.0),
+        Some(v) => format!("{:.1}", v),
+        None => "-".to_string(),
+    }
+}
+
+fn period(summary: &Summary) -> String {
+    let date = |d: Option<NaiveDate>| d.map_or("..".to_string(), |d| d.to_string());
+    format!("{} to {}", date(summary.from), date(summary.to))
+}
+
+pub fn format_comparison(comparison: &Comparison) -> String {
+    let (a, b) = (&comparison.first, &comparison.second);
+    let mut out = format!("A: {}\nB: {}\n", period(a), period(b));
+    out.push_str(&format!(
+        "Hardest boulder: {} -> {}\nHardest rope: {} -> {}\n",
+        a.hardest_boulder.as_deref().unwrap_or("-"),
+        b.hardest_boulder.as_deref().unwrap_or("-"),
+        a.hardest_rope.as_deref().unwrap_or("-"),
+        b.hardest_rope.as_deref().unwrap_or("-"),
+    ));
+    out.push_str(&format!("{:<16} {:<24} {:>10} {:>10} {:>10}\n", "SECTION", "NAME", "A", "B", "CHANGE"));
+    for row in &comparison.rows {
+        let change = match (row.change, row.section.as_str()) {
+            (Some(c), "send rate") => format!("{:+.0}%", c * 100.0),
+            (Some(c), _) => format!("{:+.1}", c),
+            (None, _) => "-".to_string(),
+        };
+        out.push_str(&format!(
+            "{:<16} {:<24} {:>10} {:>10} {:>10}\n",
+            row.section, row.name, value(row.first, &row.section), value(row.second, &row.section), change
+        ));
+    }
+    out
+}
+
+pub fn print_comparison(user: Option<Uuid>, range: &DateRange, against: &DateRange, json: bool) {
+    let sessions: Vec<ClimbingSession> = load_logs(user, is_climb);
+    let workouts: Vec<WorkoutSession> = load_logs(user, is_workout);
+    let metrics: Vec<ClimbMetricsEntry> = load_logs(user, is_metrics);
+    let (first, second) = comparison_ranges(range, against, Utc::now().date_naive());
+    let comparison = build_comparison(&sessions, &workouts, &metrics, &first, &second);
+    if json {
+        match serde_json::to_string_pretty(&comparison) {
+            Ok(out) => println!("{}", out),
+            Err(e) => error!("error {e} serializing comparison"),
+        }
+    } else {
+        print!("{}", format_comparison(&comparison));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::climblib::test_support::{climb, exercise, session, workout};
+    use crate::climblib::models::{BoulderGrade, ClimbStyle, Grade};
+
+    fn date(m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_comparison_ranges_default_to_previous_block() {
+        let range = DateRange::new(Some(date(4, 15)), Some(date(4, 28)));
+        let (first, second) = comparison_ranges(&range, &DateRange::default(), date(6, 1));
+        assert_eq!(first, DateRange::new(Some(date(4, 1)), Some(date(4, 14))));
+        assert_eq!(second, range);
+
+        let against = DateRange::new(Some(date(1, 1)), Some(date(1, 31)));
+        assert_eq!(comparison_ranges(&range, &against, date(6, 1)).0, against);
+    }
+
+    #[test]
+    fn test_build_comparison() {
+        let sessions = vec![
+            session("2024-04-02", ClimbStyle::Boulder, vec![
+                climb(Grade::Boulder(BoulderGrade::V4), 2, true, false),
+                climb(Grade::Boulder(BoulderGrade::V5), 3, false, false),
+            ]),
+            session("2024-04-16", ClimbStyle::Boulder, vec![
+                climb(Grade::Boulder(BoulderGrade::V5), 3, true, false),
+            ]),
+        ];
+        let workouts = vec![
+            workout("2024-04-03", vec![exercise("Squat", 5, 200, None)]),
+            workout("2024-04-17", vec![exercise("squat", 5, 220, None), exercise("Bench", 5, 100, None)]),
+        ];
+        let first = DateRange::new(Some(date(4, 1)), Some(date(4, 14)));
+        let second = DateRange::new(Some(date(4, 15)), Some(date(4, 28)));
+
+        let comparison = build_comparison(&sessions, &workouts, &[], &first, &second);
+
+        assert_eq!(comparison.first.hardest_boulder.as_deref(), Some("v4"));
+        assert_eq!(comparison.second.hardest_boulder.as_deref(), Some("v5"));
+        let find = |section: &str, name: &str| {
+            comparison.rows.iter().find(|r| r.section == section && r.name == name).unwrap()
+        };
+        assert_eq!(find("sessions", "Climbs").change, Some(-1.0));
+        assert_eq!(find("send rate", "v5").change, Some(1.0));
+        assert_eq!(find("send rate", "v4").second, None);
+        assert_eq!(find("lifting volume", "squat").change, Some(300.0));
+        assert_eq!(find("lifting volume", "bench").first, None);
+    }
+}
@@ -0,0 +1,204 @@
+use super::lifting::{estimate_e1rm, lift_key};
+use super::models::{ClimbMetricsEntry, ClimbingSession, Goal, GoalStatus, GoalTarget, Grade, WorkoutSession};
+use super::utils::parse_date;
+use chrono::NaiveDate;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GoalProgress {
+    pub progress: f32,
+    pub achieved_date: Option<NaiveDate>,
+}
+
+fn same_discipline(a: Grade, b: Grade) -> bool {
+    matches!((a, b), (Grade::Boulder(_), Grade::Boulder(_)) | (Grade::Rope(_), Grade::Rope(_)))
+}
+
+// Progress is the best value so far as a percentage of the target, and the goal is achieved
+// on the first date the target was reached. Logs after the deadline don't count.
+fn progress_from(values: impl Iterator<Item = (NaiveDate, f64)>, target: f64, deadline: Option<NaiveDate>) -> GoalProgress {
+    let mut best: f64 = 0.0;
+    let mut achieved_date: Option<NaiveDate> = None;
+    for (date, value) in values.filter(|(date, _)| deadline.is_none_or(|d| *date <= d)) {
+        best = best.max(value);
+        if value >= target {
+            achieved_date = Some(achieved_date.map_or(date, |d| d.min(date)));
+        }
+    }
+    let progress = if target > 0.0 { (best / target * 100.0).min(100.0) } else { 0.0 };
+    GoalProgress { progress: progress as f32, achieved_date }
+}
+
+pub fn goal_progress(
+    target: &GoalTarget,
+    deadline: Option<NaiveDate>,
+    sessions: &[ClimbingSession],
+    workouts: &[WorkoutSession],
+    metrics: &[ClimbMetricsEntry]) -> GoalProgress {
+
+    match target {
+        // Grades are compared by position on their scale, counting from 1 so the easiest grade isn't zero progress
+        GoalTarget::Send { grade } => progress_from(
+            sessions.iter().filter_map(|s| parse_date(&s.date).map(|d| (d, s))).flat_map(|(date, s)| {
+                s.climbs
+                    .iter()
+                    .filter(|c| c.sent && same_discipline(c.grade, *grade))
+                    .map(move |c| (date, c.grade.rank() as f64 + 1.0))
+            }),
+            grade.rank() as f64 + 1.0,
+            deadline,
+        ),
+        GoalTarget::FingerStrength { percent_bw } => progress_from(
+            metrics.iter().filter_map(|m| Some((parse_date(&m.date)?, m.finger_strength_percent_bw? as f64))),
+            *percent_bw as f64,
+            deadline,
+        ),
+        GoalTarget::MaxPullup { percent_bw } => progress_from(
+            metrics.iter().filter_map(|m| Some((parse_date(&m.date)?, m.max_pullup_percent_bw? as f64))),
+            *percent_bw as f64,
+            deadline,
+        ),
+        GoalTarget::Lift { exercise, bodyweight_multiple, bodyweight_lb } => {
+            let key = lift_key(exercise);
+            progress_from(
+                workouts.iter().filter_map(|w| parse_date(&w.date).map(|d| (d, w))).flat_map(|(date, w)| {
+                    w.exercises
+                        .iter()
+                        .filter(|e| lift_key(&e.name) == key)
+                        .filter_map(move |e| estimate_e1rm(e).map(|e1rm| (date, e1rm.estimate)))
+                }),
+                (*bodyweight_multiple * *bodyweight_lb) as f64,
+                deadline,
+            )
+        }
+    }
+}
+
+fn deadline(goal: &Goal) -> Option<NaiveDate> {
+    goal.deadline.as_deref().and_then(parse_date)
+}
+
+// A goal not achieved by the end of its deadline is missed
+pub fn goal_status(goal: &Goal, today: NaiveDate) -> GoalStatus {
+    match (&goal.achieved_date, deadline(goal)) {
+        (Some(_), _) => GoalStatus::Achieved,
+        (None, Some(deadline)) if deadline < today => GoalStatus::Missed,
+        _ => GoalStatus::Open,
+    }
+}
+
+// Updates progress on every unachieved goal and returns the ones achieved by this check. An
+// achieved goal keeps its date even if later logs are edited.
+pub fn check_goals(
+    goals: &mut [Goal],
+    sessions: &[ClimbingSession],
+    workouts: &[WorkoutSession],
+    metrics: &[ClimbMetricsEntry],
+    today: NaiveDate) -> Vec<Goal> {
+
+    let mut achieved = Vec::new();
+    for goal in goals.iter_mut() {
+        if goal.achieved_date.is_none() {
+            let progress = goal_progress(&goal.target, deadline(goal), sessions, workouts, metrics);
+            goal.progress = progress.progress;
+            if let Some(date) = progress.achieved_date {
+                goal.achieved_date = Some(date.format("%Y-%m-%d").to_string());
+                goal.status = GoalStatus::Achieved;
+                achieved.push(goal.clone());
+            }
+        }
+        goal.status = goal_status(goal, today);
+    }
+    achieved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::climblib::test_support::{climb, exercise, session, workout};
+    use crate::climblib::models::{BoulderGrade, ClimbStyle, RopeGrade};
+
+    fn goal(target: GoalTarget) -> Goal {
+        Goal {
+            id: None,
+            title: "goal".to_string(),
+            target,
+            deadline: Some("2026-12-31".to_string()),
+            progress: 0.0,
+            achieved_date: None,
+            status: GoalStatus::Open,
+        }
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_send_goal_progress() {
+        let sessions = vec![
+            session("2024-04-01", ClimbStyle::Boulder, vec![climb(Grade::Boulder(BoulderGrade::V3), 1, true, false)]),
+            session("2024-04-02", ClimbStyle::Rope, vec![climb(Grade::Rope(RopeGrade::FiveTwelveA), 1, true, true)]),
+        ];
+        let target = GoalTarget::Send { grade: Grade::Boulder(BoulderGrade::V7) };
+
+        let progress = goal_progress(&target, None, &sessions, &[], &[]);
+
+        let expected = (BoulderGrade::V3 as u8 + 1) as f32 / (BoulderGrade::V7 as u8 + 1) as f32 * 100.0;
+        assert!((progress.progress - expected).abs() < 1e-3);
+        assert_eq!(progress.achieved_date, None);
+    }
+
+    #[test]
+    fn test_check_goals_marks_achieved() {
+        let metrics = vec![
+            ClimbMetricsEntry { id: None, date: "2024-04-01".to_string(), finger_strength_percent_bw: Some(120.0), max_pullup_percent_bw: None, notes: None },
+            ClimbMetricsEntry { id: None, date: "2024-05-01".to_string(), finger_strength_percent_bw: Some(150.0), max_pullup_percent_bw: None, notes: None },
+        ];
+        let workouts = vec![workout("2024-04-03", vec![exercise("Bench", 1, 200, None)])];
+        let mut goals = vec![
+            goal(GoalTarget::FingerStrength { percent_bw: 150.0 }),
+            goal(GoalTarget::Lift { exercise: "bench".to_string(), bodyweight_multiple: 1.25, bodyweight_lb: 180.0 }),
+        ];
+
+        let achieved = check_goals(&mut goals, &[], &workouts, &metrics, date(2024, 5, 2));
+
+        assert_eq!(achieved.len(), 1);
+        assert_eq!(goals[0].achieved_date.as_deref(), Some("2024-05-01"));
+        assert_eq!(goals[0].progress, 100.0);
+        assert!((goals[1].progress - 200.0 / 225.0 * 100.0).abs() < 1e-3);
+        assert_eq!((goals[0].status, goals[1].status), (GoalStatus::Achieved, GoalStatus::Open));
+        assert!(check_goals(&mut goals, &[], &workouts, &metrics, date(2024, 5, 2)).is_empty());
+    }
+
+    #[test]
+    fn test_logs_after_the_deadline_do_not_count() {
+        let metrics = vec![
+            ClimbMetricsEntry { id: None, date: "2024-04-01".to_string(), finger_strength_percent_bw: Some(120.0), max_pullup_percent_bw: None, notes: None },
+            ClimbMetricsEntry { id: None, date: "2024-05-01".to_string(), finger_strength_percent_bw: Some(150.0), max_pullup_percent_bw: None, notes: None },
+        ];
+        let mut goals = vec![Goal {
+            deadline: Some("2024-04-30".to_string()),
+            ..goal(GoalTarget::FingerStrength { percent_bw: 150.0 })
+        }];
+
+        assert!(check_goals(&mut goals, &[], &[], &metrics, date(2024, 4, 15)).is_empty());
+        assert!((goals[0].progress - 80.0).abs() < 1e-3);
+        assert_eq!(goals[0].status, GoalStatus::Open);
+
+        assert!(check_goals(&mut goals, &[], &[], &metrics, date(2024, 5, 2)).is_empty());
+        assert_eq!(goals[0].achieved_date, None);
+        assert_eq!(goals[0].status, GoalStatus::Missed);
+    }
+
+    #[test]
+    fn test_goal_deserializes_flat_target() {
+        let goal: Goal = serde_json::from_str(
+            r#"{"id":"1c2d3e4f-0000-4000-8000-000000000000","title":"Send V7","kind":"send","grade":"v7","deadline":"2026-12-31","status":"achieved"}"#
+        ).unwrap();
+        assert_eq!((goal.id, goal.status), (None, GoalStatus::Open));
+        assert_eq!(goal.target, GoalTarget::Send { grade: Grade::Boulder(BoulderGrade::V7) });
+        assert_eq!(goal.progress, 0.0);
+    }
+}
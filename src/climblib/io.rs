@@ -1,15 +1,56 @@
 use std::fs::{self, File};
 use std::io::{self, Write, Result};
 use std::path::{PathBuf, Path};
-use serde_json::{to_string_pretty, from_str};
+use serde_json::{to_string_pretty, from_str, Value};
 use serde::{Serialize, de::DeserializeOwned};
 use tracing::{info, error, warn};
+use uuid::Uuid;
+
+pub const LOG_DIR: &str = "logs";
+pub const USER_LOG_DIR: &str = "users";
+
+// Logs saved through the API are kept per user under logs/users/<id>
+pub fn user_log_dir(user_id: Uuid) -> PathBuf {
+    Path::new(LOG_DIR).join(USER_LOG_DIR).join(user_id.to_string())
+}
+
+// The top level of logs/ and every user's directory under it
+pub fn log_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![PathBuf::from(LOG_DIR)];
+    if let Ok(entries) = fs::read_dir(Path::new(LOG_DIR).join(USER_LOG_DIR)) {
+        let mut user_dirs: Vec<PathBuf> = entries.filter_map(|e| Some(e.ok()?.path())).filter(|p| p.is_dir()).collect();
+        user_dirs.sort();
+        dirs.extend(user_dirs);
+    }
+    dirs
+}
+
+// A log is named by its path under logs/, so top-level logs keep their bare filename and
+// a user's logs are named users/<id>/<filename>. Sync, its manifest and lint go by this name.
+pub fn log_name(path: &Path) -> Option<String> {
+    let parts: Option<Vec<&str>> = path.strip_prefix(LOG_DIR).ok()?.iter().map(|p| p.to_str()).collect();
+    Some(parts?.join("/"))
+}
+
+// The user directory a log name points into, if any
+pub fn log_name_dir(name: &str) -> Option<&str> {
+    let (dir, _) = name.rsplit_once('/')?;
+    let user = dir.strip_prefix(USER_LOG_DIR)?.strip_prefix('/')?;
+    (!user.is_empty() && !user.contains('/')).then_some(dir)
+}
 
 pub fn save_log<T: Serialize>(data: &T, filename: &str) -> Result<()> {
-    let mut path = PathBuf::from("logs");
+    save_log_in(Path::new(LOG_DIR), data, filename)
+}
+
+pub fn save_log_in<T: Serialize>(dir: &Path, data: &T, filename: &str) -> Result<()> {
+    let mut path = dir.to_path_buf();
     path.push(filename);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
     let json = to_string_pretty(&data)
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        .map_err(io::Error::other)?;
     let mut file = File::create(&path)?;
     file.write_all(json.as_bytes())?;
     Ok(())
@@ -22,12 +63,34 @@ pub fn load_log<T: DeserializeOwned>(path: &Path) -> io::Result<T> {
     Ok(session)
 }
 
+// A file can hold one record, a JSON array of records, or one record per line
+pub fn parse_records(contents: &str) -> std::result::Result<Vec<Value>, serde_json::Error> {
+    match from_str::<Value>(contents) {
+        Ok(Value::Array(records)) => Ok(records),
+        Ok(record) => Ok(vec![record]),
+        Err(e) => {
+            let lines: Vec<&str> = contents.lines().filter(|l| !l.trim().is_empty()).collect();
+            if lines.len() < 2 {
+                return Err(e);
+            }
+            lines.into_iter().map(from_str).collect()
+        }
+    }
+}
+
+// Every log on disk, the top level first and then each user's
 pub fn log_index() -> Result<Vec<PathBuf>> {
-    let log_dir = "logs";
+    let mut files = Vec::new();
+    for dir in log_dirs() {
+        files.extend(log_index_in(&dir)?);
+    }
+    Ok(files)
+}
 
+pub fn log_index_in(log_dir: &Path) -> Result<Vec<PathBuf>> {
     // Ensure the directory exists
-    if !std::path::Path::new(log_dir).exists() {
-        warn!("Log directory not found: {log_dir}");
+    if !log_dir.exists() {
+        warn!("Log directory not found: {}", log_dir.display());
         return Ok(vec![]); // Return an empty list instead of panicking
     }
 
@@ -38,7 +101,7 @@ pub fn log_index() -> Result<Vec<PathBuf>> {
         let path = entry?.path();
         let filename = path.file_name().and_then(|f| f.to_str()).unwrap_or("");
     
-        if filename.contains("workout") || filename.contains("climb") || filename.contains("metrics") {
+        if path.is_file() && (filename.contains("workout") || filename.contains("climb") || filename.contains("metrics")) {
             files.push(path);
         }
     }
@@ -46,12 +109,40 @@ pub fn log_index() -> Result<Vec<PathBuf>> {
     Ok(files)
 }
 
+// The stats read the top level of logs/ and, when given, one user's directory, so one
+// climber's numbers aren't mixed with everyone else's
+pub fn stats_log_dirs(user: Option<Uuid>) -> Vec<PathBuf> {
+    let mut dirs = vec![PathBuf::from(LOG_DIR)];
+    dirs.extend(user.map(user_log_dir));
+    dirs
+}
+
+pub fn load_logs<T: DeserializeOwned>(user: Option<Uuid>, matches: fn(&Path) -> bool) -> Vec<T> {
+    stats_log_dirs(user).iter().flat_map(|dir| load_logs_in(dir, matches)).collect()
+}
+
+pub fn load_logs_in<T: DeserializeOwned>(dir: &Path, matches: fn(&Path) -> bool) -> Vec<T> {
+    let mut logs = Vec::new();
+    match log_index_in(dir) {
+        Ok(paths) => {
+            for path in paths.iter().filter(|p| matches(p)) {
+                match load_log(path) {
+                    Ok(log) => logs.push(log),
+                    Err(e) => error!("error {e} loading file {:?}", path),
+                }
+            }
+        }
+        Err(e) => error!("error {e} getting paths"),
+    }
+    logs
+}
+
 pub fn print_log_index() {
     match log_index() {
         Ok(paths) => {
             for path in paths {
-                if let Some(filename) = path.file_name().and_then(|f| f.to_str()){
-                    info!("{}", filename);
+                if let Some(name) = log_name(&path) {
+                    info!("{}", name);
                 }
             }
         }
@@ -73,6 +164,7 @@ mod tests {
         reps: u8,
     }
     
+    #[test]
     fn test_save_and_load_log_roundtrip() {
         let temp = tempdir().unwrap();
         let filename = "test_workout.json";
@@ -97,4 +189,31 @@ mod tests {
 
         assert_eq!(workout, loaded);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_records() {
+        assert_eq!(parse_records("{\"a\":1}").unwrap().len(), 1);
+        assert_eq!(parse_records("[{\"a\":1},{\"a\":2}]").unwrap().len(), 2);
+        assert_eq!(parse_records("{\"a\":1}\n\n{\"a\":2}\n").unwrap().len(), 2);
+        assert!(parse_records("{\"a\":").is_err());
+    }
+
+    #[test]
+    fn test_log_names() {
+        let id = "6f1c2d3e-0000-4000-8000-000000000000";
+        assert_eq!(log_name(Path::new("logs/climb-2024-04-01.json")).as_deref(), Some("climb-2024-04-01.json"));
+        let name = log_name(&user_log_dir(Uuid::parse_str(id).unwrap()).join("climb-2024-04-01.json")).unwrap();
+        assert_eq!(name, format!("users/{}/climb-2024-04-01.json", id));
+        assert_eq!(log_name_dir(&name), Some(format!("users/{}", id).as_str()));
+        assert_eq!(log_name_dir("climb-2024-04-01.json"), None);
+        assert_eq!(log_name_dir("trash/climb-2024-04-01.json"), None);
+        assert_eq!(log_name_dir("users/a/b/climb-2024-04-01.json"), None);
+    }
+
+    #[test]
+    fn test_stats_log_dirs() {
+        let user = Uuid::new_v4();
+        assert_eq!(stats_log_dirs(None), vec![PathBuf::from(LOG_DIR)]);
+        assert_eq!(stats_log_dirs(Some(user)), vec![PathBuf::from(LOG_DIR), user_log_dir(user)]);
+    }
+}
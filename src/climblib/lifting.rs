@@ -0,0 +1,254 @@
+use super::models::{ExerciseEntry, WorkoutSession};
+use super::utils::parse_date;
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+// Percent of 1RM by reps plus reps in reserve (10 - RPE), from the RTS RPE chart
+const RPE_PERCENTAGES: [f64; 16] = [
+    100.0, 95.5, 92.2, 89.2, 86.3, 83.7, 81.1, 78.6,
+    76.2, 73.9, 70.7, 68.0, 65.3, 62.6, 59.9, 57.4,
+];
+pub const MAX_E1RM_REPS: u8 = 12;
+pub const MIN_TABLE_RPE: u8 = 6;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct E1rm {
+    pub epley: f64,
+    pub brzycki: f64,
+    pub rpe: Option<f64>,
+    pub estimate: f64,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum PersonalRecord {
+    EstimatedOneRepMax { exercise: String, e1rm: f64, previous: Option<f64> },
+    RepMax { exercise: String, reps: u8, weight_lb: i32, previous: Option<i32> },
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LiftRecord {
+    pub date: NaiveDate,
+    pub sets: u8,
+    pub reps: u8,
+    pub weight_lb: i32,
+    pub rpe: Option<u8>,
+    pub e1rm: Option<E1rm>,
+}
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LiftSummary {
+    pub exercise: String,
+    pub sessions: usize,
+    pub best_e1rm: Option<f64>,
+    pub last_date: Option<NaiveDate>,
+}
+
+pub fn epley(weight: f64, reps: u8) -> f64 {
+    if reps <= 1 {
+        return weight;
+    }
+    weight * (1.0 + reps as f64 / 30.0)
+}
+
+pub fn brzycki(weight: f64, reps: u8) -> f64 {
+    weight * 36.0 / (37.0 - reps as f64)
+}
+
+pub fn rpe_percentage(reps: u8, rpe: u8) -> Option<f64> {
+    if reps == 0 || reps > MAX_E1RM_REPS || !(MIN_TABLE_RPE..=10).contains(&rpe) {
+        return None;
+    }
+    RPE_PERCENTAGES.get((reps - 1 + (10 - rpe)) as usize).copied()
+}
+
+// High-rep sets are too noisy to estimate a single from, so they get no e1RM
+pub fn estimate_e1rm(exercise: &ExerciseEntry) -> Option<E1rm> {
+    if exercise.reps == 0 || exercise.reps > MAX_E1RM_REPS || exercise.weight_lb <= 0 {
+        return None;
+    }
+    let weight = exercise.weight_lb as f64;
+    let epley = epley(weight, exercise.reps);
+    let brzycki = brzycki(weight, exercise.reps);
+    let rpe = exercise.rpe
+        .and_then(|rpe| rpe_percentage(exercise.reps, rpe))
+        .map(|pct| weight * 100.0 / pct);
+    Some(E1rm {
+        epley,
+        brzycki,
+        rpe,
+        estimate: rpe.unwrap_or((epley + brzycki) / 2.0),
+    })
+}
+
+pub fn lift_key(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+// Entries with no sets or no reps were planned or skipped rather than lifted
+fn was_lifted(exercise: &&ExerciseEntry) -> bool {
+    exercise.sets > 0 && exercise.reps > 0
+}
+
+pub fn detect_prs(history: &[WorkoutSession], workout: &WorkoutSession) -> Vec<PersonalRecord> {
+    let mut best_e1rm: BTreeMap<String, f64> = BTreeMap::new();
+    let mut rep_max: BTreeMap<(String, u8), i32> = BTreeMap::new();
+    for exercise in history.iter().flat_map(|w| &w.exercises).filter(was_lifted) {
+        let key = lift_key(&exercise.name);
+        if let Some(e1rm) = estimate_e1rm(exercise) {
+            let best = best_e1rm.entry(key.clone()).or_insert(e1rm.estimate);
+            *best = best.max(e1rm.estimate);
+        }
+        let best = rep_max.entry((key, exercise.reps)).or_insert(exercise.weight_lb);
+        *best = (*best).max(exercise.weight_lb);
+    }
+
+    let mut prs = Vec::new();
+    for exercise in workout.exercises.iter().filter(was_lifted) {
+        let key = lift_key(&exercise.name);
+        if let Some(e1rm) = estimate_e1rm(exercise) {
+            let previous = best_e1rm.get(&key).copied();
+            if previous.is_none_or(|p| e1rm.estimate > p) {
+                prs.push(PersonalRecord::EstimatedOneRepMax {
+                    exercise: exercise.name.clone(),
+                    e1rm: e1rm.estimate,
+                    previous,
+                });
+                best_e1rm.insert(key.clone(), e1rm.estimate);
+            }
+        }
+        let previous = rep_max.get(&(key.clone(), exercise.reps)).copied();
+        if exercise.weight_lb > 0 && previous.is_none_or(|p| exercise.weight_lb > p) {
+            prs.push(PersonalRecord::RepMax {
+                exercise: exercise.name.clone(),
+                reps: exercise.reps,
+                weight_lb: exercise.weight_lb,
+                previous,
+            });
+            rep_max.insert((key, exercise.reps), exercise.weight_lb);
+        }
+    }
+    prs
+}
+
+pub fn exercise_history(workouts: &[WorkoutSession], name: &str) -> Vec<LiftRecord> {
+    let key = lift_key(name);
+    let mut history: Vec<LiftRecord> = workouts
+        .iter()
+        .filter_map(|w| parse_date(&w.date).map(|d| (d, w)))
+        .flat_map(|(date, w)| {
+            w.exercises
+                .iter()
+                .filter(|e| lift_key(&e.name) == key)
+                .map(move |e| LiftRecord {
+                    date,
+                    sets: e.sets,
+                    reps: e.reps,
+                    weight_lb: e.weight_lb,
+                    rpe: e.rpe,
+                    e1rm: estimate_e1rm(e),
+                })
+        })
+        .collect();
+    history.sort_by_key(|r| r.date);
+    history
+}
+
+pub fn main_lifts(workouts: &[WorkoutSession]) -> Vec<LiftSummary> {
+    let mut lifts: BTreeMap<String, LiftSummary> = BTreeMap::new();
+    for workout in workouts {
+        let date = parse_date(&workout.date);
+        for exercise in workout.exercises.iter().filter(|e| e.is_main_lift.unwrap_or(false)) {
+            let summary = lifts.entry(lift_key(&exercise.name)).or_insert_with(|| LiftSummary {
+                exercise: exercise.name.trim().to_string(),
+                sessions: 0,
+                best_e1rm: None,
+                last_date: None,
+            });
+            summary.sessions += 1;
+            if let Some(e1rm) = estimate_e1rm(exercise) {
+                summary.best_e1rm = Some(summary.best_e1rm.map_or(e1rm.estimate, |b| b.max(e1rm.estimate)));
+            }
+            summary.last_date = summary.last_date.max(date);
+        }
+    }
+    lifts.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::climblib::test_support::{exercise, workout};
+
+    #[test]
+    fn test_e1rm_formulas() {
+        assert_eq!(epley(200.0, 1), 200.0);
+        assert!((epley(200.0, 5) - 233.333).abs() < 1e-3);
+        assert!((brzycki(200.0, 5) - 225.0).abs() < 1e-9);
+        assert_eq!(rpe_percentage(1, 10), Some(100.0));
+        assert_eq!(rpe_percentage(3, 8), Some(86.3));
+        assert_eq!(rpe_percentage(3, 5), None);
+
+        let e1rm = estimate_e1rm(&exercise("Squat", 3, 250, Some(8))).unwrap();
+        assert!((e1rm.estimate - 250.0 * 100.0 / 86.3).abs() < 1e-9);
+        let no_rpe = estimate_e1rm(&exercise("Squat", 5, 200, None)).unwrap();
+        assert!((no_rpe.estimate - (no_rpe.epley + no_rpe.brzycki) / 2.0).abs() < 1e-9);
+        assert_eq!(estimate_e1rm(&exercise("Squat", 20, 100, None)), None);
+    }
+
+    #[test]
+    fn test_detect_prs() {
+        let history = vec![
+            workout("2024-04-01", vec![exercise("Bench", 5, 185, Some(8))]),
+            workout("2024-04-08", vec![exercise("bench ", 3, 195, Some(9))]),
+        ];
+        let today = workout("2024-04-15", vec![
+            exercise("Bench", 5, 190, Some(9)),
+            exercise("Deadlift", 5, 275, None),
+        ]);
+
+        let prs = detect_prs(&history, &today);
+
+        assert!(prs.contains(&PersonalRecord::RepMax {
+            exercise: "Bench".to_string(),
+            reps: 5,
+            weight_lb: 190,
+            previous: Some(185),
+        }));
+        assert!(!prs.iter().any(|p| matches!(
+            p, PersonalRecord::EstimatedOneRepMax { exercise, .. } if exercise == "Bench"
+        )));
+        assert_eq!(prs.iter().filter(|p| matches!(
+            p, PersonalRecord::EstimatedOneRepMax { exercise, previous: None, .. } if exercise == "Deadlift"
+        )).count(), 1);
+
+        let unfinished = ExerciseEntry { sets: 0, ..exercise("Squat", 5, 315, None) };
+        let skipped = workout("2024-04-16", vec![exercise("Row", 0, 150, None), unfinished]);
+        assert!(detect_prs(&history, &skipped).is_empty());
+    }
+
+    #[test]
+    fn test_exercise_history_and_main_lifts() {
+        let workouts = vec![
+            workout("2024-04-08", vec![exercise("Bench", 3, 195, Some(9))]),
+            workout("2024-04-01", vec![
+                exercise("bench", 5, 185, Some(8)),
+                ExerciseEntry { is_main_lift: Some(false), ..exercise("Curl", 10, 30, None) },
+            ]),
+        ];
+
+        let history = exercise_history(&workouts, "BENCH");
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].weight_lb, 185);
+
+        let lifts = main_lifts(&workouts);
+        assert_eq!(lifts.len(), 1);
+        assert_eq!(lifts[0].sessions, 2);
+        assert_eq!(lifts[0].last_date, NaiveDate::from_ymd_opt(2024, 4, 8));
+    }
+}
@@ -0,0 +1,363 @@
+use super::io::{load_log, log_dirs, log_index_in, log_name, save_log};
+use super::manifest::{log_path, soft_delete};
+use super::models::{content_log_type, ClimbingSession, LogEntry};
+use super::utils::{infer_log_type, parse_date};
+use chrono::NaiveDate;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use tracing::{error, info};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LintKind {
+    Unparseable,
+    Invalid,
+    WrongCategory,
+    Duplicate,
+    FutureDate,
+    ImpossibleValue,
+}
+
+// Only changes that can't lose data: a rewrite of repaired values, or a move that
+// leaves the old file in the trash where --undelete can bring it back
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum LintFix {
+    Repair,
+    Rename { to: String },
+    Trash,
+}
+
+impl LintFix {
+    // Repairs run before renames so a renamed file carries the repaired values
+    fn order(&self) -> u8 {
+        match self {
+            LintFix::Trash => 0,
+            LintFix::Repair => 1,
+            LintFix::Rename { .. } => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LintIssue {
+    pub file: String,
+    pub kind: LintKind,
+    pub message: String,
+    pub fix: Option<LintFix>,
+    pub fixed: bool,
+}
+
+impl LintIssue {
+    fn new(file: &str, kind: LintKind, message: String, fix: Option<LintFix>) -> Self {
+        LintIssue { file: file.to_string(), kind, message, fix, fixed: false }
+    }
+}
+
+struct LintedFile<'a> {
+    filename: &'a str,
+    kind: &'static str,
+    value: Value,
+    log: LogEntry,
+}
+
+pub fn canonical_filename(kind: &str, date: &str) -> String {
+    format!("{}-{}.json", kind, date)
+}
+
+// The log name `filename` would have in the same directory as `beside`
+fn sibling(beside: &str, filename: &str) -> String {
+    match beside.rsplit_once('/') {
+        Some((dir, _)) => format!("{}/{}", dir, filename),
+        None => filename.to_string(),
+    }
+}
+
+// A send takes at least one attempt and reaches the top, so these are safe to repair
+pub fn repair_climbs(session: &mut ClimbingSession) -> Vec<String> {
+    let mut repairs = Vec::new();
+    for (i, climb) in session.climbs.iter_mut().enumerate() {
+        if climb.sent && !climb.reached_top {
+            climb.reached_top = true;
+            repairs.push(format!("climbs/{}: sent without reachedTop", i));
+        }
+        if climb.sent && climb.attempts == 0 {
+            climb.attempts = 1;
+            repairs.push(format!("climbs/{}: sent with 0 attempts", i));
+        }
+    }
+    repairs
+}
+
+fn parse_log<'a>(filename: &'a str, contents: &str) -> Result<LintedFile<'a>, String> {
+    let value: Value = serde_json::from_str(contents).map_err(|e| e.to_string())?;
+    let Some(kind) = content_log_type(&value).or_else(|| infer_log_type(Path::new(filename))) else {
+        return Err("can't tell whether this is a climb, workout or metrics log".to_string());
+    };
+    let log = LogEntry::parse(kind, value.clone()).map_err(|e| format!("not a valid {} log: {}", kind, e))?;
+    Ok(LintedFile { filename, kind, value, log })
+}
+
+fn check_values(file: &mut LintedFile, today: NaiveDate) -> Vec<LintIssue> {
+    let filename = file.filename;
+    let invalid = file.log.validation_errors();
+    let mut impossible = Vec::new();
+    match &mut file.log {
+        LogEntry::Climbing(session) => {
+            impossible.extend(repair_climbs(session).into_iter().map(|r| (r, Some(LintFix::Repair))));
+        }
+        LogEntry::Workout(workout) => {
+            for (i, exercise) in workout.exercises.iter().enumerate() {
+                if exercise.sets == 0 && exercise.reps > 0 {
+                    impossible.push((format!("exercises/{}: {} reps logged over 0 sets", i, exercise.reps), None));
+                }
+            }
+        }
+        LogEntry::Metrics(_) => {}
+    }
+    let mut issues: Vec<LintIssue> = invalid
+        .into_iter()
+        .map(|message| LintIssue::new(filename, LintKind::Invalid, message, None))
+        .chain(impossible.into_iter().map(|(message, fix)| LintIssue::new(filename, LintKind::ImpossibleValue, message, fix)))
+        .collect();
+    if let Some(date) = parse_date(file.log.date()).filter(|d| *d > today) {
+        issues.push(LintIssue::new(filename, LintKind::FutureDate, format!("dated {}, after today", date), None));
+    }
+    issues
+}
+
+// Files are (log name, contents) pairs from one log directory
+pub fn lint_logs(files: &[(String, String)], today: NaiveDate) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    let mut parsed = Vec::new();
+    for (filename, contents) in files {
+        match parse_log(filename, contents) {
+            Ok(file) => parsed.push(file),
+            Err(e) => issues.push(LintIssue::new(filename, LintKind::Unparseable, e, None)),
+        }
+    }
+
+    // Identical logs keep the file with the expected name, or else the first one
+    let mut copies: HashMap<(&str, String), Vec<&str>> = HashMap::new();
+    for file in &parsed {
+        copies.entry((file.kind, file.value.to_string())).or_default().push(file.filename);
+    }
+    let mut trashed = HashSet::new();
+    for file in &parsed {
+        let group = &copies[&(file.kind, file.value.to_string())];
+        let expected = sibling(file.filename, &canonical_filename(file.kind, file.log.date()));
+        let keep = group.iter().find(|f| **f == expected).unwrap_or(&group[0]);
+        if *keep != file.filename {
+            trashed.insert(file.filename);
+            let message = format!("same {} log as {}", file.kind, keep);
+            issues.push(LintIssue::new(file.filename, LintKind::Duplicate, message, Some(LintFix::Trash)));
+        }
+    }
+
+    let mut taken: BTreeSet<String> = files.iter().map(|(f, _)| f.clone()).collect();
+    for mut file in parsed.into_iter().filter(|f| !trashed.contains(f.filename)) {
+        issues.extend(check_values(&mut file, today));
+        let named = infer_log_type(Path::new(file.filename));
+        if named != Some(file.kind) {
+            let target = sibling(file.filename, &canonical_filename(file.kind, file.log.date()));
+            let fix = taken.insert(target.clone()).then_some(LintFix::Rename { to: target });
+            let message = match named {
+                Some(named) => format!("named like a {} log but holds a {} log", named, file.kind),
+                None => format!("holds a {} log but the name doesn't say so", file.kind),
+            };
+            issues.push(LintIssue::new(file.filename, LintKind::WrongCategory, message, fix));
+        }
+    }
+    issues.sort_by(|a, b| a.file.cmp(&b.file));
+    issues
+}
+
+fn apply_fix(fix: &LintFix, filename: &str) -> io::Result<()> {
+    match fix {
+        LintFix::Repair => {
+            let mut session: ClimbingSession = load_log(&log_path(filename))?;
+            repair_climbs(&mut session);
+            save_log(&session, filename)
+        }
+        // Trashing the old name records a tombstone, so sync removes it from the bucket too
+        LintFix::Rename { to } => {
+            fs::copy(log_path(filename), log_path(to))?;
+            soft_delete(filename)
+        }
+        LintFix::Trash => soft_delete(filename),
+    }
+}
+
+// A file with several problems behind the same fix is only changed once
+pub fn apply_fixes(issues: &mut [LintIssue]) {
+    let mut order: Vec<usize> = (0..issues.len()).filter(|&i| issues[i].fix.is_some()).collect();
+    order.sort_by_key(|&i| issues[i].fix.as_ref().map(LintFix::order));
+    let mut done: HashMap<(String, LintFix), bool> = HashMap::new();
+    for i in order {
+        let issue = &mut issues[i];
+        let Some(fix) = issue.fix.clone() else { continue };
+        issue.fixed = *done.entry((issue.file.clone(), fix.clone())).or_insert_with(|| match apply_fix(&fix, &issue.file) {
+            Ok(()) => true,
+            Err(e) => {
+                error!("error {e} fixing {}", issue.file);
+                false
+            }
+        });
+        if issue.fixed {
+            info!("Fixed {}: {}", issue.file, issue.message);
+        }
+    }
+}
+
+pub fn format_lint(issues: &[LintIssue]) -> String {
+    if issues.is_empty() {
+        return "No problems found\n".to_string();
+    }
+    let mut out = String::new();
+    for issue in issues {
+        let status = match (&issue.fix, issue.fixed) {
+            (_, true) => "fixed",
+            (Some(_), false) => "fixable",
+            (None, false) => "",
+        };
+        out.push_str(&format!("{:<8} {:<16} {:<40} {}\n", status, format!("{:?}", issue.kind), issue.file, issue.message));
+    }
+    let open = issues.iter().filter(|i| !i.fixed).count();
+    let fixable = issues.iter().filter(|i| !i.fixed && i.fix.is_some()).count();
+    out.push_str(&format!("\n{} problems, {} open, {} fixable with --fix\n", issues.len(), open, fixable));
+    out
+}
+
+fn read_logs_in(dir: &Path) -> Vec<(String, String)> {
+    let mut files = Vec::new();
+    match log_index_in(dir) {
+        Ok(paths) => {
+            for path in paths {
+                let Some(filename) = log_name(&path) else {
+                    continue;
+                };
+                match fs::read_to_string(&path) {
+                    Ok(contents) => files.push((filename, contents)),
+                    Err(e) => error!("error {e} reading {:?}", path),
+                }
+            }
+        }
+        Err(e) => error!("error {e} getting paths"),
+    }
+    files.sort();
+    files
+}
+
+// Returns false while any problem is left unfixed. Each user's logs are linted apart, so
+// two users logging the same session aren't duplicates.
+pub fn print_lint(fix: bool, json: bool) -> bool {
+    let today = chrono::Utc::now().date_naive();
+    let mut issues: Vec<LintIssue> = log_dirs().iter().flat_map(|dir| lint_logs(&read_logs_in(dir), today)).collect();
+    if fix {
+        apply_fixes(&mut issues);
+    }
+    if json {
+        match serde_json::to_string_pretty(&issues) {
+            Ok(out) => println!("{}", out),
+            Err(e) => error!("error {e} serializing lint results"),
+        }
+    } else {
+        print!("{}", format_lint(&issues));
+    }
+    issues.iter().all(|i| i.fixed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 4, 10).unwrap()
+    }
+
+    fn file(filename: &str, log: Value) -> (String, String) {
+        (filename.to_string(), serde_json::to_string_pretty(&log).unwrap())
+    }
+
+    fn climb_log(date: &str, sent: bool, reached_top: bool, attempts: u8) -> Value {
+        json!({
+            "date": date, "location": "Movement", "style": "boulder",
+            "climbs": [{ "grade": "v4", "attempts": attempts, "sent": sent, "reachedTop": reached_top, "lead": false }]
+        })
+    }
+
+    fn kinds(issues: &[LintIssue], file: &str) -> Vec<LintKind> {
+        issues.iter().filter(|i| i.file == file).map(|i| i.kind).collect()
+    }
+
+    #[test]
+    fn test_lint_reports_each_problem() {
+        let files = vec![
+            file("climb-2024-04-01.json", climb_log("2024-04-01", true, false, 0)),
+            ("climb-2024-04-02.json".to_string(), "{\"date\": ".to_string()),
+            file("metrics-2024-04-03.json", json!({ "date": "2024-04-03", "fingerStrengthPercentBw": 50 })),
+            file("climb-2024-05-01.json", climb_log("2024-05-01", false, false, 3)),
+            file("workout-2024-04-04.json", json!({
+                "date": "2024-04-04",
+                "exercises": [{ "name": "Squat", "sets": 0, "reps": 5, "weightLb": 200 }]
+            })),
+        ];
+        let issues = lint_logs(&files, today());
+        assert_eq!(kinds(&issues, "climb-2024-04-01.json"), vec![LintKind::ImpossibleValue; 2]);
+        assert!(issues[0].fix == Some(LintFix::Repair));
+        assert_eq!(kinds(&issues, "climb-2024-04-02.json"), vec![LintKind::Unparseable]);
+        assert_eq!(kinds(&issues, "metrics-2024-04-03.json"), vec![LintKind::Invalid]);
+        assert_eq!(kinds(&issues, "climb-2024-05-01.json"), vec![LintKind::FutureDate]);
+        assert_eq!(kinds(&issues, "workout-2024-04-04.json"), vec![LintKind::ImpossibleValue]);
+        assert!(issues.iter().filter(|i| i.kind != LintKind::ImpossibleValue).all(|i| i.fix.is_none()));
+    }
+
+    #[test]
+    fn test_lint_duplicates_and_wrong_category() {
+        let log = climb_log("2024-04-01", true, true, 1);
+        let files = vec![
+            file("climb-2024-04-01 (copy).json", log.clone()),
+            file("climb-2024-04-01.json", log.clone()),
+            file("workout-2024-04-02.json", climb_log("2024-04-02", false, false, 2)),
+            file("metrics-2024-04-03.json", climb_log("2024-04-03", false, false, 2)),
+            file("climb-2024-04-03.json", climb_log("2024-04-03", true, true, 4)),
+        ];
+        let issues = lint_logs(&files, today());
+        assert_eq!(issues.len(), 3);
+        assert_eq!(issues[0].file, "climb-2024-04-01 (copy).json");
+        assert_eq!(issues[0].kind, LintKind::Duplicate);
+        assert_eq!(issues[0].fix, Some(LintFix::Trash));
+        assert_eq!(issues[1].message, "named like a metrics log but holds a climb log");
+        assert_eq!(issues[1].fix, None);
+        assert_eq!(issues[2].fix, Some(LintFix::Rename { to: "climb-2024-04-02.json".to_string() }));
+    }
+
+    #[test]
+    fn test_lint_fixes_stay_in_the_users_directory() {
+        let log = climb_log("2024-04-01", true, true, 1);
+        let files = vec![
+            file("users/6f1c/climb-2024-04-01 (copy).json", log.clone()),
+            file("users/6f1c/climb-2024-04-01.json", log.clone()),
+            file("users/6f1c/workout-2024-04-02.json", climb_log("2024-04-02", false, false, 2)),
+        ];
+        let issues = lint_logs(&files, today());
+        assert_eq!(issues[0].message, "same climb log as users/6f1c/climb-2024-04-01.json");
+        assert_eq!(issues[1].fix, Some(LintFix::Rename { to: "users/6f1c/climb-2024-04-02.json".to_string() }));
+    }
+
+    #[test]
+    fn test_repair_climbs() {
+        let mut session: ClimbingSession = serde_json::from_value(climb_log("2024-04-01", true, false, 0)).unwrap();
+        assert_eq!(repair_climbs(&mut session).len(), 2);
+        assert!(session.climbs[0].reached_top);
+        assert_eq!(session.climbs[0].attempts, 1);
+        assert!(repair_climbs(&mut session).is_empty());
+        assert_eq!(content_log_type(&json!({ "date": "2024-04-01" })), None);
+    }
+}
@@ -0,0 +1,185 @@
+use super::io::load_logs;
+use super::models::{ClimbEntry, ClimbingSession, ExerciseEntry, WorkoutSession};
+use super::utils::{is_climb, is_workout, parse_date, Period};
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+pub const DEFAULT_ACWR_THRESHOLD: f64 = 1.5;
+pub const ACUTE_DAYS: i64 = 7;
+pub const CHRONIC_DAYS: i64 = 28;
+// Sets logged without an RPE are treated as moderately hard
+pub const DEFAULT_RPE: u8 = 7;
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WeeklyLoad {
+    pub week: String,
+    pub climbing_load: f64,
+    pub lifting_load: f64,
+    pub climbing_acwr: Option<f64>,
+    pub lifting_acwr: Option<f64>,
+}
+
+impl WeeklyLoad {
+    pub fn exceeds(&self, threshold: f64) -> bool {
+        self.climbing_acwr.is_some_and(|r| r > threshold)
+            || self.lifting_acwr.is_some_and(|r| r > threshold)
+    }
+}
+
+pub fn climb_load(climb: &ClimbEntry) -> f64 {
+    let difficulty = climb.grade.rank() as f64 + 1.0;
+    let rests = if climb.lead { climb.rests.unwrap_or(0) } else { 0 };
+    difficulty * (climb.attempts as f64 + rests as f64)
+}
+
+pub fn exercise_load(exercise: &ExerciseEntry) -> f64 {
+    let rpe = exercise.rpe.unwrap_or(DEFAULT_RPE) as f64;
+    exercise.sets as f64 * exercise.reps as f64 * exercise.weight_lb.max(0) as f64 * rpe / 10.0
+}
+
+pub fn climbing_daily_load(sessions: &[ClimbingSession]) -> BTreeMap<NaiveDate, f64> {
+    let mut daily = BTreeMap::new();
+    for session in sessions {
+        let Some(date) = parse_date(&session.date) else {
+            continue;
+        };
+        *daily.entry(date).or_insert(0.0) += session.climbs.iter().map(climb_load).sum::<f64>();
+    }
+    daily
+}
+
+pub fn lifting_daily_load(workouts: &[WorkoutSession]) -> BTreeMap<NaiveDate, f64> {
+    let mut daily = BTreeMap::new();
+    for workout in workouts {
+        let Some(date) = parse_date(&workout.date) else {
+            continue;
+        };
+        *daily.entry(date).or_insert(0.0) += workout.exercises.iter().map(exercise_load).sum::<f64>();
+    }
+    daily
+}
+
+fn load_between(daily: &BTreeMap<NaiveDate, f64>, end: NaiveDate, days: i64) -> f64 {
+    daily.range(end - Duration::days(days - 1)..=end).map(|(_, l)| l).sum()
+}
+
+// Acute load is the last 7 days, chronic load is the weekly average over the last 28.
+// Without 28 days of history, or with nothing logged before the acute week, the chronic
+// load is just the acute load spread out, so the ratio would always read as a spike.
+pub fn acwr(daily: &BTreeMap<NaiveDate, f64>, end: NaiveDate) -> Option<f64> {
+    let window_start = end - Duration::days(CHRONIC_DAYS - 1);
+    let first = daily.keys().next()?;
+    if *first > window_start {
+        return None;
+    }
+    let acute = load_between(daily, end, ACUTE_DAYS);
+    let before_acute = load_between(daily, end - Duration::days(ACUTE_DAYS), CHRONIC_DAYS - ACUTE_DAYS);
+    if before_acute <= 0.0 {
+        return None;
+    }
+    let chronic = (acute + before_acute) / (CHRONIC_DAYS / ACUTE_DAYS) as f64;
+    Some(acute / chronic)
+}
+
+pub fn weekly_loads(
+    climbing: &BTreeMap<NaiveDate, f64>,
+    lifting: &BTreeMap<NaiveDate, f64>) -> Vec<WeeklyLoad> {
+
+    let first = climbing.keys().chain(lifting.keys()).min().copied();
+    let last = climbing.keys().chain(lifting.keys()).max().copied();
+    let (Some(first), Some(last)) = (first, last) else {
+        return vec![];
+    };
+
+    let mut weeks = Vec::new();
+    let mut start = Period::Week.start(first);
+    while start <= last {
+        let end = start + Duration::days(ACUTE_DAYS - 1);
+        weeks.push(WeeklyLoad {
+            week: Period::Week.label(start),
+            climbing_load: load_between(climbing, end, ACUTE_DAYS),
+            lifting_load: load_between(lifting, end, ACUTE_DAYS),
+            climbing_acwr: acwr(climbing, end),
+            lifting_acwr: acwr(lifting, end),
+        });
+        start = Period::Week.next(start);
+    }
+    weeks
+}
+
+pub fn training_load(user: Option<Uuid>) -> Vec<WeeklyLoad> {
+    let sessions: Vec<ClimbingSession> = load_logs(user, is_climb);
+    let workouts: Vec<WorkoutSession> = load_logs(user, is_workout);
+    weekly_loads(&climbing_daily_load(&sessions), &lifting_daily_load(&workouts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::climblib::models::{BoulderGrade, Grade, RopeGrade};
+    use crate::climblib::test_support::climb;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 4, d).unwrap()
+    }
+
+    #[test]
+    fn test_climb_load_counts_lead_rests() {
+        let mut lead = climb(Grade::Rope(RopeGrade::FiveSix), 2, true, true);
+        lead.rests = Some(3);
+        let mut top_rope = climb(Grade::Rope(RopeGrade::FiveSix), 2, true, false);
+        top_rope.rests = Some(3);
+
+        assert_eq!(climb_load(&lead), 10.0);
+        assert_eq!(climb_load(&top_rope), 4.0);
+        assert_eq!(climb_load(&climb(Grade::Boulder(BoulderGrade::V4), 3, false, false)), 18.0);
+    }
+
+    #[test]
+    fn test_exercise_load_scales_with_rpe() {
+        let squat = ExerciseEntry {
+            name: "Squat".to_string(),
+            sets: 3,
+            reps: 5,
+            weight_lb: 200,
+            rpe: Some(8),
+            is_main_lift: Some(true),
+        };
+        assert_eq!(exercise_load(&squat), 2400.0);
+    }
+
+    #[test]
+    fn test_acwr_flags_spike() {
+        let mut daily = BTreeMap::new();
+        for d in [1, 8, 15, 22] {
+            daily.insert(day(d), 10.0);
+        }
+        daily.insert(day(29), 40.0);
+
+        let steady = acwr(&daily, day(28)).unwrap();
+        assert!((steady - 1.0).abs() < 1e-9);
+        let spike = acwr(&daily, day(29) + Duration::days(6)).unwrap();
+        assert!((spike - 40.0 / 17.5).abs() < 1e-9);
+
+        let weeks = weekly_loads(&daily, &BTreeMap::new());
+        assert_eq!(weeks.len(), 5);
+        assert_eq!(weeks[0].climbing_acwr, None);
+        assert_eq!(weeks[2].climbing_acwr, None);
+        assert!(!weeks[0].exceeds(DEFAULT_ACWR_THRESHOLD));
+        assert!(!weeks[3].exceeds(DEFAULT_ACWR_THRESHOLD));
+        assert!(weeks[4].exceeds(DEFAULT_ACWR_THRESHOLD));
+        assert_eq!(weeks[4].lifting_acwr, None);
+    }
+
+    #[test]
+    fn test_acwr_waits_after_a_long_break() {
+        let mut daily = BTreeMap::from([(day(1), 10.0), (day(2), 10.0)]);
+        let back = day(2) + Duration::days(40);
+        daily.insert(back, 30.0);
+        assert_eq!(acwr(&daily, back), None);
+        assert!(acwr(&daily, back + Duration::days(21)).is_some());
+    }
+}
@@ -0,0 +1,247 @@
+use super::io::{load_log, log_index_in, save_log, user_log_dir, LOG_DIR};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+pub const MANIFEST_KEY: &str = "manifest.json";
+pub const LOCAL_MANIFEST: &str = ".manifest.json";
+pub const TRASH_DIR: &str = ".trash";
+pub const TRASH_PREFIX: &str = "trash/";
+pub const DEFAULT_RETENTION_DAYS: i64 = 30;
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SyncManifest {
+    pub tombstones: BTreeMap<String, Tombstone>,
+}
+
+// A tombstone is kept after an undelete too, so the restore wins over older deletes elsewhere
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Tombstone {
+    pub deleted: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl SyncManifest {
+    pub fn is_deleted(&self, filename: &str) -> bool {
+        self.tombstones.get(filename).is_some_and(|t| t.deleted)
+    }
+
+    pub fn record(&mut self, filename: &str, deleted: bool, at: DateTime<Utc>) {
+        self.tombstones.insert(filename.to_string(), Tombstone { deleted, updated_at: at });
+    }
+
+    // Last write wins per file, so deletes and undeletes propagate in both directions
+    pub fn merge(&self, other: &SyncManifest) -> SyncManifest {
+        let mut merged = self.clone();
+        for (filename, theirs) in &other.tombstones {
+            match merged.tombstones.get(filename) {
+                Some(ours) if ours.updated_at >= theirs.updated_at => {}
+                _ => {
+                    merged.tombstones.insert(filename.clone(), theirs.clone());
+                }
+            }
+        }
+        merged
+    }
+
+    // A file written after its delete was logged again, so it wins over the tombstone
+    pub fn revive(&mut self, filename: &str, modified: DateTime<Utc>) -> bool {
+        match self.tombstones.get(filename) {
+            Some(t) if t.deleted && modified > t.updated_at => {
+                self.record(filename, false, modified);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn purge_expired(&mut self, now: DateTime<Utc>, retention_days: i64) -> Vec<String> {
+        let cutoff = now - Duration::days(retention_days);
+        let expired: Vec<String> = self.tombstones
+            .iter()
+            .filter(|(_, t)| t.deleted && t.updated_at < cutoff)
+            .map(|(f, _)| f.clone())
+            .collect();
+        for filename in &expired {
+            self.tombstones.remove(filename);
+        }
+        expired
+    }
+}
+
+pub fn trash_key(key: &str) -> String {
+    format!("{}{}", TRASH_PREFIX, key)
+}
+
+pub fn log_path(filename: &str) -> PathBuf {
+    let mut path = PathBuf::from("logs");
+    path.push(filename);
+    path
+}
+
+pub fn trash_path(filename: &str) -> PathBuf {
+    let mut path = PathBuf::from("logs");
+    path.push(TRASH_DIR);
+    path.push(filename);
+    path
+}
+
+pub fn load_local_manifest() -> io::Result<SyncManifest> {
+    let path = log_path(LOCAL_MANIFEST);
+    if !path.exists() {
+        return Ok(SyncManifest::default());
+    }
+    load_log(&path)
+}
+
+pub fn save_local_manifest(manifest: &SyncManifest) -> io::Result<()> {
+    save_log(manifest, LOCAL_MANIFEST)
+}
+
+// An earlier deleted copy of the same log is kept beside the new one as `<file>.1`, `<file>.2`, ...
+pub fn move_to_trash(filename: &str) -> io::Result<()> {
+    let target = trash_path(filename);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if target.exists() {
+        let kept = (1..)
+            .map(|n| trash_path(&format!("{}.{}", filename, n)))
+            .find(|p| !p.exists())
+            .expect("some suffix is free");
+        fs::rename(&target, kept)?;
+    }
+    fs::rename(log_path(filename), target)
+}
+
+pub fn restore_from_trash(filename: &str) -> io::Result<()> {
+    let target = log_path(filename);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(trash_path(filename), target)
+}
+
+// Returns the deleted logs that exist locally again, recording them as live
+pub fn revive_relogged(manifest: &mut SyncManifest) -> Vec<String> {
+    let deleted: Vec<String> = manifest.tombstones
+        .iter()
+        .filter(|(_, t)| t.deleted)
+        .map(|(f, _)| f.clone())
+        .collect();
+    deleted
+        .into_iter()
+        .filter(|filename| {
+            let modified = fs::metadata(log_path(filename)).and_then(|m| m.modified());
+            modified.is_ok_and(|m| manifest.revive(filename, m.into()))
+        })
+        .collect()
+}
+
+// Moves the top-level logs into a user's directory. The old names get tombstones, so the
+// next sync moves their bucket copies to the trash and uploads the logs under the user's
+// prefix. A log the user already has for that name is left where it is.
+pub fn claim_logs(user_id: Uuid) -> io::Result<Vec<String>> {
+    let mut manifest = load_local_manifest()?;
+    let dir = user_log_dir(user_id);
+    fs::create_dir_all(&dir)?;
+    let mut claimed = Vec::new();
+    for path in log_index_in(Path::new(LOG_DIR))? {
+        let Some(filename) = path.file_name().and_then(|f| f.to_str()) else {
+            continue;
+        };
+        let target = dir.join(filename);
+        if target.exists() {
+            warn!("{} already exists, leaving {} in place", target.display(), filename);
+            continue;
+        }
+        fs::rename(&path, &target)?;
+        manifest.record(filename, true, Utc::now());
+        claimed.push(filename.to_string());
+    }
+    save_local_manifest(&manifest)?;
+    Ok(claimed)
+}
+
+pub fn soft_delete(filename: &str) -> io::Result<()> {
+    let mut manifest = load_local_manifest()?;
+    move_to_trash(filename)?;
+    manifest.record(filename, true, Utc::now());
+    save_local_manifest(&manifest)?;
+    info!("Deleted {}, restore with --undelete {}", filename, filename);
+    Ok(())
+}
+
+pub fn undelete(filename: &str) -> io::Result<()> {
+    let mut manifest = load_local_manifest()?;
+    if trash_path(filename).exists() {
+        restore_from_trash(filename)?;
+        info!("Restored {}", filename);
+    } else {
+        warn!("{} is not in the local trash, it will be restored on the next pull", filename);
+    }
+    manifest.record(filename, false, Utc::now());
+    save_local_manifest(&manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 4, day, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_merge_keeps_latest_tombstone() {
+        let mut local = SyncManifest::default();
+        local.record("climb-2024-04-01.json", true, at(2));
+        local.record("workout-2024-04-02.json", false, at(5));
+
+        let mut remote = SyncManifest::default();
+        remote.record("climb-2024-04-01.json", false, at(3));
+        remote.record("workout-2024-04-02.json", true, at(4));
+        remote.record("metrics-2024-04-03.json", true, at(4));
+
+        let merged = local.merge(&remote);
+
+        assert!(!merged.is_deleted("climb-2024-04-01.json"));
+        assert!(!merged.is_deleted("workout-2024-04-02.json"));
+        assert!(merged.is_deleted("metrics-2024-04-03.json"));
+        assert_eq!(merged, remote.merge(&local));
+    }
+
+    #[test]
+    fn test_revive_only_after_the_delete() {
+        let mut manifest = SyncManifest::default();
+        manifest.record("climb-2024-04-01.json", true, at(5));
+        manifest.record("climb-2024-04-02.json", false, at(5));
+
+        assert!(!manifest.revive("climb-2024-04-01.json", at(4)));
+        assert!(manifest.is_deleted("climb-2024-04-01.json"));
+        assert!(!manifest.revive("climb-2024-04-02.json", at(6)));
+        assert!(manifest.revive("climb-2024-04-01.json", at(6)));
+        assert!(!manifest.is_deleted("climb-2024-04-01.json"));
+        assert_eq!(manifest.tombstones["climb-2024-04-01.json"].updated_at, at(6));
+    }
+
+    #[test]
+    fn test_purge_expired_only_drops_old_deletes() {
+        let mut manifest = SyncManifest::default();
+        manifest.record("climb-2024-04-01.json", true, at(1));
+        manifest.record("climb-2024-04-02.json", false, at(1));
+        manifest.record("climb-2024-04-20.json", true, at(20));
+
+        let purged = manifest.purge_expired(at(25), 10);
+
+        assert_eq!(purged, vec!["climb-2024-04-01.json".to_string()]);
+        assert!(manifest.tombstones.contains_key("climb-2024-04-02.json"));
+        assert!(manifest.is_deleted("climb-2024-04-20.json"));
+    }
+}
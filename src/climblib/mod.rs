@@ -2,4 +2,24 @@ pub mod models;
 pub mod io;
 pub mod summary;
 pub mod sync;
-pub mod utils;
\ No newline at end of file
+pub mod utils;
+pub mod bundle;
+pub mod manifest;
+pub mod watch;
+pub mod status;
+pub mod pyramid;
+pub mod progression;
+pub mod load;
+pub mod lifting;
+pub mod sends;
+pub mod trends;
+pub mod report;
+pub mod calendar;
+pub mod goals;
+pub mod recommend;
+pub mod compare;
+pub mod team;
+pub mod schema;
+pub mod lint;
+#[cfg(test)]
+pub(crate) mod test_support;
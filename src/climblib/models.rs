@@ -1,10 +1,15 @@
 use serde::{Serialize, Deserialize};
-use validator::{Validate};
+use utoipa::ToSchema;
+use schemars::JsonSchema;
+use validator::{Validate, ValidationError};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
 use std::fmt;
+use std::str::FromStr;
 use super::utils::{validate_date_format};
 
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, ToSchema, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum ClimbStyle {
     Boulder,
@@ -21,7 +26,7 @@ impl fmt::Display for ClimbStyle {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, ToSchema, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum RopeGrade {
     #[serde(rename = "5.intro")] FiveIntro,
@@ -55,6 +60,20 @@ pub enum RopeGrade {
     #[serde(rename = "5.15d")] FiveFifteenD,
 }
 
+impl RopeGrade {
+    pub const ALL: [RopeGrade; 29] = [
+        RopeGrade::FiveIntro, RopeGrade::FiveSix, RopeGrade::FiveSeven, RopeGrade::FiveEight,
+        RopeGrade::FiveNine, RopeGrade::FiveTenA, RopeGrade::FiveTenB, RopeGrade::FiveTenC,
+        RopeGrade::FiveTenD, RopeGrade::FiveElevenA, RopeGrade::FiveElevenB, RopeGrade::FiveElevenC,
+        RopeGrade::FiveElevenD, RopeGrade::FiveTwelveA, RopeGrade::FiveTwelveB,
+        RopeGrade::FiveTwelveC, RopeGrade::FiveTwelveD, RopeGrade::FiveThirteenA,
+        RopeGrade::FiveThirteenB, RopeGrade::FiveThirteenC, RopeGrade::FiveThirteenD,
+        RopeGrade::FiveFourteenA, RopeGrade::FiveFourteenB, RopeGrade::FiveFourteenC,
+        RopeGrade::FiveFourteenD, RopeGrade::FiveFifteenA, RopeGrade::FiveFifteenB,
+        RopeGrade::FiveFifteenC, RopeGrade::FiveFifteenD,
+    ];
+}
+
 impl fmt::Display for RopeGrade {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let s = match self {
@@ -93,7 +112,7 @@ impl fmt::Display for RopeGrade {
 }
 
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, ToSchema, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum BoulderGrade {
     #[serde(rename = "vintro")] VIntro,
@@ -117,6 +136,16 @@ pub enum BoulderGrade {
     #[serde(rename = "v17")] V17,
 }
 
+impl BoulderGrade {
+    pub const ALL: [BoulderGrade; 19] = [
+        BoulderGrade::VIntro, BoulderGrade::V0, BoulderGrade::V1, BoulderGrade::V2,
+        BoulderGrade::V3, BoulderGrade::V4, BoulderGrade::V5, BoulderGrade::V6, BoulderGrade::V7,
+        BoulderGrade::V8, BoulderGrade::V9, BoulderGrade::V10, BoulderGrade::V11, BoulderGrade::V12,
+        BoulderGrade::V13, BoulderGrade::V14, BoulderGrade::V15, BoulderGrade::V16,
+        BoulderGrade::V17,
+    ];
+}
+
 impl fmt::Display for BoulderGrade {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let s = match self {
@@ -145,14 +174,58 @@ impl fmt::Display for BoulderGrade {
 }
 
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, ToSchema, JsonSchema)]
 #[serde(untagged)]
 pub enum Grade {
     Rope(RopeGrade),
     Boulder(BoulderGrade),
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize, Validate)]
+impl fmt::Display for Grade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Grade::Rope(r) => r.fmt(f),
+            Grade::Boulder(b) => b.fmt(f),
+        }
+    }
+}
+
+impl Grade {
+    // Position on its own scale, starting at 0 for vintro and 5.intro
+    pub fn rank(&self) -> u8 {
+        match self {
+            Grade::Rope(r) => *r as u8,
+            Grade::Boulder(b) => *b as u8,
+        }
+    }
+
+    // Moves along its own scale, stopping at the easiest and hardest grades
+    pub fn step(&self, steps: i32) -> Grade {
+        let index = (self.rank() as i32 + steps).max(0) as usize;
+        match self {
+            Grade::Rope(_) => Grade::Rope(RopeGrade::ALL[index.min(RopeGrade::ALL.len() - 1)]),
+            Grade::Boulder(_) => Grade::Boulder(BoulderGrade::ALL[index.min(BoulderGrade::ALL.len() - 1)]),
+        }
+    }
+}
+
+impl FromStr for Grade {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_value(serde_json::Value::String(s.to_lowercase()))
+    }
+}
+
+impl FromStr for ClimbStyle {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_value(serde_json::Value::String(s.to_lowercase()))
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Validate, ToSchema, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ClimbEntry {
     #[validate(length(min = 0, max = 100))]
@@ -167,10 +240,23 @@ pub struct ClimbEntry {
     pub rests: Option<u8>,
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize, Validate)]
+// A calendar date, so validators that check formats reject days like 2024-13-45
+fn date_schema(_: &mut schemars::r#gen::SchemaGenerator) -> schemars::schema::Schema {
+    schemars::schema::SchemaObject {
+        instance_type: Some(schemars::schema::InstanceType::String.into()),
+        format: Some("date".to_string()),
+        ..Default::default()
+    }
+    .into()
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize, Validate, ToSchema, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ClimbMetricsEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
     #[validate(length(min = 1), custom(function = "validate_date_format"))]
+    #[schemars(schema_with = "date_schema")]
     pub date: String,
     #[validate(range(min = 100, max = 300))]
     pub finger_strength_percent_bw: Option<f32>,
@@ -180,10 +266,16 @@ pub struct ClimbMetricsEntry {
     pub notes: Option<String>,
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize, Validate)]
+#[derive(Debug, serde::Serialize, serde::Deserialize, Validate, ToSchema, JsonSchema)]
 #[serde(rename = "camelCaseName")]
+#[schemars(rename = "ClimbingSession")]
 pub struct ClimbingSession {
+    // Set on sessions read back from the database, and sent by clients that retry saves so
+    // a replay is recognised instead of saved twice
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
     #[validate(length(min = 1), custom(function = "validate_date_format"))]
+    #[schemars(schema_with = "date_schema")]
     pub date: String,
     #[validate(length(min = 0, max = 100))]
     pub location: String,
@@ -191,9 +283,12 @@ pub struct ClimbingSession {
     #[validate(length(min = 0, max = 300))]
     pub notes: Option<String>,
     pub climbs: Vec<ClimbEntry>,
+    // Private sessions are hidden from coaches but still count in the athlete's own stats
+    #[serde(default)]
+    pub private: bool,
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize, Validate)]
+#[derive(Debug, serde::Serialize, serde::Deserialize, Validate, ToSchema, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ExerciseEntry {
     #[validate(length(min = 0, max = 100))]
@@ -209,18 +304,211 @@ pub struct ExerciseEntry {
     pub is_main_lift: Option<bool>,
   }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize, Validate)]
+#[derive(Debug, serde::Serialize, serde::Deserialize, Validate, ToSchema, JsonSchema)]
 #[serde(rename = "camelCaseName")]
+#[schemars(rename = "WorkoutSession")]
 pub struct WorkoutSession {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
     #[validate(length(min = 1), custom(function = "validate_date_format"))]
+    #[schemars(schema_with = "date_schema")]
     pub date: String,
     #[validate(length(min = 0, max = 300))]
     pub notes: Option<String>,
     pub exercises: Vec<ExerciseEntry>,
   }
 
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum GoalTarget {
+    Send { grade: Grade },
+    #[serde(rename_all = "camelCase")]
+    FingerStrength { percent_bw: f32 },
+    #[serde(rename_all = "camelCase")]
+    MaxPullup { percent_bw: f32 },
+    #[serde(rename_all = "camelCase")]
+    Lift { exercise: String, bodyweight_multiple: f32, bodyweight_lb: f32 },
+}
+
+fn validate_goal_target(target: &GoalTarget) -> Result<(), ValidationError> {
+    let valid = match target {
+        GoalTarget::Send { .. } => true,
+        GoalTarget::FingerStrength { percent_bw } | GoalTarget::MaxPullup { percent_bw } => {
+            (100.0..=300.0).contains(percent_bw)
+        }
+        GoalTarget::Lift { exercise, bodyweight_multiple, bodyweight_lb } => {
+            !exercise.trim().is_empty()
+                && (0.1..=5.0).contains(bodyweight_multiple)
+                && (50.0..=500.0).contains(bodyweight_lb)
+        }
+    };
+    if valid { Ok(()) } else { Err(ValidationError::new("invalid_goal_target")) }
+}
+
+// Derived from the achieved date and deadline, so it's never read from clients
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum GoalStatus {
+    #[default]
+    Open,
+    Achieved,
+    Missed,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Goal {
+    #[serde(default, skip_deserializing)]
+    pub id: Option<Uuid>,
+    #[validate(length(min = 1, max = 100))]
+    pub title: String,
+    #[serde(flatten)]
+    #[validate(custom(function = "validate_goal_target"))]
+    pub target: GoalTarget,
+    #[validate(custom(function = "validate_date_format"))]
+    pub deadline: Option<String>,
+    #[serde(default)]
+    pub progress: f32,
+    #[serde(default)]
+    pub achieved_date: Option<String>,
+    #[serde(default, skip_deserializing)]
+    pub status: GoalStatus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TeamRole {
+    Coach,
+    Athlete,
+}
+
+impl fmt::Display for TeamRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            TeamRole::Coach => "coach",
+            TeamRole::Athlete => "athlete",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for TeamRole {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_value(serde_json::Value::String(s.to_lowercase()))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamMembership {
+    pub team_id: Uuid,
+    pub name: String,
+    pub role: TeamRole,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamMember {
+    pub user_id: Uuid,
+    pub username: String,
+    pub role: TeamRole,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamInvitation {
+    pub id: Uuid,
+    pub team_id: Uuid,
+    pub team_name: String,
+    pub role: TeamRole,
+    pub invited_by: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionComment {
+    pub id: Uuid,
+    pub session_id: Uuid,
+    pub author: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
 pub enum LogEntry {
     Climbing(ClimbingSession),
     Workout(WorkoutSession),
     Metrics(ClimbMetricsEntry),
 }
+
+impl LogEntry {
+    // `kind` is one of the names `infer_log_type` returns
+    pub fn parse(kind: &str, value: serde_json::Value) -> Result<LogEntry, serde_json::Error> {
+        match kind {
+            "climb" => serde_json::from_value(value).map(LogEntry::Climbing),
+            "workout" => serde_json::from_value(value).map(LogEntry::Workout),
+            _ => serde_json::from_value(value).map(LogEntry::Metrics),
+        }
+    }
+
+    pub fn id(&self) -> Option<Uuid> {
+        match self {
+            LogEntry::Climbing(s) => s.id,
+            LogEntry::Workout(w) => w.id,
+            LogEntry::Metrics(m) => m.id,
+        }
+    }
+
+    pub fn id_mut(&mut self) -> &mut Option<Uuid> {
+        match self {
+            LogEntry::Climbing(s) => &mut s.id,
+            LogEntry::Workout(w) => &mut w.id,
+            LogEntry::Metrics(m) => &mut m.id,
+        }
+    }
+
+    pub fn date(&self) -> &str {
+        match self {
+            LogEntry::Climbing(s) => &s.date,
+            LogEntry::Workout(w) => &w.date,
+            LogEntry::Metrics(m) => &m.date,
+        }
+    }
+
+    // Sessions don't validate their entries themselves, so each entry is checked too
+    pub fn validation_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        match self {
+            LogEntry::Climbing(session) => {
+                errors.extend(session.validate().err().map(|e| e.to_string()));
+                for (i, climb) in session.climbs.iter().enumerate() {
+                    errors.extend(climb.validate().err().map(|e| format!("climbs/{}: {}", i, e)));
+                }
+            }
+            LogEntry::Workout(workout) => {
+                errors.extend(workout.validate().err().map(|e| e.to_string()));
+                for (i, exercise) in workout.exercises.iter().enumerate() {
+                    errors.extend(exercise.validate().err().map(|e| format!("exercises/{}: {}", i, e)));
+                }
+            }
+            LogEntry::Metrics(entry) => errors.extend(entry.validate().err().map(|e| e.to_string())),
+        }
+        errors
+    }
+}
+
+// Goes by the fields a log holds, so a misnamed file is still recognised
+pub fn content_log_type(value: &serde_json::Value) -> Option<&'static str> {
+    let object = value.as_object()?;
+    if object.contains_key("climbs") {
+        Some("climb")
+    } else if object.contains_key("exercises") {
+        Some("workout")
+    } else if object.contains_key("fingerStrengthPercentBw") || object.contains_key("maxPullupPercentBw") {
+        Some("metrics")
+    } else {
+        None
+    }
+}
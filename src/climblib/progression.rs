@@ -0,0 +1,227 @@
+use super::io::load_logs;
+use super::models::{ClimbingSession, Grade};
+use super::utils::{is_climb, parse_date, DateRange, Period};
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tracing::error;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+pub const DEFAULT_WINDOW: usize = 4;
+pub const DEFAULT_TOP_N: usize = 5;
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressionPoint {
+    pub period: String,
+    pub start: NaiveDate,
+    pub sends: usize,
+    pub max_grade: Option<String>,
+    pub rolling_max: Option<String>,
+    pub median_top_n: Option<String>,
+}
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+pub struct Milestone {
+    pub grade: String,
+    pub date: NaiveDate,
+}
+
+#[derive(Debug, Default, Serialize, PartialEq, ToSchema)]
+pub struct DisciplineProgression {
+    pub points: Vec<ProgressionPoint>,
+    pub milestones: Vec<Milestone>,
+}
+
+#[derive(Debug, Default, Serialize, PartialEq, ToSchema)]
+pub struct Progression {
+    pub boulder: DisciplineProgression,
+    pub rope: DisciplineProgression,
+}
+
+fn median(grades: &[Grade]) -> Option<Grade> {
+    let mut sorted = grades.to_vec();
+    sorted.sort();
+    // Lower middle for even counts, so the median is always a grade that was actually sent
+    sorted.get(sorted.len().saturating_sub(1) / 2).copied()
+}
+
+fn top_n(grades: &[Grade], n: usize) -> Vec<Grade> {
+    let mut sorted = grades.to_vec();
+    sorted.sort_by(|a, b| b.cmp(a));
+    sorted.truncate(n);
+    sorted
+}
+
+fn build_discipline(
+    sends: &[(NaiveDate, Grade)],
+    period: Period,
+    window: usize,
+    n: usize) -> DisciplineProgression {
+
+    let mut buckets: BTreeMap<NaiveDate, Vec<Grade>> = BTreeMap::new();
+    for (date, grade) in sends {
+        buckets.entry(period.start(*date)).or_default().push(*grade);
+    }
+
+    let mut milestones: BTreeMap<Grade, NaiveDate> = BTreeMap::new();
+    for (date, grade) in sends {
+        milestones
+            .entry(*grade)
+            .and_modify(|d| *d = (*d).min(*date))
+            .or_insert(*date);
+    }
+
+    let mut points = Vec::new();
+    let (Some(first), Some(last)) = (buckets.keys().next().copied(), buckets.keys().last().copied()) else {
+        return DisciplineProgression::default();
+    };
+    // Empty periods stay in the series so charts keep an even time axis
+    let mut recent: Vec<Vec<Grade>> = Vec::new();
+    let mut start = first;
+    while start <= last {
+        let grades = buckets.remove(&start).unwrap_or_default();
+        recent.push(grades.clone());
+        if recent.len() > window {
+            recent.remove(0);
+        }
+        let windowed: Vec<Grade> = recent.iter().flatten().copied().collect();
+        points.push(ProgressionPoint {
+            period: period.label(start),
+            start,
+            sends: grades.len(),
+            max_grade: grades.iter().max().map(|g| g.to_string()),
+            rolling_max: windowed.iter().max().map(|g| g.to_string()),
+            median_top_n: median(&top_n(&windowed, n)).map(|g| g.to_string()),
+        });
+        start = period.next(start);
+    }
+
+    let mut milestones: Vec<Milestone> = milestones
+        .into_iter()
+        .map(|(grade, date)| Milestone { grade: grade.to_string(), date })
+        .collect();
+    milestones.sort_by_key(|m| m.date);
+
+    DisciplineProgression { points, milestones }
+}
+
+pub fn build_progression(
+    sessions: &[ClimbingSession],
+    range: &DateRange,
+    period: Period,
+    window: usize,
+    n: usize) -> Progression {
+
+    let mut boulder = Vec::new();
+    let mut rope = Vec::new();
+    for session in sessions.iter().filter(|s| range.contains(&s.date)) {
+        let Some(date) = parse_date(&session.date) else {
+            continue;
+        };
+        for climb in session.climbs.iter().filter(|c| c.sent) {
+            match climb.grade {
+                Grade::Boulder(_) => boulder.push((date, climb.grade)),
+                Grade::Rope(_) => rope.push((date, climb.grade)),
+            }
+        }
+    }
+
+    Progression {
+        boulder: build_discipline(&boulder, period, window, n),
+        rope: build_discipline(&rope, period, window, n),
+    }
+}
+
+fn format_discipline(out: &mut String, title: &str, progression: &DisciplineProgression) {
+    if progression.points.is_empty() {
+        return;
+    }
+    out.push_str(&format!("{}\n", title));
+    out.push_str(&format!("{:<10} {:>6} {:>8} {:>8} {:>8}\n", "PERIOD", "SENDS", "MAX", "ROLLING", "MEDIAN"));
+    for point in &progression.points {
+        out.push_str(&format!(
+            "{:<10} {:>6} {:>8} {:>8} {:>8}\n",
+            point.period,
+            point.sends,
+            point.max_grade.as_deref().unwrap_or("-"),
+            point.rolling_max.as_deref().unwrap_or("-"),
+            point.median_top_n.as_deref().unwrap_or("-"),
+        ));
+    }
+    for milestone in &progression.milestones {
+        out.push_str(&format!("{} first send at {}\n", milestone.date, milestone.grade));
+    }
+    out.push('\n');
+}
+
+pub fn format_progression(progression: &Progression) -> String {
+    let mut out = String::new();
+    format_discipline(&mut out, "Boulder", &progression.boulder);
+    format_discipline(&mut out, "Rope", &progression.rope);
+    if out.is_empty() {
+        out.push_str("No sends logged in this range\n");
+    }
+    out
+}
+
+pub fn print_progression(user: Option<Uuid>, range: &DateRange, period: Period, json: bool) {
+    let sessions: Vec<ClimbingSession> = load_logs(user, is_climb);
+    let progression = build_progression(&sessions, range, period, DEFAULT_WINDOW, DEFAULT_TOP_N);
+    if json {
+        match serde_json::to_string_pretty(&progression) {
+            Ok(out) => println!("{}", out),
+            Err(e) => error!("error {e} serializing progression"),
+        }
+    } else {
+        print!("{}", format_progression(&progression));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::climblib::models::{BoulderGrade, ClimbStyle};
+    use crate::climblib::test_support::{climb, session};
+
+    fn boulder(grade: BoulderGrade, sent: bool) -> crate::climblib::models::ClimbEntry {
+        climb(Grade::Boulder(grade), 1, sent, false)
+    }
+
+    #[test]
+    fn test_build_progression_monthly() {
+        let sessions = vec![
+            session("2024-03-05", ClimbStyle::Boulder, vec![
+                boulder(BoulderGrade::V3, true),
+                boulder(BoulderGrade::V4, true),
+                boulder(BoulderGrade::V6, false),
+            ]),
+            session("2024-05-10", ClimbStyle::Boulder, vec![
+                boulder(BoulderGrade::V5, true),
+                boulder(BoulderGrade::V3, true),
+            ]),
+        ];
+
+        let progression = build_progression(&sessions, &DateRange::default(), Period::Month, 2, 3);
+        let points = &progression.boulder.points;
+
+        assert_eq!(points.len(), 3);
+        assert_eq!(points[0].max_grade.as_deref(), Some("v4"));
+        assert_eq!(points[1].period, "2024-04");
+        assert_eq!(points[1].max_grade, None);
+        assert_eq!(points[1].rolling_max.as_deref(), Some("v4"));
+        assert_eq!(points[2].rolling_max.as_deref(), Some("v5"));
+        assert_eq!(points[2].median_top_n.as_deref(), Some("v3"));
+        assert_eq!(progression.boulder.milestones.len(), 3);
+        assert_eq!(progression.boulder.milestones[2].grade, "v5");
+        assert!(progression.rope.points.is_empty());
+    }
+
+    #[test]
+    fn test_median_picks_a_sent_grade() {
+        let grades = [Grade::Boulder(BoulderGrade::V2), Grade::Boulder(BoulderGrade::V5)];
+        assert_eq!(median(&grades), Some(Grade::Boulder(BoulderGrade::V2)));
+        assert_eq!(median(&[]), None);
+    }
+}
@@ -0,0 +1,148 @@
+use super::io::load_logs;
+use super::models::{ClimbingSession, ClimbEntry, Grade};
+use super::utils::{is_climb, DateRange};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tracing::error;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Default, Clone, Serialize, PartialEq, ToSchema)]
+pub struct PyramidRow {
+    pub grade: String,
+    pub sends: u32,
+    pub attempts: u32,
+    pub flashes: u32,
+}
+
+#[derive(Debug, Default, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Pyramid {
+    pub boulder: Vec<PyramidRow>,
+    pub lead: Vec<PyramidRow>,
+    pub top_rope: Vec<PyramidRow>,
+}
+
+pub fn is_flash(climb: &ClimbEntry) -> bool {
+    climb.sent && climb.attempts <= 1
+}
+
+fn add_climb(rows: &mut BTreeMap<Grade, PyramidRow>, climb: &ClimbEntry) {
+    let row = rows.entry(climb.grade).or_insert_with(|| PyramidRow {
+        grade: climb.grade.to_string(),
+        ..Default::default()
+    });
+    row.attempts += climb.attempts as u32;
+    if climb.sent {
+        row.sends += 1;
+    }
+    if is_flash(climb) {
+        row.flashes += 1;
+    }
+}
+
+// Rows are ordered hardest grade first so the output reads like a pyramid
+fn into_rows(rows: BTreeMap<Grade, PyramidRow>) -> Vec<PyramidRow> {
+    rows.into_values().rev().collect()
+}
+
+pub fn build_pyramid(sessions: &[ClimbingSession], range: &DateRange) -> Pyramid {
+    let mut boulder = BTreeMap::new();
+    let mut lead = BTreeMap::new();
+    let mut top_rope = BTreeMap::new();
+
+    for session in sessions.iter().filter(|s| range.contains(&s.date)) {
+        for climb in &session.climbs {
+            match climb.grade {
+                Grade::Boulder(_) => add_climb(&mut boulder, climb),
+                Grade::Rope(_) if climb.lead => add_climb(&mut lead, climb),
+                Grade::Rope(_) => add_climb(&mut top_rope, climb),
+            }
+        }
+    }
+
+    Pyramid {
+        boulder: into_rows(boulder),
+        lead: into_rows(lead),
+        top_rope: into_rows(top_rope),
+    }
+}
+
+fn format_section(out: &mut String, title: &str, rows: &[PyramidRow]) {
+    if rows.is_empty() {
+        return;
+    }
+    out.push_str(&format!("{}\n", title));
+    out.push_str(&format!("{:<8} {:>6} {:>9} {:>8}\n", "GRADE", "SENDS", "ATTEMPTS", "FLASHES"));
+    for row in rows {
+        out.push_str(&format!(
+            "{:<8} {:>6} {:>9} {:>8}  {}\n",
+            row.grade, row.sends, row.attempts, row.flashes, "#".repeat(row.sends as usize)
+        ));
+    }
+    out.push('\n');
+}
+
+pub fn format_pyramid(pyramid: &Pyramid) -> String {
+    let mut out = String::new();
+    format_section(&mut out, "Boulder", &pyramid.boulder);
+    format_section(&mut out, "Rope (lead)", &pyramid.lead);
+    format_section(&mut out, "Rope (top-rope)", &pyramid.top_rope);
+    if out.is_empty() {
+        out.push_str("No climbs logged in this range\n");
+    }
+    out
+}
+
+pub fn print_pyramid(user: Option<Uuid>, range: &DateRange, json: bool) {
+    let sessions: Vec<ClimbingSession> = load_logs(user, is_climb);
+    let pyramid = build_pyramid(&sessions, range);
+    if json {
+        match serde_json::to_string_pretty(&pyramid) {
+            Ok(out) => println!("{}", out),
+            Err(e) => error!("error {e} serializing pyramid"),
+        }
+    } else {
+        print!("{}", format_pyramid(&pyramid));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::climblib::test_support::{climb, session};
+    use crate::climblib::models::{BoulderGrade, ClimbStyle, RopeGrade};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_build_pyramid_splits_by_discipline() {
+        let sessions = vec![
+            session("2024-04-01", ClimbStyle::Boulder, vec![
+                climb(Grade::Boulder(BoulderGrade::V4), 1, true, false),
+                climb(Grade::Boulder(BoulderGrade::V4), 3, true, false),
+                climb(Grade::Boulder(BoulderGrade::V6), 5, false, false),
+            ]),
+            session("2024-04-03", ClimbStyle::Rope, vec![
+                climb(Grade::Rope(RopeGrade::FiveElevenA), 2, true, true),
+                climb(Grade::Rope(RopeGrade::FiveTenA), 1, true, false),
+            ]),
+            session("2024-05-01", ClimbStyle::Boulder, vec![
+                climb(Grade::Boulder(BoulderGrade::V7), 1, true, false),
+            ]),
+        ];
+        let april = DateRange::new(
+            NaiveDate::from_ymd_opt(2024, 4, 1),
+            NaiveDate::from_ymd_opt(2024, 4, 30),
+        );
+
+        let pyramid = build_pyramid(&sessions, &april);
+
+        assert_eq!(pyramid.boulder, vec![
+            PyramidRow { grade: "v6".to_string(), sends: 0, attempts: 5, flashes: 0 },
+            PyramidRow { grade: "v4".to_string(), sends: 2, attempts: 4, flashes: 1 },
+        ]);
+        assert_eq!(pyramid.lead.len(), 1);
+        assert_eq!(pyramid.lead[0].grade, "5.11a");
+        assert_eq!(pyramid.top_rope[0].flashes, 1);
+    }
+}
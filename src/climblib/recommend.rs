@@ -0,0 +1,190 @@
+use super::load::{acwr, climbing_daily_load, DEFAULT_ACWR_THRESHOLD};
+use super::models::{ClimbEntry, ClimbingSession, Grade};
+use super::sends::grade_stats;
+use super::utils::DateRange;
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+pub const HISTORY_DAYS: i64 = 90;
+pub const VOLUME_SEND_RATE: f64 = 0.8;
+pub const MIN_CLIMBS: u32 = 3;
+pub const EASY_ATTEMPTS: f64 = 2.0;
+pub const HARD_ATTEMPTS: f64 = 5.0;
+pub const LOW_ACWR: f64 = 0.8;
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+pub struct GradeRange {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DisciplineRecommendation {
+    pub max_sent: String,
+    pub project_score: i32,
+    pub project: GradeRange,
+    pub volume: GradeRange,
+    pub reasons: Vec<String>,
+}
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Recommendations {
+    pub today: NaiveDate,
+    pub climbing_acwr: Option<f64>,
+    pub boulder: Option<DisciplineRecommendation>,
+    pub rope: Option<DisciplineRecommendation>,
+}
+
+fn range(from: Grade, to: Grade) -> GradeRange {
+    GradeRange { from: from.to_string(), to: to.to_string() }
+}
+
+// The project score is how many grades above the hardest recent send to aim for. It starts at
+// one, goes up when that grade is going down quickly, and down when it is still a fight or
+// recent load is spiking. The volume range sits at the hardest grade that is sent reliably.
+fn recommend_discipline(climbs: &[&ClimbEntry], climbing_acwr: Option<f64>) -> Option<DisciplineRecommendation> {
+    let mut by_grade: BTreeMap<Grade, Vec<&ClimbEntry>> = BTreeMap::new();
+    for climb in climbs {
+        by_grade.entry(climb.grade).or_default().push(climb);
+    }
+    let stats: BTreeMap<Grade, _> = by_grade.into_iter().map(|(g, c)| (g, grade_stats(&c))).collect();
+    let (max_sent, max_stats) = stats.iter().rev().find(|(_, s)| s.send_rate > 0.0)?;
+
+    let mut reasons = vec![format!("Hardest send in the last {} days is {}", HISTORY_DAYS, max_sent)];
+    let mut score = 1;
+    match max_stats.avg_attempts_to_send {
+        Some(attempts) if attempts <= EASY_ATTEMPTS => {
+            score += 1;
+            reasons.push(format!("{} goes in {:.1} attempts on average, +1", max_sent, attempts));
+        }
+        Some(attempts) if attempts >= HARD_ATTEMPTS => {
+            score -= 1;
+            reasons.push(format!("{} still takes {:.1} attempts on average, -1", max_sent, attempts));
+        }
+        _ => {}
+    }
+
+    let overloaded = climbing_acwr.is_some_and(|r| r > DEFAULT_ACWR_THRESHOLD);
+    match climbing_acwr {
+        Some(r) if overloaded => {
+            score -= 1;
+            reasons.push(format!("Climbing ACWR is {:.2}, above {}, so back off this session, -1", r, DEFAULT_ACWR_THRESHOLD));
+        }
+        Some(r) if r < LOW_ACWR => {
+            reasons.push(format!("Climbing ACWR is {:.2}, below {}, so there is room to add volume", r, LOW_ACWR));
+        }
+        _ => {}
+    }
+    let score = score.max(0);
+
+    let consolidated = stats
+        .iter()
+        .rev()
+        .find(|(_, s)| s.send_rate >= VOLUME_SEND_RATE && s.climbs >= MIN_CLIMBS)
+        .map(|(g, _)| *g);
+    let volume_top = match consolidated {
+        Some(grade) => {
+            reasons.push(format!(
+                "{} is the hardest grade sent at least {:.0}% of the time over {}+ climbs",
+                grade, VOLUME_SEND_RATE * 100.0, MIN_CLIMBS
+            ));
+            grade
+        }
+        None => {
+            reasons.push(format!("No grade is sent reliably yet, so volume sits two grades below {}", max_sent));
+            max_sent.step(-2)
+        }
+    };
+    let volume_top = if overloaded { volume_top.step(-1) } else { volume_top };
+
+    Some(DisciplineRecommendation {
+        max_sent: max_sent.to_string(),
+        project_score: score,
+        project: range(max_sent.step(score), max_sent.step(score + 1)),
+        volume: range(volume_top.step(-1), volume_top),
+        reasons,
+    })
+}
+
+pub fn build_recommendations(sessions: &[ClimbingSession], today: NaiveDate) -> Recommendations {
+    let recent_range = DateRange::new(Some(today - Duration::days(HISTORY_DAYS - 1)), Some(today));
+    let recent: Vec<&ClimbingSession> = sessions.iter().filter(|s| recent_range.contains(&s.date)).collect();
+    let boulder: Vec<&ClimbEntry> = recent.iter().flat_map(|s| &s.climbs).filter(|c| matches!(c.grade, Grade::Boulder(_))).collect();
+    let rope: Vec<&ClimbEntry> = recent.iter().flat_map(|s| &s.climbs).filter(|c| matches!(c.grade, Grade::Rope(_))).collect();
+    let climbing_acwr = acwr(&climbing_daily_load(sessions), today);
+
+    Recommendations {
+        today,
+        climbing_acwr,
+        boulder: recommend_discipline(&boulder, climbing_acwr),
+        rope: recommend_discipline(&rope, climbing_acwr),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::climblib::models::{BoulderGrade, ClimbStyle};
+    use crate::climblib::test_support::{climb, session};
+
+    fn v(grade: BoulderGrade, attempts: u8, sent: bool) -> ClimbEntry {
+        climb(Grade::Boulder(grade), attempts, sent, false)
+    }
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 4, 30).unwrap()
+    }
+
+    #[test]
+    fn test_grade_step_clamps() {
+        assert_eq!(Grade::Boulder(BoulderGrade::V5).step(2), Grade::Boulder(BoulderGrade::V7));
+        assert_eq!(Grade::Boulder(BoulderGrade::V0).step(-3), Grade::Boulder(BoulderGrade::VIntro));
+        assert_eq!(Grade::Boulder(BoulderGrade::V17).step(1), Grade::Boulder(BoulderGrade::V17));
+    }
+
+    #[test]
+    fn test_recommendations_step_up_after_quick_sends() {
+        let sessions = vec![
+            session("2024-04-01", ClimbStyle::Boulder, vec![
+                v(BoulderGrade::V4, 1, true), v(BoulderGrade::V4, 1, true), v(BoulderGrade::V4, 2, true),
+                v(BoulderGrade::V5, 2, true), v(BoulderGrade::V6, 3, false),
+            ]),
+            session("2024-04-08", ClimbStyle::Boulder, vec![v(BoulderGrade::V4, 1, true)]),
+            session("2024-04-15", ClimbStyle::Boulder, vec![v(BoulderGrade::V4, 1, true)]),
+            session("2024-04-22", ClimbStyle::Boulder, vec![v(BoulderGrade::V4, 1, true)]),
+            session("2024-04-29", ClimbStyle::Boulder, vec![v(BoulderGrade::V4, 1, true)]),
+            session("2023-10-01", ClimbStyle::Boulder, vec![v(BoulderGrade::V9, 1, true)]),
+        ];
+
+        let recommendations = build_recommendations(&sessions, today());
+        let boulder = recommendations.boulder.unwrap();
+
+        assert_eq!(boulder.max_sent, "v5");
+        assert_eq!(boulder.project_score, 2);
+        assert_eq!(boulder.project, GradeRange { from: "v7".to_string(), to: "v8".to_string() });
+        assert_eq!(boulder.volume, GradeRange { from: "v3".to_string(), to: "v4".to_string() });
+        assert_eq!(boulder.reasons.len(), 3);
+        assert!(recommendations.rope.is_none());
+    }
+
+    #[test]
+    fn test_recommendations_back_off_under_load_spike() {
+        let sessions = vec![
+            session("2024-04-01", ClimbStyle::Boulder, vec![v(BoulderGrade::V3, 1, true)]),
+            session("2024-04-15", ClimbStyle::Boulder, vec![v(BoulderGrade::V3, 1, true)]),
+            session("2024-04-29", ClimbStyle::Boulder, vec![
+                v(BoulderGrade::V5, 6, true), v(BoulderGrade::V5, 6, false), v(BoulderGrade::V5, 6, false),
+            ]),
+        ];
+
+        let boulder = build_recommendations(&sessions, today()).boulder.unwrap();
+
+        assert_eq!(boulder.project_score, 0);
+        assert_eq!(boulder.project.from, "v5");
+        assert_eq!(boulder.volume, GradeRange { from: "v1".to_string(), to: "v2".to_string() });
+    }
+}
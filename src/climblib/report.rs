@@ -0,0 +1,384 @@
+use super::io::load_logs;
+use super::lifting::{detect_prs, PersonalRecord};
+use super::models::{ClimbEntry, ClimbMetricsEntry, ClimbingSession, Grade, WorkoutSession};
+use super::pyramid::{build_pyramid, Pyramid, PyramidRow};
+use super::summary::{lifting_volume, metric_changes, LiftVolume, MetricChange};
+use super::utils::{is_climb, is_metrics, is_workout, DateRange, Period};
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+pub const REPORT_DIR: &str = "reports";
+pub const TOP_SENDS: usize = 5;
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TopSend {
+    pub date: String,
+    pub grade: String,
+    pub name: Option<String>,
+    pub attempts: u8,
+}
+
+// Rope and boulder grades sit on different scales, so each gets its own list
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct TopSends {
+    pub boulder: Vec<TopSend>,
+    pub rope: Vec<TopSend>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteHighlight {
+    pub date: String,
+    pub source: String,
+    pub note: String,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    pub period: String,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub climbing_sessions: usize,
+    pub climbs: usize,
+    pub sends: usize,
+    pub workouts: usize,
+    pub metric_tests: usize,
+    pub pyramid: Pyramid,
+    pub top_sends: TopSends,
+    pub lifting_volume: Vec<LiftVolume>,
+    pub total_volume_lb: f64,
+    pub prs: Vec<PersonalRecord>,
+    pub metric_changes: Vec<MetricChange>,
+    pub notes: Vec<NoteHighlight>,
+}
+
+pub fn report_range(period: Period, end: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let start = period.start(end);
+    (start, period.next(start) - Duration::days(1))
+}
+
+fn note(date: &str, source: &str, note: &Option<String>) -> Option<NoteHighlight> {
+    let note = note.as_deref().map(str::trim).filter(|n| !n.is_empty())?;
+    Some(NoteHighlight { date: date.to_string(), source: source.to_string(), note: note.to_string() })
+}
+
+// Hardest first, with fewer attempts breaking ties. Only compare sends on the same scale.
+fn top_sends(mut sends: Vec<(&ClimbingSession, &ClimbEntry)>) -> Vec<TopSend> {
+    sends.sort_by(|a, b| b.1.grade.rank().cmp(&a.1.grade.rank()).then(a.1.attempts.cmp(&b.1.attempts)));
+    sends
+        .into_iter()
+        .take(TOP_SENDS)
+        .map(|(s, c)| TopSend { date: s.date.clone(), grade: c.grade.to_string(), name: c.name.clone(), attempts: c.attempts })
+        .collect()
+}
+
+pub fn build_report(
+    sessions: &[ClimbingSession],
+    mut workouts: Vec<WorkoutSession>,
+    metrics: &[ClimbMetricsEntry],
+    period: Period,
+    end: NaiveDate) -> Report {
+
+    let (from, to) = report_range(period, end);
+    let range = DateRange::new(Some(from), Some(to));
+    let in_range: Vec<&ClimbingSession> = sessions.iter().filter(|s| range.contains(&s.date)).collect();
+
+    let sends: Vec<(&ClimbingSession, &ClimbEntry)> = in_range
+        .iter()
+        .flat_map(|s| s.climbs.iter().filter(|c| c.sent).map(move |c| (*s, c)))
+        .collect();
+    let send_count = sends.len();
+    let (boulder, rope): (Vec<_>, Vec<_>) = sends.into_iter().partition(|(_, c)| matches!(c.grade, Grade::Boulder(_)));
+    let top_sends = TopSends { boulder: top_sends(boulder), rope: top_sends(rope) };
+
+    // ISO dates sort lexicographically, so everything before the partition point is earlier history
+    workouts.sort_by(|a, b| a.date.cmp(&b.date));
+    let in_range_workouts: Vec<&WorkoutSession> = workouts.iter().filter(|w| range.contains(&w.date)).collect();
+    let mut prs = Vec::new();
+    for workout in &in_range_workouts {
+        let before = workouts.partition_point(|w| w.date < workout.date);
+        prs.extend(detect_prs(&workouts[..before], workout));
+    }
+    let lifting_volume = lifting_volume(&in_range_workouts);
+
+    let mut tests: Vec<&ClimbMetricsEntry> = metrics.iter().filter(|m| range.contains(&m.date)).collect();
+    tests.sort_by(|a, b| a.date.cmp(&b.date));
+    let metric_changes = metric_changes(&tests);
+
+    let mut notes: Vec<NoteHighlight> = in_range
+        .iter()
+        .filter_map(|s| note(&s.date, "climb", &s.notes))
+        .chain(in_range_workouts.iter().filter_map(|w| note(&w.date, "workout", &w.notes)))
+        .chain(tests.iter().filter_map(|m| note(&m.date, "metrics", &m.notes)))
+        .collect();
+    notes.sort_by(|a, b| a.date.cmp(&b.date));
+
+    Report {
+        period: period.label(from),
+        from,
+        to,
+        climbing_sessions: in_range.len(),
+        climbs: in_range.iter().map(|s| s.climbs.len()).sum(),
+        sends: send_count,
+        workouts: in_range_workouts.len(),
+        metric_tests: tests.len(),
+        pyramid: build_pyramid(sessions, &range),
+        top_sends,
+        total_volume_lb: lifting_volume.iter().map(|v| v.volume_lb).sum(),
+        lifting_volume,
+        prs,
+        metric_changes,
+        notes,
+    }
+}
+
+fn describe_pr(pr: &PersonalRecord) -> String {
+    match pr {
+        PersonalRecord::EstimatedOneRepMax { exercise, e1rm, previous } => format!(
+            "{} estimated 1RM {:.0} lb{}",
+            exercise, e1rm, previous.map_or(String::new(), |p| format!(" (was {:.0})", p))
+        ),
+        PersonalRecord::RepMax { exercise, reps, weight_lb, previous } => format!(
+            "{} {}RM {} lb{}",
+            exercise, reps, weight_lb, previous.map_or(String::new(), |p| format!(" (was {})", p))
+        ),
+    }
+}
+
+fn pyramid_sections(pyramid: &Pyramid) -> [(&'static str, &Vec<PyramidRow>); 3] {
+    [("Boulder", &pyramid.boulder), ("Lead", &pyramid.lead), ("Top rope", &pyramid.top_rope)]
+}
+
+fn top_send_sections(top_sends: &TopSends) -> [(&'static str, &Vec<TopSend>); 2] {
+    [("Boulder", &top_sends.boulder), ("Rope", &top_sends.rope)]
+}
+
+fn describe_send(send: &TopSend) -> String {
+    format!(
+        "{} {}{} in {} attempts",
+        send.date, send.grade, send.name.as_deref().map_or(String::new(), |n| format!(" {}", n)), send.attempts
+    )
+}
+
+pub fn render_markdown(report: &Report) -> String {
+    let mut out = format!("# Training report {}\n\n{} to {}\n\n", report.period, report.from, report.to);
+
+    out.push_str("## Sessions\n\n");
+    out.push_str(&format!(
+        "- Climbing sessions: {} ({} climbs, {} sends)\n- Workouts: {}\n- Metric tests: {}\n\n",
+        report.climbing_sessions, report.climbs, report.sends, report.workouts, report.metric_tests
+    ));
+
+    out.push_str("## Climbs by grade\n\n| Discipline | Grade | Sends | Attempts | Flashes |\n|---|---|---|---|---|\n");
+    for (title, rows) in pyramid_sections(&report.pyramid) {
+        for row in rows {
+            out.push_str(&format!("| {} | {} | {} | {} | {} |\n", title, row.grade, row.sends, row.attempts, row.flashes));
+        }
+    }
+
+    out.push_str("\n## Top sends\n");
+    for (title, sends) in top_send_sections(&report.top_sends) {
+        if sends.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("\n### {}\n\n", title));
+        for send in sends {
+            out.push_str(&format!("- {}\n", describe_send(send)));
+        }
+    }
+
+    out.push_str(&format!("\n## Lifting volume\n\nTotal {:.0} lb\n\n| Exercise | Volume (lb) |\n|---|---|\n", report.total_volume_lb));
+    for volume in &report.lifting_volume {
+        out.push_str(&format!("| {} | {:.0} |\n", volume.exercise, volume.volume_lb));
+    }
+
+    out.push_str("\n## Personal records\n\n");
+    for pr in &report.prs {
+        out.push_str(&format!("- {}\n", describe_pr(pr)));
+    }
+
+    out.push_str("\n## Metric changes\n\n");
+    for change in &report.metric_changes {
+        out.push_str(&format!("- {}: {:.1} -> {:.1} ({:+.1})\n", change.metric, change.first, change.last, change.change));
+    }
+
+    out.push_str("\n## Notes\n\n");
+    for note in &report.notes {
+        out.push_str(&format!("- {} ({}): {}\n", note.date, note.source, note.note));
+    }
+    out
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+pub fn svg_bar_chart(bars: &[(String, f64)]) -> String {
+    const WIDTH: f64 = 480.0;
+    const LABEL: f64 = 90.0;
+    const ROW: f64 = 22.0;
+    let max = bars.iter().map(|(_, v)| *v).fold(0.0, f64::max);
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="sans-serif" font-size="12">"#,
+        WIDTH, ROW * bars.len() as f64
+    );
+    for (i, (label, value)) in bars.iter().enumerate() {
+        let y = i as f64 * ROW;
+        let width = if max > 0.0 { (WIDTH - LABEL - 60.0) * value / max } else { 0.0 };
+        svg.push_str(&format!(
+            r##"<text x="0" y="{:.0}">{}</text><rect x="{}" y="{:.0}" width="{:.1}" height="16" fill="#4f7cac"/><text x="{:.1}" y="{:.0}">{:.0}</text>"##,
+            y + 15.0, escape(label), LABEL, y + 3.0, width, LABEL + width + 4.0, y + 15.0, value
+        ));
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+fn html_list(items: impl Iterator<Item = String>) -> String {
+    let items: String = items.map(|i| format!("<li>{}</li>", escape(&i))).collect();
+    format!("<ul>{}</ul>\n", items)
+}
+
+pub fn render_html(report: &Report) -> String {
+    let title = format!("Training report {}", report.period);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{0}</title>\n\
+         <style>body{{font-family:sans-serif;max-width:760px;margin:auto}}table{{border-collapse:collapse}}td,th{{border:1px solid #ccc;padding:2px 8px}}</style>\n\
+         </head><body>\n<h1>{0}</h1>\n<p>{1} to {2}</p>\n",
+        escape(&title), report.from, report.to
+    );
+
+    out.push_str("<h2>Sessions</h2>\n");
+    out.push_str(&html_list([
+        format!("Climbing sessions: {} ({} climbs, {} sends)", report.climbing_sessions, report.climbs, report.sends),
+        format!("Workouts: {}", report.workouts),
+        format!("Metric tests: {}", report.metric_tests),
+    ].into_iter()));
+
+    out.push_str("<h2>Climbs by grade</h2>\n");
+    for (title, rows) in pyramid_sections(&report.pyramid) {
+        if rows.is_empty() {
+            continue;
+        }
+        let bars: Vec<(String, f64)> = rows.iter().map(|r| (r.grade.clone(), r.sends as f64)).collect();
+        out.push_str(&format!("<h3>{}</h3>\n{}\n", title, svg_bar_chart(&bars)));
+    }
+
+    out.push_str("<h2>Top sends</h2>\n");
+    for (title, sends) in top_send_sections(&report.top_sends) {
+        if sends.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("<h3>{}</h3>\n{}", title, html_list(sends.iter().map(describe_send))));
+    }
+
+    out.push_str(&format!("<h2>Lifting volume</h2>\n<p>Total {:.0} lb</p>\n", report.total_volume_lb));
+    let bars: Vec<(String, f64)> = report.lifting_volume.iter().map(|v| (v.exercise.clone(), v.volume_lb)).collect();
+    out.push_str(&svg_bar_chart(&bars));
+
+    out.push_str("\n<h2>Personal records</h2>\n");
+    out.push_str(&html_list(report.prs.iter().map(describe_pr)));
+
+    out.push_str("<h2>Metric changes</h2>\n");
+    out.push_str(&html_list(report.metric_changes.iter().map(|c| format!(
+        "{}: {:.1} -> {:.1} ({:+.1})", c.metric, c.first, c.last, c.change
+    ))));
+
+    out.push_str("<h2>Notes</h2>\n");
+    out.push_str(&html_list(report.notes.iter().map(|n| format!("{} ({}): {}", n.date, n.source, n.note))));
+
+    out.push_str("</body></html>\n");
+    out
+}
+
+pub fn write_report(user: Option<Uuid>, period: Period, end: NaiveDate) -> io::Result<(PathBuf, PathBuf)> {
+    let sessions: Vec<ClimbingSession> = load_logs(user, is_climb);
+    let workouts: Vec<WorkoutSession> = load_logs(user, is_workout);
+    let metrics: Vec<ClimbMetricsEntry> = load_logs(user, is_metrics);
+    let report = build_report(&sessions, workouts, &metrics, period, end);
+
+    fs::create_dir_all(REPORT_DIR)?;
+    let markdown = PathBuf::from(REPORT_DIR).join(format!("{}.md", report.period));
+    let html = PathBuf::from(REPORT_DIR).join(format!("{}.html", report.period));
+    fs::write(&markdown, render_markdown(&report))?;
+    fs::write(&html, render_html(&report))?;
+    Ok((markdown, html))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::climblib::test_support::{climb, exercise, session, workout};
+    use crate::climblib::models::{BoulderGrade, ClimbStyle, RopeGrade};
+
+    fn date(m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_report_range() {
+        assert_eq!(report_range(Period::Week, date(4, 10)), (date(4, 8), date(4, 14)));
+        assert_eq!(report_range(Period::Month, date(2, 10)), (date(2, 1), date(2, 29)));
+    }
+
+    #[test]
+    fn test_build_report() {
+        let mut hard = session("2024-04-09", ClimbStyle::Boulder, vec![
+            climb(Grade::Boulder(BoulderGrade::V6), 4, true, false),
+            climb(Grade::Boulder(BoulderGrade::V3), 1, true, false),
+            climb(Grade::Boulder(BoulderGrade::V7), 2, false, false),
+        ]);
+        hard.notes = Some("  Finally sent the crimpy V6 ".to_string());
+        let sessions = vec![
+            hard,
+            session("2024-04-02", ClimbStyle::Boulder, vec![climb(Grade::Boulder(BoulderGrade::V8), 1, true, false)]),
+            session("2024-04-10", ClimbStyle::Rope, vec![climb(Grade::Rope(RopeGrade::FiveTenA), 1, true, true)]),
+        ];
+        let workouts = vec![
+            workout("2024-04-10", vec![exercise("Squat", 5, 200, None)]),
+            workout("2024-04-01", vec![exercise("Squat", 5, 190, None)]),
+        ];
+        let metrics = vec![
+            ClimbMetricsEntry { id: None, date: "2024-04-08".to_string(), finger_strength_percent_bw: Some(120.0), max_pullup_percent_bw: None, notes: None },
+            ClimbMetricsEntry { id: None, date: "2024-04-14".to_string(), finger_strength_percent_bw: Some(124.0), max_pullup_percent_bw: None, notes: None },
+        ];
+
+        let report = build_report(&sessions, workouts, &metrics, Period::Week, date(4, 10));
+
+        assert_eq!(report.period, "2024-W15");
+        assert_eq!((report.climbing_sessions, report.climbs, report.sends), (2, 4, 3));
+        assert_eq!(report.top_sends.boulder[0].grade, "v6");
+        assert_eq!(report.top_sends.boulder.len(), 2);
+        assert_eq!(report.top_sends.rope[0].grade, "5.10a");
+        assert_eq!(report.workouts, 1);
+        assert_eq!(report.total_volume_lb, 3000.0);
+        assert!(report.prs.contains(&PersonalRecord::RepMax {
+            exercise: "Squat".to_string(),
+            reps: 5,
+            weight_lb: 200,
+            previous: Some(190),
+        }));
+        assert_eq!(report.metric_changes.len(), 1);
+        assert_eq!(report.metric_changes[0].change, 4.0);
+        assert_eq!(report.notes[0].note, "Finally sent the crimpy V6");
+
+        let markdown = render_markdown(&report);
+        assert!(markdown.starts_with("# Training report 2024-W15"));
+        assert!(markdown.contains("| Boulder | v6 | 1 | 4 | 0 |"));
+        let html = render_html(&report);
+        assert!(html.contains("<svg"));
+    }
+
+    #[test]
+    fn test_svg_bar_chart_escapes_labels() {
+        let svg = svg_bar_chart(&[("<v1>".to_string(), 2.0), ("v2".to_string(), 1.0)]);
+        assert_eq!(svg.matches("<rect").count(), 2);
+        assert!(svg.contains("&lt;v1&gt;"));
+    }
+}
@@ -0,0 +1,193 @@
+use super::io::parse_records;
+use super::models::{ClimbEntry, ClimbMetricsEntry, ClimbingSession, WorkoutSession};
+use super::utils::infer_log_type;
+use jsonschema::JSONSchema;
+use schemars::schema_for;
+use serde::Serialize;
+use serde_json::Value;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogSchema {
+    Climb,
+    ClimbEntry,
+    Workout,
+    Metrics,
+}
+
+impl LogSchema {
+    pub const ALL: [LogSchema; 4] = [LogSchema::Climb, LogSchema::ClimbEntry, LogSchema::Workout, LogSchema::Metrics];
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            LogSchema::Climb => "ClimbingSession",
+            LogSchema::ClimbEntry => "ClimbEntry",
+            LogSchema::Workout => "WorkoutSession",
+            LogSchema::Metrics => "ClimbMetricsEntry",
+        }
+    }
+
+    // Draft 7 schemas, with the `#[validate]` length and range limits and the grade enums
+    pub fn schema(&self) -> Value {
+        let schema = match self {
+            LogSchema::Climb => schema_for!(ClimbingSession),
+            LogSchema::ClimbEntry => schema_for!(ClimbEntry),
+            LogSchema::Workout => schema_for!(WorkoutSession),
+            LogSchema::Metrics => schema_for!(ClimbMetricsEntry),
+        };
+        serde_json::to_value(schema).expect("schemas always serialize")
+    }
+
+    // Formats are checked too, which draft 7 validators skip by default
+    pub fn compile(&self) -> Result<JSONSchema, String> {
+        JSONSchema::options().should_validate_formats(true).compile(&self.schema()).map_err(|e| e.to_string())
+    }
+
+    // Picks the schema from a log file name, the same way the loaders do
+    pub fn for_path(path: &Path) -> Option<LogSchema> {
+        match infer_log_type(path)? {
+            "climb" => Some(LogSchema::Climb),
+            "workout" => Some(LogSchema::Workout),
+            "metrics" => Some(LogSchema::Metrics),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FileValidation {
+    pub file: PathBuf,
+    pub schema: Option<String>,
+    pub records: usize,
+    pub errors: Vec<String>,
+}
+
+impl FileValidation {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+// One error per failed keyword, prefixed with the JSON pointer to the offending value
+pub fn validate_value(schema: &JSONSchema, instance: &Value, prefix: &str) -> Vec<String> {
+    match schema.validate(instance) {
+        Ok(()) => vec![],
+        Err(errors) => errors.map(|e| format!("{}{}: {}", prefix, e.instance_path, e)).collect(),
+    }
+}
+
+pub fn validate_file(path: &Path, kind: Option<LogSchema>) -> Result<FileValidation, Box<dyn Error>> {
+    let Some(kind) = kind.or_else(|| LogSchema::for_path(path)) else {
+        return Ok(FileValidation {
+            file: path.to_path_buf(),
+            schema: None,
+            records: 0,
+            errors: vec!["can't tell the log type from the file name, pass --schema".to_string()],
+        });
+    };
+    let schema = kind.compile()?;
+    let records = parse_records(&fs::read_to_string(path)?)?;
+    let many = records.len() > 1;
+    let errors = records
+        .iter()
+        .enumerate()
+        .flat_map(|(i, record)| validate_value(&schema, record, &if many { format!("/{}", i) } else { String::new() }))
+        .collect();
+    Ok(FileValidation { file: path.to_path_buf(), schema: Some(kind.type_name().to_string()), records: records.len(), errors })
+}
+
+pub fn print_schema(kind: Option<LogSchema>) {
+    let schema = match kind {
+        Some(kind) => kind.schema(),
+        None => Value::Object(LogSchema::ALL.iter().map(|k| (k.type_name().to_string(), k.schema())).collect()),
+    };
+    match serde_json::to_string_pretty(&schema) {
+        Ok(out) => println!("{}", out),
+        Err(e) => error!("error {e} serializing schema"),
+    }
+}
+
+// Returns false when any file failed to parse or validate
+pub fn print_validation(paths: &[PathBuf], kind: Option<LogSchema>, json: bool) -> bool {
+    let mut results = Vec::new();
+    for path in paths {
+        match validate_file(path, kind) {
+            Ok(result) => results.push(result),
+            Err(e) => {
+                error!("error {e} reading {:?}", path);
+                results.push(FileValidation { file: path.clone(), schema: None, records: 0, errors: vec![e.to_string()] });
+            }
+        }
+    }
+    if json {
+        match serde_json::to_string_pretty(&results) {
+            Ok(out) => println!("{}", out),
+            Err(e) => error!("error {e} serializing validation results"),
+        }
+    } else {
+        for result in &results {
+            let status = if result.is_valid() { "ok" } else { "FAIL" };
+            println!("{:<5} {} ({} records)", status, result.file.display(), result.records);
+            for e in &result.errors {
+                println!("      {}", e);
+            }
+        }
+    }
+    results.iter().all(FileValidation::is_valid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn compiled(kind: LogSchema) -> JSONSchema {
+        kind.compile().unwrap()
+    }
+
+    #[test]
+    fn test_schemas_carry_constraints_and_grades() {
+        let entry = LogSchema::ClimbEntry.schema();
+        assert_eq!(entry["properties"]["attempts"]["maximum"], json!(100.0));
+        let grades = serde_json::to_string(&entry["definitions"]).unwrap();
+        assert!(grades.contains("\"5.10a\"") && grades.contains("\"vintro\""));
+
+        let metrics = LogSchema::Metrics.schema();
+        assert_eq!(metrics["properties"]["fingerStrengthPercentBw"]["minimum"], json!(100.0));
+        assert_eq!(LogSchema::Climb.schema()["title"], "ClimbingSession");
+    }
+
+    #[test]
+    fn test_validate_value() {
+        let schema = compiled(LogSchema::Climb);
+        let valid = json!({
+            "date": "2024-04-01", "location": "Movement", "style": "boulder",
+            "climbs": [{ "grade": "v4", "attempts": 2, "sent": true, "reachedTop": true, "lead": false }]
+        });
+        assert!(validate_value(&schema, &valid, "").is_empty());
+
+        let invalid = json!({
+            "date": "04/01/2024", "location": "Movement", "style": "boulder",
+            "climbs": [{ "grade": "v25", "attempts": 200, "sent": true, "reachedTop": true, "lead": false }]
+        });
+        let errors = validate_value(&schema, &invalid, "/3");
+        assert_eq!(errors.len(), 3);
+        assert!(errors.iter().any(|e| e.starts_with("/3/date")));
+        assert!(errors.iter().any(|e| e.starts_with("/3/climbs/0/attempts")));
+
+        let metrics = compiled(LogSchema::Metrics);
+        assert!(validate_value(&metrics, &json!({ "date": "2024-02-29" }), "").is_empty());
+        assert_eq!(validate_value(&metrics, &json!({ "date": "2024-13-45" }), "").len(), 1);
+        assert_eq!(validate_value(&metrics, &json!({ "date": "2023-02-29" }), "").len(), 1);
+    }
+
+    #[test]
+    fn test_infer_schema() {
+        assert_eq!(LogSchema::for_path(Path::new("logs/metrics-2024-04-01.json")), Some(LogSchema::Metrics));
+        assert_eq!(LogSchema::for_path(Path::new("export.json")), None);
+    }
+}
@@ -0,0 +1,241 @@
+use super::io::load_logs;
+use super::models::{ClimbEntry, ClimbingSession, Grade};
+use super::pyramid::is_flash;
+use super::utils::{is_climb, DateRange};
+use chrono::{Duration, NaiveDate, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tracing::error;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+pub const DEFAULT_PERIOD_DAYS: i64 = 28;
+
+#[derive(Debug, Clone, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GradeStats {
+    pub climbs: u32,
+    pub send_rate: f64,
+    pub avg_attempts_to_send: Option<f64>,
+    pub top_out_without_send_rate: f64,
+    pub flash_rate: f64,
+    pub avg_lead_rests: Option<f64>,
+}
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GradeComparison {
+    pub grade: String,
+    pub current: Option<GradeStats>,
+    pub previous: Option<GradeStats>,
+    pub send_rate_change: Option<f64>,
+}
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SendRateReport {
+    pub current: (NaiveDate, NaiveDate),
+    pub previous: (NaiveDate, NaiveDate),
+    pub grades: Vec<GradeComparison>,
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+pub fn grade_stats(climbs: &[&ClimbEntry]) -> GradeStats {
+    let total = climbs.len() as f64;
+    let rate = |count: usize| if total > 0.0 { count as f64 / total } else { 0.0 };
+    let attempts_to_send: Vec<f64> = climbs.iter()
+        .filter(|c| c.sent)
+        .map(|c| c.attempts as f64)
+        .collect();
+    let lead_rests: Vec<f64> = climbs.iter()
+        .filter(|c| c.lead)
+        .map(|c| c.rests.unwrap_or(0) as f64)
+        .collect();
+
+    GradeStats {
+        climbs: climbs.len() as u32,
+        send_rate: rate(climbs.iter().filter(|c| c.sent).count()),
+        avg_attempts_to_send: mean(&attempts_to_send),
+        top_out_without_send_rate: rate(climbs.iter().filter(|c| c.reached_top && !c.sent).count()),
+        flash_rate: rate(climbs.iter().filter(|c| is_flash(c)).count()),
+        avg_lead_rests: mean(&lead_rests),
+    }
+}
+
+pub fn stats_by_grade(sessions: &[ClimbingSession], range: &DateRange) -> BTreeMap<Grade, GradeStats> {
+    let mut by_grade: BTreeMap<Grade, Vec<&ClimbEntry>> = BTreeMap::new();
+    for session in sessions.iter().filter(|s| range.contains(&s.date)) {
+        for climb in &session.climbs {
+            by_grade.entry(climb.grade).or_default().push(climb);
+        }
+    }
+    by_grade.into_iter().map(|(g, climbs)| (g, grade_stats(&climbs))).collect()
+}
+
+// Without explicit bounds the current period is the last four weeks, and the previous
+// period is always the same number of days immediately before it
+pub fn comparison_periods(range: &DateRange, today: NaiveDate) -> (DateRange, DateRange) {
+    let to = range.to.unwrap_or(today);
+    let from = range.from.unwrap_or(to - Duration::days(DEFAULT_PERIOD_DAYS - 1));
+    let days = (to - from).num_days() + 1;
+    let previous_to = from - Duration::days(1);
+    let previous_from = previous_to - Duration::days(days - 1);
+    (
+        DateRange::new(Some(from), Some(to)),
+        DateRange::new(Some(previous_from), Some(previous_to)),
+    )
+}
+
+pub fn build_send_rates(sessions: &[ClimbingSession], range: &DateRange, today: NaiveDate) -> SendRateReport {
+    let (current_range, previous_range) = comparison_periods(range, today);
+    let mut current = stats_by_grade(sessions, &current_range);
+    let mut previous = stats_by_grade(sessions, &previous_range);
+
+    let mut grades: Vec<Grade> = current.keys().chain(previous.keys()).copied().collect();
+    grades.sort();
+    grades.dedup();
+
+    let grades = grades
+        .into_iter()
+        .rev()
+        .map(|grade| {
+            let current = current.remove(&grade);
+            let previous = previous.remove(&grade);
+            let send_rate_change = match (&current, &previous) {
+                (Some(c), Some(p)) => Some(c.send_rate - p.send_rate),
+                _ => None,
+            };
+            GradeComparison { grade: grade.to_string(), current, previous, send_rate_change }
+        })
+        .collect();
+
+    SendRateReport {
+        current: (current_range.from.unwrap_or(today), current_range.to.unwrap_or(today)),
+        previous: (previous_range.from.unwrap_or(today), previous_range.to.unwrap_or(today)),
+        grades,
+    }
+}
+
+fn percent(value: Option<f64>) -> String {
+    value.map_or("-".to_string(), |v| format!("{:.0}%", v * 100.0))
+}
+
+fn decimal(value: Option<f64>) -> String {
+    value.map_or("-".to_string(), |v| format!("{:.1}", v))
+}
+
+fn trend(current: Option<f64>, previous: Option<f64>) -> String {
+    format!("{} ({})", decimal(current), decimal(previous))
+}
+
+pub fn format_send_rates(report: &SendRateReport) -> String {
+    let mut out = format!(
+        "Current {} to {}, previous {} to {} in brackets\n",
+        report.current.0, report.current.1, report.previous.0, report.previous.1
+    );
+    out.push_str(&format!(
+        "{:<8} {:>9} {:>15} {:>13} {:>13} {:>13} {:>13}\n",
+        "GRADE", "CLIMBS", "SEND %", "TRIES/SEND", "TOP NO SEND", "FLASH %", "LEAD RESTS"
+    ));
+    for row in &report.grades {
+        let c = row.current.as_ref();
+        let p = row.previous.as_ref();
+        out.push_str(&format!(
+            "{:<8} {:>9} {:>15} {:>13} {:>13} {:>13} {:>13}\n",
+            row.grade,
+            format!("{} ({})", c.map_or(0, |s| s.climbs), p.map_or(0, |s| s.climbs)),
+            format!("{} ({})", percent(c.map(|s| s.send_rate)), percent(p.map(|s| s.send_rate))),
+            trend(c.and_then(|s| s.avg_attempts_to_send), p.and_then(|s| s.avg_attempts_to_send)),
+            format!(
+                "{} ({})",
+                percent(c.map(|s| s.top_out_without_send_rate)),
+                percent(p.map(|s| s.top_out_without_send_rate))
+            ),
+            format!("{} ({})", percent(c.map(|s| s.flash_rate)), percent(p.map(|s| s.flash_rate))),
+            trend(c.and_then(|s| s.avg_lead_rests), p.and_then(|s| s.avg_lead_rests)),
+        ));
+    }
+    out
+}
+
+pub fn print_send_rates(user: Option<Uuid>, range: &DateRange, json: bool) {
+    let sessions: Vec<ClimbingSession> = load_logs(user, is_climb);
+    let report = build_send_rates(&sessions, range, Utc::now().date_naive());
+    if json {
+        match serde_json::to_string_pretty(&report) {
+            Ok(out) => println!("{}", out),
+            Err(e) => error!("error {e} serializing send rates"),
+        }
+    } else {
+        print!("{}", format_send_rates(&report));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::climblib::models::{BoulderGrade, ClimbStyle, RopeGrade};
+    use crate::climblib::test_support::{climb, session};
+
+    fn date(m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_grade_stats() {
+        let flash = climb(Grade::Boulder(BoulderGrade::V4), 1, true, false);
+        let worked = climb(Grade::Boulder(BoulderGrade::V4), 3, true, false);
+        let mut topped = climb(Grade::Boulder(BoulderGrade::V4), 2, false, false);
+        topped.reached_top = true;
+        let failed = climb(Grade::Boulder(BoulderGrade::V4), 4, false, false);
+
+        let stats = grade_stats(&[&flash, &worked, &topped, &failed]);
+
+        assert_eq!(stats.climbs, 4);
+        assert_eq!(stats.send_rate, 0.5);
+        assert_eq!(stats.avg_attempts_to_send, Some(2.0));
+        assert_eq!(stats.top_out_without_send_rate, 0.25);
+        assert_eq!(stats.flash_rate, 0.25);
+        assert_eq!(stats.avg_lead_rests, None);
+    }
+
+    #[test]
+    fn test_comparison_periods() {
+        let range = DateRange::new(Some(date(4, 11)), Some(date(4, 20)));
+        let (current, previous) = comparison_periods(&range, date(6, 1));
+        assert_eq!(current, range);
+        assert_eq!(previous, DateRange::new(Some(date(4, 1)), Some(date(4, 10))));
+
+        let (current, _) = comparison_periods(&DateRange::default(), date(4, 28));
+        assert_eq!(current.from, Some(date(4, 1)));
+    }
+
+    #[test]
+    fn test_build_send_rates_compares_periods() {
+        let mut lead = climb(Grade::Rope(RopeGrade::FiveElevenA), 1, true, true);
+        lead.rests = Some(2);
+        let sessions = vec![
+            session("2024-04-02", ClimbStyle::Boulder, vec![
+                climb(Grade::Boulder(BoulderGrade::V5), 2, false, false),
+                climb(Grade::Boulder(BoulderGrade::V5), 3, true, false),
+            ]),
+            session("2024-04-15", ClimbStyle::Boulder, vec![
+                climb(Grade::Boulder(BoulderGrade::V5), 1, true, false),
+            ]),
+            session("2024-04-16", ClimbStyle::Rope, vec![lead]),
+        ];
+        let range = DateRange::new(Some(date(4, 11)), Some(date(4, 20)));
+
+        let report = build_send_rates(&sessions, &range, date(6, 1));
+
+        assert_eq!(report.grades.len(), 2);
+        let v5 = report.grades.iter().find(|g| g.grade == "v5").unwrap();
+        assert_eq!(v5.send_rate_change, Some(0.5));
+        let rope = report.grades.iter().find(|g| g.grade == "5.11a").unwrap();
+        assert_eq!(rope.current.as_ref().unwrap().avg_lead_rests, Some(2.0));
+        assert!(rope.previous.is_none());
+    }
+}
@@ -0,0 +1,368 @@
+use super::bundle::BundleIndex;
+use super::io::{load_log, log_index, log_name, save_log};
+use super::manifest::{SyncManifest, load_local_manifest};
+use super::sync::{fetch_bundle_index, fetch_remote_manifest, key_log_name, list_aws_files, SyncMode};
+
+use aws_config::defaults;
+use aws_config::BehaviorVersion;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::error;
+
+pub const SYNC_STATE: &str = ".sync_state.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FileInfo {
+    pub size: u64,
+    pub modified: Option<DateTime<Utc>>,
+    pub e_tag: Option<String>,
+}
+
+// What each file looked like the last time it was transferred, used as the base for diffs
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct SyncState {
+    pub files: BTreeMap<String, FileInfo>,
+}
+
+impl SyncState {
+    pub fn record(&mut self, filename: &str, local: FileInfo, e_tag: Option<String>) {
+        self.files.insert(filename.to_string(), FileInfo { e_tag, ..local });
+    }
+}
+
+pub fn load_sync_state() -> io::Result<SyncState> {
+    let mut path = PathBuf::from("logs");
+    path.push(SYNC_STATE);
+    if !path.exists() {
+        return Ok(SyncState::default());
+    }
+    load_log(&path)
+}
+
+pub fn save_sync_state(state: &SyncState) -> io::Result<()> {
+    save_log(state, SYNC_STATE)
+}
+
+pub fn local_file_info(path: &Path) -> io::Result<FileInfo> {
+    let metadata = std::fs::metadata(path)?;
+    Ok(FileInfo {
+        size: metadata.len(),
+        modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+        e_tag: None,
+    })
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    LocalOnly,
+    RemoteOnly,
+    ModifiedLocally,
+    ModifiedRemotely,
+    Conflicting,
+    Deleted,
+}
+
+impl FileStatus {
+    fn label(&self) -> &'static str {
+        match self {
+            FileStatus::LocalOnly => "local-only",
+            FileStatus::RemoteOnly => "remote-only",
+            FileStatus::ModifiedLocally => "modified (local)",
+            FileStatus::ModifiedRemotely => "modified (remote)",
+            FileStatus::Conflicting => "conflicting",
+            FileStatus::Deleted => "deleted",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct StatusEntry {
+    pub filename: String,
+    pub status: FileStatus,
+    pub local: Option<FileInfo>,
+    pub remote: Option<FileInfo>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+fn classify(local: &FileInfo, remote: &FileInfo, base: Option<&FileInfo>) -> Option<FileStatus> {
+    let Some(base) = base else {
+        // Never transferred from here, so only a size mismatch tells us the copies differ
+        return (local.size != remote.size).then_some(FileStatus::Conflicting);
+    };
+    let local_changed = local.size != base.size
+        || matches!((local.modified, base.modified), (Some(l), Some(b)) if l > b);
+    let remote_changed = remote.e_tag.is_some() && remote.e_tag != base.e_tag;
+    match (local_changed, remote_changed) {
+        (true, true) => Some(FileStatus::Conflicting),
+        (true, false) => Some(FileStatus::ModifiedLocally),
+        (false, true) => Some(FileStatus::ModifiedRemotely),
+        (false, false) => None,
+    }
+}
+
+pub fn diff(
+    local: &BTreeMap<String, FileInfo>,
+    remote: &BTreeMap<String, FileInfo>,
+    state: &SyncState,
+    manifest: &SyncManifest) -> Vec<StatusEntry> {
+
+    let filenames: BTreeSet<&String> = local.keys()
+        .chain(remote.keys())
+        .chain(manifest.tombstones.keys())
+        .collect();
+
+    let mut entries = Vec::new();
+    for filename in filenames {
+        let local_info = local.get(filename);
+        let remote_info = remote.get(filename);
+        let status = if manifest.is_deleted(filename) {
+            Some(FileStatus::Deleted)
+        } else {
+            match (local_info, remote_info) {
+                (Some(_), None) => Some(FileStatus::LocalOnly),
+                (None, Some(_)) => Some(FileStatus::RemoteOnly),
+                (Some(l), Some(r)) => classify(l, r, state.files.get(filename)),
+                (None, None) => None,
+            }
+        };
+        if let Some(status) = status {
+            entries.push(StatusEntry {
+                filename: filename.clone(),
+                status,
+                local: local_info.cloned(),
+                remote: remote_info.cloned(),
+                deleted_at: manifest.tombstones
+                    .get(filename)
+                    .filter(|t| t.deleted)
+                    .map(|t| t.updated_at),
+            });
+        }
+    }
+    entries.sort_by(|a, b| a.status.cmp(&b.status).then(a.filename.cmp(&b.filename)));
+    entries
+}
+
+// Bundle members have no object of their own, so they are compared by membership. A member
+// takes the local copy's size, and only counts as changed when edited since its last transfer.
+pub fn bundle_remote(index: &BundleIndex, local: &BTreeMap<String, FileInfo>) -> BTreeMap<String, FileInfo> {
+    let mut remote = BTreeMap::new();
+    for bundle in index.bundles.values() {
+        let modified = DateTime::parse_from_rfc3339(&bundle.updated_at).ok().map(|m| m.with_timezone(&Utc));
+        for filename in &bundle.files {
+            let size = local.get(filename).map_or(0, |info| info.size);
+            remote.insert(filename.clone(), FileInfo { size, modified, e_tag: None });
+        }
+    }
+    remote
+}
+
+fn describe(info: Option<&FileInfo>) -> String {
+    match info {
+        Some(info) => format!(
+            "{} B {}",
+            info.size,
+            info.modified.map(|m| m.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_default()
+        ),
+        None => "-".to_string(),
+    }
+}
+
+pub fn format_status(entries: &[StatusEntry]) -> String {
+    if entries.is_empty() {
+        return "Everything is in sync\n".to_string();
+    }
+    let mut out = format!("{:<18} {:<36} {:<28} {:<28}\n", "STATUS", "FILE", "LOCAL", "REMOTE");
+    for entry in entries {
+        let remote = match entry.deleted_at {
+            Some(at) => format!("deleted {}", at.format("%Y-%m-%d %H:%M")),
+            None => describe(entry.remote.as_ref()),
+        };
+        out.push_str(&format!(
+            "{:<18} {:<36} {:<28} {:<28}\n",
+            entry.status.label(),
+            entry.filename,
+            describe(entry.local.as_ref()),
+            remote
+        ));
+    }
+    out
+}
+
+pub async fn print_sync_status(bucket_name: &str, json: bool, mode: SyncMode) {
+    let config = defaults(BehaviorVersion::latest()).load().await;
+    let client = aws_sdk_s3::Client::new(&config);
+
+    let listing = match list_aws_files(bucket_name, None, &client).await {
+        Ok(resp) => resp,
+        Err(e) => {
+            error!("Error listing S3 keys: {e}");
+            return;
+        }
+    };
+    let per_file: BTreeMap<String, FileInfo> = listing
+        .iter()
+        .filter_map(|o| {
+            let filename = key_log_name(o.key()?)?;
+            Some((filename, FileInfo {
+                size: o.size().unwrap_or(0) as u64,
+                modified: o.last_modified().and_then(|m| DateTime::from_timestamp(m.secs(), 0)),
+                e_tag: o.e_tag().map(|s| s.to_string()),
+            }))
+        })
+        .collect();
+
+    let mut local = BTreeMap::new();
+    match log_index() {
+        Ok(paths) => {
+            for path in paths {
+                let Some(filename) = log_name(&path) else {
+                    continue;
+                };
+                match local_file_info(&path) {
+                    Ok(info) => { local.insert(filename, info); }
+                    Err(e) => error!("error {e} reading {:?}", path),
+                }
+            }
+        }
+        Err(e) => {
+            error!("Error getting local logs: {e}");
+            return;
+        }
+    }
+
+    // In bundle mode logs live only in the monthly bundles, whatever per-file keys are left over
+    let remote = match mode {
+        SyncMode::PerFile => per_file,
+        SyncMode::Bundled => {
+            let remote_keys: HashSet<String> = listing.iter().filter_map(|o| o.key().map(str::to_string)).collect();
+            match fetch_bundle_index(bucket_name, &client, &remote_keys).await {
+                Ok(index) => bundle_remote(&index, &local),
+                Err(e) => {
+                    error!("error {e} fetching the bundle index");
+                    return;
+                }
+            }
+        }
+    };
+
+    let manifest = match (load_local_manifest(), fetch_remote_manifest(bucket_name, &client).await) {
+        (Ok(local), Ok(remote)) => local.merge(&remote),
+        (Err(e), _) => {
+            error!("error {e} loading local manifest");
+            return;
+        }
+        (_, Err(e)) => {
+            error!("error {e} fetching remote manifest");
+            return;
+        }
+    };
+    let state = match load_sync_state() {
+        Ok(state) => state,
+        Err(e) => {
+            error!("error {e} loading sync state");
+            return;
+        }
+    };
+
+    let entries = diff(&local, &remote, &state, &manifest);
+    if json {
+        match serde_json::to_string_pretty(&entries) {
+            Ok(out) => println!("{}", out),
+            Err(e) => error!("error {e} serializing sync status"),
+        }
+    } else {
+        print!("{}", format_status(&entries));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::climblib::bundle::BundleEntry;
+    use chrono::TimeZone;
+
+    fn info(size: u64, day: u32, e_tag: Option<&str>) -> FileInfo {
+        FileInfo {
+            size,
+            modified: Some(Utc.with_ymd_and_hms(2024, 4, day, 12, 0, 0).unwrap()),
+            e_tag: e_tag.map(|s| s.to_string()),
+        }
+    }
+
+    fn files(entries: Vec<(&str, FileInfo)>) -> BTreeMap<String, FileInfo> {
+        entries.into_iter().map(|(f, i)| (f.to_string(), i)).collect()
+    }
+
+    #[test]
+    fn test_diff_classifies_files() {
+        let local = files(vec![
+            ("climb-2024-04-01.json", info(10, 1, None)),
+            ("climb-2024-04-02.json", info(12, 5, None)),
+            ("climb-2024-04-03.json", info(10, 3, None)),
+            ("climb-2024-04-04.json", info(14, 6, None)),
+            ("climb-2024-04-05.json", info(10, 5, None)),
+        ]);
+        let remote = files(vec![
+            ("climb-2024-04-02.json", info(10, 2, Some("a"))),
+            ("climb-2024-04-03.json", info(11, 4, Some("changed"))),
+            ("climb-2024-04-04.json", info(11, 4, Some("changed"))),
+            ("climb-2024-04-05.json", info(10, 5, Some("c"))),
+            ("workout-2024-04-06.json", info(10, 6, None)),
+        ]);
+        let mut state = SyncState::default();
+        state.record("climb-2024-04-02.json", info(10, 2, None), Some("a".to_string()));
+        state.record("climb-2024-04-03.json", info(10, 3, None), Some("b".to_string()));
+        state.record("climb-2024-04-04.json", info(10, 3, None), Some("b".to_string()));
+        state.record("climb-2024-04-05.json", info(10, 5, None), Some("c".to_string()));
+        let mut manifest = SyncManifest::default();
+        manifest.record("metrics-2024-04-07.json", true, Utc::now());
+
+        let entries = diff(&local, &remote, &state, &manifest);
+        let statuses: Vec<(&str, FileStatus)> = entries
+            .iter()
+            .map(|e| (e.filename.as_str(), e.status))
+            .collect();
+
+        assert_eq!(statuses, vec![
+            ("climb-2024-04-01.json", FileStatus::LocalOnly),
+            ("workout-2024-04-06.json", FileStatus::RemoteOnly),
+            ("climb-2024-04-02.json", FileStatus::ModifiedLocally),
+            ("climb-2024-04-03.json", FileStatus::ModifiedRemotely),
+            ("climb-2024-04-04.json", FileStatus::Conflicting),
+            ("metrics-2024-04-07.json", FileStatus::Deleted),
+        ]);
+    }
+
+    #[test]
+    fn test_bundle_members_count_as_remote() {
+        let local = files(vec![
+            ("climb-2024-04-01.json", info(10, 1, None)),
+            ("climb-2024-04-02.json", info(12, 5, None)),
+            ("climb-2024-04-03.json", info(10, 3, None)),
+        ]);
+        let mut index = BundleIndex::default();
+        index.bundles.insert("2024-04".to_string(), BundleEntry {
+            key: "bundles/2024-04.jsonl.gz".to_string(),
+            files: ["climb-2024-04-01.json", "climb-2024-04-02.json", "users/6f1c/climb-2024-04-09.json"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            updated_at: "2024-04-04T12:00:00+00:00".to_string(),
+        });
+        let mut state = SyncState::default();
+        state.record("climb-2024-04-02.json", info(10, 2, None), None);
+
+        let remote = bundle_remote(&index, &local);
+        let entries = diff(&local, &remote, &state, &SyncManifest::default());
+        let statuses: Vec<(&str, FileStatus)> = entries.iter().map(|e| (e.filename.as_str(), e.status)).collect();
+        assert_eq!(statuses, vec![
+            ("climb-2024-04-03.json", FileStatus::LocalOnly),
+            ("users/6f1c/climb-2024-04-09.json", FileStatus::RemoteOnly),
+            ("climb-2024-04-02.json", FileStatus::ModifiedLocally),
+        ]);
+    }
+}
@@ -1,46 +1,144 @@
-use super::io::{load_log, log_index};
-use super::models::{ClimbingSession, WorkoutSession, ClimbMetricsEntry};
-use super::utils::{is_climb, is_workout, is_metrics};
-use tracing::{info, error};
-
-pub fn print_summary() {
-    let mut num_climbs = 0;
-    let mut num_workouts = 0;
-    let mut num_metrics = 0;
-    match log_index() {
-        Ok(paths) => {
-            for path in paths {
-                if is_climb(&path) {
-                    match load_log::<ClimbingSession>(&path){
-                        Ok(c) => {
-                            num_climbs += c.climbs.len();
-                        }
-                        Err(e) => error!("error {e} loading file {:?}", path),
-                    }
-                }
-                else if is_workout(&path) {
-                    match load_log::<WorkoutSession>(&path) {
-                    Ok(_w) => {
-                        num_workouts += 1;
-                    }
-                    Err(e) => error!("error {e} loading file {:?}", path),
-                    }
-                }
-                else if is_metrics(&path) {
-                    match load_log::<ClimbMetricsEntry>(&path) {
-                    Ok(_m) => {
-                        num_metrics += 1;
-                    }
-                    Err(e) => error!("error {e} loading file {:?}", path),
-                    }
-                }
-            }
-        }
-        Err(e) => error!("error {e} getting paths"),
+use super::io::load_logs;
+use super::load::training_load;
+use super::models::{ClimbingSession, WorkoutSession, ClimbMetricsEntry, Grade};
+use super::sends::stats_by_grade;
+use super::utils::{is_climb, is_workout, is_metrics, DateRange};
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LiftVolume {
+    pub exercise: String,
+    pub volume_lb: f64,
+}
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricChange {
+    pub metric: String,
+    pub first: f64,
+    pub last: f64,
+    pub change: f64,
+}
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GradeSendRate {
+    pub grade: String,
+    pub climbs: u32,
+    pub send_rate: f64,
+}
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Summary {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub climbing_sessions: usize,
+    pub climbs: usize,
+    pub sends: usize,
+    pub workouts: usize,
+    pub metric_tests: usize,
+    pub hardest_boulder: Option<String>,
+    pub hardest_rope: Option<String>,
+    pub send_rates: Vec<GradeSendRate>,
+    pub lifting_volume: Vec<LiftVolume>,
+    pub total_volume_lb: f64,
+    pub metric_changes: Vec<MetricChange>,
+}
+
+// Volume is sets x reps x weight per exercise, heaviest first
+pub fn lifting_volume(workouts: &[&WorkoutSession]) -> Vec<LiftVolume> {
+    let mut volumes: BTreeMap<String, LiftVolume> = BTreeMap::new();
+    for exercise in workouts.iter().flat_map(|w| &w.exercises) {
+        let volume = volumes
+            .entry(exercise.name.trim().to_lowercase())
+            .or_insert_with(|| LiftVolume { exercise: exercise.name.trim().to_string(), volume_lb: 0.0 });
+        volume.volume_lb += exercise.sets as f64 * exercise.reps as f64 * exercise.weight_lb.max(0) as f64;
+    }
+    let mut volumes: Vec<LiftVolume> = volumes.into_values().collect();
+    volumes.sort_by(|a, b| b.volume_lb.total_cmp(&a.volume_lb));
+    volumes
+}
+
+fn metric_change(
+    metric: &str,
+    metrics: &[&ClimbMetricsEntry],
+    value: fn(&ClimbMetricsEntry) -> Option<f32>) -> Option<MetricChange> {
+
+    let values: Vec<f64> = metrics.iter().filter_map(|m| value(m)).map(|v| v as f64).collect();
+    let (first, last) = (*values.first()?, *values.last()?);
+    Some(MetricChange { metric: metric.to_string(), first, last, change: last - first })
+}
+
+// Change from the first to the last test, with tests sorted by date
+pub fn metric_changes(metrics: &[&ClimbMetricsEntry]) -> Vec<MetricChange> {
+    let mut tests = metrics.to_vec();
+    tests.sort_by(|a, b| a.date.cmp(&b.date));
+    [
+        metric_change("Finger strength (% BW)", &tests, |m| m.finger_strength_percent_bw),
+        metric_change("Max pull-up (% BW)", &tests, |m| m.max_pullup_percent_bw),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+pub fn build_summary(
+    sessions: &[ClimbingSession],
+    workouts: &[WorkoutSession],
+    metrics: &[ClimbMetricsEntry],
+    range: &DateRange) -> Summary {
+
+    let in_range: Vec<&ClimbingSession> = sessions.iter().filter(|s| range.contains(&s.date)).collect();
+    let workouts: Vec<&WorkoutSession> = workouts.iter().filter(|w| range.contains(&w.date)).collect();
+    let metrics: Vec<&ClimbMetricsEntry> = metrics.iter().filter(|m| range.contains(&m.date)).collect();
+    let sent: Vec<Grade> = in_range.iter().flat_map(|s| &s.climbs).filter(|c| c.sent).map(|c| c.grade).collect();
+    let lifting_volume = lifting_volume(&workouts);
+
+    Summary {
+        from: range.from,
+        to: range.to,
+        climbing_sessions: in_range.len(),
+        climbs: in_range.iter().map(|s| s.climbs.len()).sum(),
+        sends: sent.len(),
+        workouts: workouts.len(),
+        metric_tests: metrics.len(),
+        hardest_boulder: sent.iter().filter(|g| matches!(g, Grade::Boulder(_))).max().map(|g| g.to_string()),
+        hardest_rope: sent.iter().filter(|g| matches!(g, Grade::Rope(_))).max().map(|g| g.to_string()),
+        send_rates: stats_by_grade(sessions, range)
+            .into_iter()
+            .rev()
+            .map(|(grade, stats)| GradeSendRate { grade: grade.to_string(), climbs: stats.climbs, send_rate: stats.send_rate })
+            .collect(),
+        total_volume_lb: lifting_volume.iter().map(|v| v.volume_lb).sum(),
+        lifting_volume,
+        metric_changes: metric_changes(&metrics),
+    }
+}
+
+pub fn print_summary(user: Option<Uuid>, acwr_threshold: f64) {
+    let sessions: Vec<ClimbingSession> = load_logs(user, is_climb);
+    let workouts: Vec<WorkoutSession> = load_logs(user, is_workout);
+    let metrics: Vec<ClimbMetricsEntry> = load_logs(user, is_metrics);
+    let summary = build_summary(&sessions, &workouts, &metrics, &DateRange::default());
+    info!("Number of climbs: {}", summary.climbs);
+    info!("Number of workouts: {}", summary.workouts);
+    info!("Number of metrics: {}", summary.metric_tests);
+
+    for week in training_load(user).iter().filter(|w| w.exceeds(acwr_threshold)) {
+        warn!(
+            "Week {} load spike: climbing ACWR {}, lifting ACWR {} (threshold {acwr_threshold})",
+            week.week,
+            week.climbing_acwr.map_or("-".to_string(), |r| format!("{r:.2}")),
+            week.lifting_acwr.map_or("-".to_string(), |r| format!("{r:.2}")),
+        );
     }
-    info!("Number of climbs: {num_climbs}");
-    info!("Number of workouts: {num_workouts}");
-    info!("Number of metrics: {num_metrics}");
 }
 
 pub fn print_sent_climbs(session: &ClimbingSession){
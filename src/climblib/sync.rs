@@ -1,4 +1,14 @@
-use super::io::{log_index};
+use super::bundle::{
+    BundleEntry, BundleIndex, BundledLog, BUNDLE_INDEX_KEY,
+    bundle_key, group_by_month, pack_bundle, unpack_bundle,
+};
+use super::io::{load_log, log_index, log_name, log_name_dir, save_log};
+use super::manifest::{
+    SyncManifest, MANIFEST_KEY, TRASH_PREFIX,
+    load_local_manifest, save_local_manifest, move_to_trash, restore_from_trash, revive_relogged,
+    log_path, trash_path, trash_key,
+};
+use super::status::{SyncState, load_sync_state, save_sync_state, local_file_info};
 use super::utils::{is_climb, is_workout, is_metrics};
 
 use aws_sdk_s3;
@@ -7,7 +17,7 @@ use aws_config::BehaviorVersion;
 use tokio;
 use std::collections::HashSet;
 use std::error::Error;
-use std::path::{PathBuf, Path};
+use std::path::Path;
 use tracing::{info, error};
 
 #[derive(PartialEq)]
@@ -16,16 +26,36 @@ pub enum AwsActions {
     Pull,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyncMode {
+    PerFile,
+    Bundled,
+}
+
 pub async fn aws_entrypoint(
     action: AwsActions, 
     bucket_name: &str, 
-    dry_run: bool) {
+    dry_run: bool,
+    mode: SyncMode,
+    retention_days: i64) {
     let config = defaults(BehaviorVersion::latest()).load().await;
     let client = aws_sdk_s3::Client::new(&config);
+    sync_with_client(action, bucket_name, &client, dry_run, mode, retention_days, &HashSet::new()).await;
+}
 
-    let remote_keys: HashSet<String> = match list_aws_files(bucket_name, &client).await {
-        Ok(resp) => resp
-            .contents()
+// `changed` names local logs to upload even when the bucket already has a copy, which is
+// how the watcher pushes edits through the same tombstone and bundle handling as --sync
+pub(crate) async fn sync_with_client(
+    action: AwsActions,
+    bucket_name: &str,
+    client: &aws_sdk_s3::Client,
+    dry_run: bool,
+    mode: SyncMode,
+    retention_days: i64,
+    changed: &HashSet<String>) {
+
+    let remote_keys: HashSet<String> = match list_aws_files(bucket_name, None, client).await {
+        Ok(objects) => objects
             .iter()
             .filter_map(|o| o.key().map(|s| s.to_string()))
             .collect(),
@@ -35,10 +65,19 @@ pub async fn aws_entrypoint(
         }
     };
 
+    let Some(manifest) = reconcile_tombstones(bucket_name, client, dry_run, &remote_keys, retention_days).await else {
+        return;
+    };
+    let remote_keys: HashSet<String> = remote_keys
+        .into_iter()
+        .filter(|k| !k.starts_with(TRASH_PREFIX))
+        .filter(|k| !key_log_name(k).is_some_and(|name| manifest.is_deleted(&name)))
+        .collect();
+
     let local_paths: HashSet<String> = match log_index() {
         Ok(paths) => paths
                 .iter()
-                .filter_map(|p| p.file_name()?.to_str().map(|s| s.to_string()))
+                .filter_map(|p| log_name(p))
                 .collect(),
         Err(e) => {
             error!("Error getting local logs: {e}");
@@ -46,20 +85,27 @@ pub async fn aws_entrypoint(
         }
     };
 
-    if action == AwsActions::Pull {
-        pull(bucket_name, &client, dry_run, &remote_keys, &local_paths).await;
-    }
-    else if action == AwsActions::Sync {
-        sync(bucket_name, &client, dry_run, &remote_keys, &local_paths).await;
+    match (action, mode) {
+        (AwsActions::Pull, SyncMode::PerFile) => {
+            pull(bucket_name, client, dry_run, &remote_keys, &local_paths).await;
+        }
+        (AwsActions::Sync, SyncMode::PerFile) => {
+            sync(bucket_name, client, dry_run, &remote_keys, &local_paths, changed).await;
+        }
+        (AwsActions::Pull, SyncMode::Bundled) => {
+            pull_bundles(bucket_name, client, dry_run, &remote_keys, &local_paths, &manifest).await;
+        }
+        (AwsActions::Sync, SyncMode::Bundled) => {
+            sync_bundles(bucket_name, client, dry_run, &remote_keys, &manifest, changed).await;
+        }
     }
 }
 
-async fn download_log_from_s3(
+pub(crate) async fn get_object_bytes(
     bucket_name: &str, 
-    path: &Path,
     key: &str, 
     client: &aws_sdk_s3::Client
-) -> Result<(), Box<dyn Error>> {
+) -> Result<Vec<u8>, Box<dyn Error>> {
     let response = client
                     .get_object()
                     .bucket(bucket_name)
@@ -68,42 +114,294 @@ async fn download_log_from_s3(
                     .await?;
 
     let data = response.body.collect().await?.into_bytes();
-    tokio::fs::write(path, data).await?;
+    Ok(data.to_vec())
+}
 
-    Ok(())
+pub(crate) async fn put_object_bytes(
+    bucket_name: &str, 
+    key: &str, 
+    body: Vec<u8>, 
+    client: &aws_sdk_s3::Client
+) -> Result<Option<String>, Box<dyn Error>> {
+    let response = client.put_object()
+    .bucket(bucket_name)
+    .key(key)
+    .body(body.into())
+    .send()
+    .await?;
+
+    Ok(response.e_tag().map(|s| s.to_string()))
 }
 
-async fn list_aws_files(
+async fn download_log_from_s3(
     bucket_name: &str, 
+    path: &Path,
+    key: &str, 
     client: &aws_sdk_s3::Client
-) -> Result<aws_sdk_s3::operation::list_objects_v2::ListObjectsV2Output, Box<dyn Error>> {
+) -> Result<Option<String>, Box<dyn Error>> {
     let response = client
-                    .list_objects_v2()
+                    .get_object()
                     .bucket(bucket_name)
+                    .key(key)
                     .send()
                     .await?;
-    Ok(response)
+
+    let e_tag = response.e_tag().map(|s| s.to_string());
+    let data = response.body.collect().await?.into_bytes();
+    tokio::fs::write(path, data).await?;
+
+    Ok(e_tag)
 }
 
-async fn upload_log_to_s3(
+async fn copy_object(
     bucket_name: &str, 
-    key: &str, 
-    path: &Path, 
+    from: &str, 
+    to: &str, 
     client: &aws_sdk_s3::Client
 ) -> Result<(), Box<dyn Error>> {
+    client.copy_object()
+    .bucket(bucket_name)
+    .copy_source(format!("{}/{}", bucket_name, from))
+    .key(to)
+    .send()
+    .await?;
 
-    let body = tokio::fs::read(path).await?;
+    Ok(())
+}
 
-    client.put_object()
+async fn delete_object(
+    bucket_name: &str, 
+    key: &str, 
+    client: &aws_sdk_s3::Client
+) -> Result<(), Box<dyn Error>> {
+    client.delete_object()
     .bucket(bucket_name)
     .key(key)
-    .body(body.into())
     .send()
     .await?;
 
     Ok(())
 }
 
+async fn move_object(
+    bucket_name: &str, 
+    from: &str, 
+    to: &str, 
+    client: &aws_sdk_s3::Client
+) -> Result<(), Box<dyn Error>> {
+    copy_object(bucket_name, from, to, client).await?;
+    delete_object(bucket_name, from, client).await
+}
+
+// Follows continuation tokens, since one listing stops at 1000 keys
+pub(crate) async fn list_aws_files(
+    bucket_name: &str, 
+    prefix: Option<&str>,
+    client: &aws_sdk_s3::Client
+) -> Result<Vec<aws_sdk_s3::types::Object>, Box<dyn Error>> {
+    let mut objects = Vec::new();
+    let mut token = None;
+    loop {
+        let response = client
+                        .list_objects_v2()
+                        .bucket(bucket_name)
+                        .set_prefix(prefix.map(str::to_string))
+                        .set_continuation_token(token)
+                        .send()
+                        .await?;
+        objects.extend_from_slice(response.contents());
+        match response.next_continuation_token() {
+            Some(next) => token = Some(next.to_string()),
+            None => return Ok(objects),
+        }
+    }
+}
+
+pub(crate) async fn upload_log_to_s3(
+    bucket_name: &str, 
+    key: &str, 
+    path: &Path, 
+    client: &aws_sdk_s3::Client
+) -> Result<Option<String>, Box<dyn Error>> {
+
+    let body = tokio::fs::read(path).await?;
+    put_object_bytes(bucket_name, key, body, client).await
+}
+
+// Takes a log name from `log_name`. A user's logs keep their users/<id>/ prefix in the
+// bucket, so two users logging on the same date never share a key.
+pub(crate) fn bucket_key(name: &str) -> Option<String> {
+    let (dir, filename) = match name.rsplit_once('/') {
+        Some((_, filename)) => (Some(log_name_dir(name)?), filename),
+        None => (None, name),
+    };
+    let path = Path::new(filename);
+    let category = if is_climb(path) {
+        "climbs"
+    } else if is_workout(path) {
+        "workouts"
+    } else if is_metrics(path) {
+        "metrics"
+    } else {
+        return None;
+    };
+    Some(match dir {
+        Some(dir) => format!("{}/{}/{}", dir, category, filename),
+        None => format!("{}/{}", category, filename),
+    })
+}
+
+// The log name a bucket key holds, for keys that `bucket_key` would produce
+pub(crate) fn key_log_name(key: &str) -> Option<String> {
+    let (prefix, filename) = key.rsplit_once('/')?;
+    let name = match prefix.rsplit_once('/') {
+        Some((dir, _)) => format!("{}/{}", dir, filename),
+        None => filename.to_string(),
+    };
+    (bucket_key(&name)? == key).then_some(name)
+}
+
+// Asks for the key itself rather than trusting a listing, so a bucket too big to list
+// in one page can't make an existing manifest look missing and get overwritten
+pub(crate) async fn fetch_remote_manifest(
+    bucket_name: &str, 
+    client: &aws_sdk_s3::Client) -> Result<SyncManifest, Box<dyn Error>> {
+    let response = match client.get_object().bucket(bucket_name).key(MANIFEST_KEY).send().await {
+        Ok(response) => response,
+        Err(e) if e.as_service_error().is_some_and(|e| e.is_no_such_key()) => {
+            return Ok(SyncManifest::default());
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let data = response.body.collect().await?.into_bytes();
+    Ok(serde_json::from_slice(&data)?)
+}
+
+async fn reconcile_tombstones(
+    bucket_name: &str, 
+    client: &aws_sdk_s3::Client, 
+    dry_run: bool,
+    remote_keys: &HashSet<String>,
+    retention_days: i64) -> Option<SyncManifest> {
+
+    let local = match load_local_manifest() {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            error!("error {e} loading local manifest");
+            return None;
+        }
+    };
+    let remote = match fetch_remote_manifest(bucket_name, client).await {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            error!("error {e} fetching {}", MANIFEST_KEY);
+            return None;
+        }
+    };
+    let mut merged = local.merge(&remote);
+    for filename in revive_relogged(&mut merged) {
+        info!("{} was logged again after it was deleted, keeping it", filename);
+    }
+
+    for (filename, tombstone) in &merged.tombstones {
+        let Some(key) = bucket_key(filename) else {
+            continue;
+        };
+        let trashed_key = trash_key(&key);
+        if tombstone.deleted {
+            if log_path(filename).exists() {
+                if dry_run {
+                    info!("Would delete local {}", filename);
+                } else if let Err(e) = move_to_trash(filename) {
+                    error!("error {e} deleting local {}", filename);
+                }
+            }
+            if remote_keys.contains(&key) {
+                if dry_run {
+                    info!("Would delete {}", key);
+                } else {
+                    match move_object(bucket_name, &key, &trashed_key, client).await {
+                        Ok(()) => info!("Deleted {}", key),
+                        Err(e) => error!("error {e} deleting {}", key),
+                    }
+                }
+            }
+        } else {
+            if !log_path(filename).exists() && trash_path(filename).exists() {
+                if dry_run {
+                    info!("Would restore local {}", filename);
+                } else if let Err(e) = restore_from_trash(filename) {
+                    error!("error {e} restoring local {}", filename);
+                }
+            }
+            if !remote_keys.contains(&key) && remote_keys.contains(&trashed_key) {
+                if dry_run {
+                    info!("Would restore {}", key);
+                } else {
+                    match move_object(bucket_name, &trashed_key, &key, client).await {
+                        Ok(()) => info!("Restored {}", key),
+                        Err(e) => error!("error {e} restoring {}", key),
+                    }
+                }
+            }
+        }
+    }
+
+    for filename in merged.purge_expired(chrono::Utc::now(), retention_days) {
+        if dry_run {
+            info!("Would purge {}", filename);
+            continue;
+        }
+        if trash_path(&filename).exists()
+            && let Err(e) = std::fs::remove_file(trash_path(&filename)) {
+            error!("error {e} purging local {}", filename);
+        }
+        if let Some(trashed_key) = bucket_key(&filename).map(|k| trash_key(&k))
+            && remote_keys.contains(&trashed_key)
+            && let Err(e) = delete_object(bucket_name, &trashed_key, client).await {
+            error!("error {e} purging {}", trashed_key);
+        }
+        info!("Purged {}", filename);
+    }
+
+    if !dry_run {
+        if let Err(e) = save_local_manifest(&merged) {
+            error!("error {e} saving local manifest");
+        }
+        if merged != remote {
+            let upload = match serde_json::to_vec_pretty(&merged) {
+                Ok(body) => put_object_bytes(bucket_name, MANIFEST_KEY, body, client).await,
+                Err(e) => Err(e.into()),
+            };
+            if let Err(e) = upload {
+                error!("error {e} uploading {}", MANIFEST_KEY);
+            }
+        }
+    }
+
+    Some(merged)
+}
+
+pub(crate) fn record_transfer(state: &mut SyncState, filename: &str, path: &Path, e_tag: Option<String>) {
+    match local_file_info(path) {
+        Ok(info) => state.record(filename, info, e_tag),
+        Err(e) => error!("error {e} reading {:?}", path),
+    }
+}
+
+pub(crate) fn finish_transfers(state: &SyncState) {
+    if let Err(e) = save_sync_state(state) {
+        error!("error {e} saving sync state");
+    }
+}
+
+fn current_sync_state() -> SyncState {
+    load_sync_state().unwrap_or_else(|e| {
+        error!("error {e} loading sync state");
+        SyncState::default()
+    })
+}
+
 async fn pull(
     bucket_name: &str, 
     client: &aws_sdk_s3::Client, 
@@ -111,23 +409,36 @@ async fn pull(
     remote_keys: &HashSet<String>,
     local_paths: &HashSet<String>) {
 
+    let mut state = current_sync_state();
     for key in remote_keys {
-        let filename = key.split('/').last().unwrap_or("unknown.json").to_string();
+        let Some(filename) = key_log_name(key) else {
+            continue;
+        };
         let exists_locally = local_paths.contains(&filename);
         if !exists_locally {
-            let mut local_path = PathBuf::from("logs");
-            local_path.push(filename);
+            let local_path = log_path(&filename);
             if dry_run {
                 info!("Would download {}", &key);
             }
             else {
-                match download_log_from_s3(bucket_name, &local_path, &key, client).await {
-                    Ok(()) => info!("Downloaded {}", &key),
-                    Err(e) => error!("error {e} downloading {}", &key)
+                if let Some(parent) = local_path.parent()
+                    && let Err(e) = tokio::fs::create_dir_all(parent).await {
+                    error!("error {e} creating {:?}", parent);
+                    continue;
+                }
+                match download_log_from_s3(bucket_name, &local_path, key, client).await {
+                    Ok(e_tag) => {
+                        info!("Downloaded {}", key);
+                        record_transfer(&mut state, &filename, &local_path, e_tag);
+                    }
+                    Err(e) => error!("error {e} downloading {}", key)
                 }
             }
         }
     }
+    if !dry_run {
+        finish_transfers(&state);
+    }
 }
 
 async fn sync(
@@ -135,33 +446,203 @@ async fn sync(
     client: &aws_sdk_s3::Client, 
     dry_run: bool,
     remote_keys: &HashSet<String>,
-    local_paths: &HashSet<String>) {
+    local_paths: &HashSet<String>,
+    changed: &HashSet<String>) {
 
+    let mut state = current_sync_state();
     for filename in local_paths {
-        let mut path = PathBuf::from("logs");
-        path.push(filename);
-        let bucket_path = if is_climb(&path) {
-            format!("climbs/{}", filename)
-        } else if is_workout(&path) {
-            format!("workouts/{}", filename)
-        } else if is_metrics(&path) {
-            format!("metrics/{}", filename)
-        } else {
+        let path = log_path(filename);
+        let Some(bucket_path) = bucket_key(filename) else {
             continue;
         };
         let exists_remotely = remote_keys.contains(&bucket_path);
-        if !exists_remotely {
+        if !exists_remotely || changed.contains(filename) {
             if dry_run {
                 info!("Would upload {}", bucket_path);
             }
             else {
                 match upload_log_to_s3(bucket_name, &bucket_path, &path, client).await {
-                    Ok(_) => info!("Uploaded {}", bucket_path),
+                    Ok(e_tag) => {
+                        info!("Uploaded {}", bucket_path);
+                        record_transfer(&mut state, filename, &path, e_tag);
+                    }
                     Err(e) => error!("error {e} uploading {}", bucket_path),
                 }
             }
         }
     }
+    if !dry_run {
+        finish_transfers(&state);
+    }
+}
+
+pub(crate) async fn fetch_bundle_index(
+    bucket_name: &str, 
+    client: &aws_sdk_s3::Client,
+    remote_keys: &HashSet<String>) -> Result<BundleIndex, Box<dyn Error>> {
+    if !remote_keys.contains(BUNDLE_INDEX_KEY) {
+        return Ok(BundleIndex::default());
+    }
+    let data = get_object_bytes(bucket_name, BUNDLE_INDEX_KEY, client).await?;
+    Ok(serde_json::from_slice(&data)?)
+}
+
+fn load_local_logs() -> Vec<BundledLog> {
+    let paths = match log_index() {
+        Ok(paths) => paths,
+        Err(e) => {
+            error!("error {e} getting paths");
+            return vec![];
+        }
+    };
+    let mut logs = Vec::new();
+    for path in paths {
+        let Some(filename) = log_name(&path) else {
+            continue;
+        };
+        match load_log::<serde_json::Value>(&path) {
+            Ok(log) => logs.push(BundledLog { filename, log }),
+            Err(e) => error!("error {e} loading file {:?}", path),
+        }
+    }
+    logs
+}
+
+async fn sync_bundles(
+    bucket_name: &str, 
+    client: &aws_sdk_s3::Client, 
+    dry_run: bool,
+    remote_keys: &HashSet<String>,
+    manifest: &SyncManifest,
+    changed: &HashSet<String>) {
+
+    let mut index = match fetch_bundle_index(bucket_name, client, remote_keys).await {
+        Ok(index) => index,
+        Err(e) => {
+            error!("error {e} fetching {}", BUNDLE_INDEX_KEY);
+            return;
+        }
+    };
+    let mut index_changed = false;
+
+    for (month, mut logs) in group_by_month(load_local_logs()) {
+        let key = bundle_key(&month);
+        let remote_files = index.bundles.get(&month).map(|b| b.files.clone()).unwrap_or_default();
+        let has_deletes = remote_files.iter().any(|f| manifest.is_deleted(f));
+        let has_changes = logs.iter().any(|l| changed.contains(&l.filename));
+        if !has_deletes && !has_changes && logs.iter().all(|l| remote_files.contains(&l.filename)) {
+            continue;
+        }
+        if dry_run {
+            info!("Would upload bundle {} ({} logs)", key, logs.len());
+            continue;
+        }
+
+        // Keep logs that only exist remotely so re-packing a month never drops them
+        if !remote_files.is_empty() {
+            match get_object_bytes(bucket_name, &key, client).await.and_then(|d| Ok(unpack_bundle(&d)?)) {
+                Ok(remote_logs) => {
+                    for remote_log in remote_logs {
+                        if manifest.is_deleted(&remote_log.filename) {
+                            continue;
+                        }
+                        if !logs.iter().any(|l| l.filename == remote_log.filename) {
+                            logs.push(remote_log);
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!("error {e} downloading bundle {}", key);
+                    continue;
+                }
+            }
+        }
+
+        let body = match pack_bundle(&logs) {
+            Ok(body) => body,
+            Err(e) => {
+                error!("error {e} packing bundle {}", key);
+                continue;
+            }
+        };
+        match put_object_bytes(bucket_name, &key, body, client).await {
+            Ok(_) => {
+                info!("Uploaded bundle {} ({} logs)", key, logs.len());
+                index.bundles.insert(month, BundleEntry {
+                    key,
+                    files: logs.into_iter().map(|l| l.filename).collect(),
+                    updated_at: chrono::Utc::now().to_rfc3339(),
+                });
+                index_changed = true;
+            }
+            Err(e) => error!("error {e} uploading bundle {}", key),
+        }
+    }
+
+    if index_changed {
+        let body = match serde_json::to_vec_pretty(&index) {
+            Ok(body) => body,
+            Err(e) => {
+                error!("error {e} serializing {}", BUNDLE_INDEX_KEY);
+                return;
+            }
+        };
+        match put_object_bytes(bucket_name, BUNDLE_INDEX_KEY, body, client).await {
+            Ok(_) => info!("Uploaded {}", BUNDLE_INDEX_KEY),
+            Err(e) => error!("error {e} uploading {}", BUNDLE_INDEX_KEY),
+        }
+    }
+}
+
+async fn pull_bundles(
+    bucket_name: &str, 
+    client: &aws_sdk_s3::Client, 
+    dry_run: bool,
+    remote_keys: &HashSet<String>,
+    local_paths: &HashSet<String>,
+    manifest: &SyncManifest) {
+
+    let index = match fetch_bundle_index(bucket_name, client, remote_keys).await {
+        Ok(index) => index,
+        Err(e) => {
+            error!("error {e} fetching {}", BUNDLE_INDEX_KEY);
+            return;
+        }
+    };
+
+    let mut state = current_sync_state();
+    for bundle in index.bundles.values() {
+        let missing: Vec<&String> = bundle.files
+            .iter()
+            .filter(|f| !local_paths.contains(*f) && !manifest.is_deleted(f))
+            .collect();
+        if missing.is_empty() {
+            continue;
+        }
+        if dry_run {
+            info!("Would download bundle {} ({} logs)", bundle.key, missing.len());
+            continue;
+        }
+        let logs = match get_object_bytes(bucket_name, &bundle.key, client).await.and_then(|d| Ok(unpack_bundle(&d)?)) {
+            Ok(logs) => logs,
+            Err(e) => {
+                error!("error {e} downloading bundle {}", bundle.key);
+                continue;
+            }
+        };
+        for log in logs.iter().filter(|l| missing.contains(&&l.filename)) {
+            match save_log(&log.log, &log.filename) {
+                Ok(()) => {
+                    info!("Unpacked {} from {}", log.filename, bundle.key);
+                    record_transfer(&mut state, &log.filename, &log_path(&log.filename), None);
+                }
+                Err(e) => error!("error {e} unpacking {}", log.filename),
+            }
+        }
+    }
+    if !dry_run {
+        finish_transfers(&state);
+    }
 }
 
 #[cfg(test)]
@@ -185,7 +666,7 @@ mod tests {
         let config = defaults(BehaviorVersion::latest()).load().await;
         let client = aws_sdk_s3::Client::new(&config);
 
-        sync("test-bucket", &client, true, &remote_keys, &local_paths).await;
+        sync("test-bucket", &client, true, &remote_keys, &local_paths, &HashSet::new()).await;
 
         assert!(logs_contain("Would upload workouts/2024-04-02_workout.json"));
         assert!(!logs_contain("climbs/2024-04-01_climb.json"));
@@ -211,4 +692,15 @@ mod tests {
         assert!(logs_contain("Would download workouts/2024-04-02_workout.json"));
         assert!(!logs_contain("climbs/2024-04-01_climb.json"));
     }
+
+    #[test]
+    fn test_user_logs_have_their_own_keys() {
+        let name = "users/6f1c/climb-2024-04-01.json";
+        assert_eq!(bucket_key(name).as_deref(), Some("users/6f1c/climbs/climb-2024-04-01.json"));
+        assert_eq!(key_log_name("users/6f1c/climbs/climb-2024-04-01.json").as_deref(), Some(name));
+        assert_eq!(key_log_name("climbs/climb-2024-04-01.json").as_deref(), Some("climb-2024-04-01.json"));
+        assert_eq!(key_log_name("trash/climbs/climb-2024-04-01.json"), None);
+        assert_eq!(key_log_name("bundles/2024-04.json.gz"), None);
+        assert_eq!(bucket_key(".trash/climb-2024-04-01.json"), None);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,95 @@
+use super::models::{ClimbMetricsEntry, ClimbingSession, TeamMember, WorkoutSession};
+use super::pyramid::{build_pyramid, Pyramid};
+use super::summary::{build_summary, Summary};
+use super::utils::DateRange;
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+pub struct AthleteLogs {
+    pub member: TeamMember,
+    pub sessions: Vec<ClimbingSession>,
+    pub workouts: Vec<WorkoutSession>,
+    pub metrics: Vec<ClimbMetricsEntry>,
+}
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AthleteStats {
+    pub user_id: Uuid,
+    pub username: String,
+    pub summary: Summary,
+}
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamStats {
+    pub athletes: Vec<AthleteStats>,
+    pub team: Summary,
+    pub pyramid: Pyramid,
+}
+
+// Private sessions are dropped before anything is counted, so they never show up in a coach's view
+pub fn build_team_stats(athletes: Vec<AthleteLogs>, range: &DateRange) -> TeamStats {
+    let mut stats = Vec::new();
+    let mut sessions = Vec::new();
+    let mut workouts = Vec::new();
+    let mut metrics = Vec::new();
+    for mut athlete in athletes {
+        athlete.sessions.retain(|s| !s.private);
+        stats.push(AthleteStats {
+            user_id: athlete.member.user_id,
+            username: athlete.member.username,
+            summary: build_summary(&athlete.sessions, &athlete.workouts, &athlete.metrics, range),
+        });
+        sessions.extend(athlete.sessions);
+        workouts.extend(athlete.workouts);
+        metrics.extend(athlete.metrics);
+    }
+
+    TeamStats {
+        athletes: stats,
+        team: build_summary(&sessions, &workouts, &metrics, range),
+        pyramid: build_pyramid(&sessions, range),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::climblib::test_support::{climb, exercise, session, workout};
+    use crate::climblib::models::{BoulderGrade, ClimbStyle, Grade, TeamRole};
+
+    fn athlete(username: &str, sessions: Vec<ClimbingSession>, workouts: Vec<WorkoutSession>) -> AthleteLogs {
+        AthleteLogs {
+            member: TeamMember { user_id: Uuid::new_v4(), username: username.to_string(), role: TeamRole::Athlete },
+            sessions,
+            workouts,
+            metrics: vec![],
+        }
+    }
+
+    #[test]
+    fn test_team_stats_skip_private_sessions() {
+        let mut private = session("2024-04-03", ClimbStyle::Boulder, vec![climb(Grade::Boulder(BoulderGrade::V9), 1, true, false)]);
+        private.private = true;
+        let athletes = vec![
+            athlete("ana", vec![
+                session("2024-04-01", ClimbStyle::Boulder, vec![climb(Grade::Boulder(BoulderGrade::V4), 2, true, false)]),
+                private,
+            ], vec![]),
+            athlete("ben", vec![
+                session("2024-04-02", ClimbStyle::Boulder, vec![climb(Grade::Boulder(BoulderGrade::V6), 3, true, false)]),
+            ], vec![workout("2024-04-02", vec![exercise("Squat", 5, 200, None)])]),
+        ];
+
+        let stats = build_team_stats(athletes, &DateRange::default());
+
+        assert_eq!(stats.athletes[0].summary.climbing_sessions, 1);
+        assert_eq!(stats.athletes[0].summary.hardest_boulder.as_deref(), Some("v4"));
+        assert_eq!(stats.team.climbing_sessions, 2);
+        assert_eq!(stats.team.workouts, 1);
+        assert_eq!(stats.team.hardest_boulder.as_deref(), Some("v6"));
+        assert_eq!(stats.pyramid.boulder.len(), 2);
+    }
+}
@@ -0,0 +1,46 @@
+// Log builders shared by the analytics tests
+use super::models::{ClimbEntry, ClimbStyle, ClimbingSession, ExerciseEntry, Grade, WorkoutSession};
+
+pub(crate) fn climb(grade: Grade, attempts: u8, sent: bool, lead: bool) -> ClimbEntry {
+    ClimbEntry {
+        name: None,
+        grade,
+        attempts,
+        sent,
+        reached_top: sent,
+        lead,
+        rests: None,
+    }
+}
+
+pub(crate) fn session(date: &str, style: ClimbStyle, climbs: Vec<ClimbEntry>) -> ClimbingSession {
+    ClimbingSession {
+        id: None,
+        date: date.to_string(),
+        location: "Movement".to_string(),
+        style,
+        notes: None,
+        climbs,
+        private: false,
+    }
+}
+
+pub(crate) fn exercise(name: &str, reps: u8, weight_lb: i32, rpe: Option<u8>) -> ExerciseEntry {
+    ExerciseEntry {
+        name: name.to_string(),
+        sets: 3,
+        reps,
+        weight_lb,
+        rpe,
+        is_main_lift: Some(true),
+    }
+}
+
+pub(crate) fn workout(date: &str, exercises: Vec<ExerciseEntry>) -> WorkoutSession {
+    WorkoutSession {
+        id: None,
+        date: date.to_string(),
+        notes: None,
+        exercises,
+    }
+}
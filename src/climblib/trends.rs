@@ -0,0 +1,279 @@
+use super::io::load_logs;
+use super::models::{ClimbMetricsEntry, ClimbingSession, Grade};
+use super::utils::{is_climb, is_metrics, parse_date, DateRange, Period};
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tracing::error;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+pub const DAYS_PER_MONTH: f64 = 30.44;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Regression {
+    pub slope: f64,
+    pub intercept: f64,
+    pub r_squared: f64,
+}
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricTrend {
+    pub metric: String,
+    pub points: Vec<(NaiveDate, f64)>,
+    pub regression: Option<Regression>,
+    pub change_per_month: Option<f64>,
+}
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CorrelationWindow {
+    pub period: String,
+    pub finger_strength: f64,
+    pub max_grade: String,
+}
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Correlation {
+    pub discipline: String,
+    pub windows: Vec<CorrelationWindow>,
+    pub pearson_r: Option<f64>,
+}
+
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TrendReport {
+    pub finger_strength: MetricTrend,
+    pub max_pullup: MetricTrend,
+    pub correlations: Vec<Correlation>,
+}
+
+pub fn linear_regression(points: &[(f64, f64)]) -> Option<Regression> {
+    let n = points.len() as f64;
+    if points.len() < 2 {
+        return None;
+    }
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let sxx: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let syy: f64 = points.iter().map(|(_, y)| (y - mean_y).powi(2)).sum();
+    let sxy: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    if sxx == 0.0 {
+        return None;
+    }
+    let slope = sxy / sxx;
+    Some(Regression {
+        slope,
+        intercept: mean_y - slope * mean_x,
+        r_squared: if syy == 0.0 { 1.0 } else { sxy * sxy / (sxx * syy) },
+    })
+}
+
+pub fn pearson(pairs: &[(f64, f64)]) -> Option<f64> {
+    let regression = linear_regression(pairs)?;
+    let n = pairs.len() as f64;
+    let mean_y = pairs.iter().map(|(_, y)| y).sum::<f64>() / n;
+    if pairs.iter().all(|(_, y)| *y == mean_y) {
+        return None;
+    }
+    Some(regression.r_squared.sqrt().copysign(regression.slope))
+}
+
+fn metric_trend(metric: &str, points: Vec<(NaiveDate, f64)>) -> MetricTrend {
+    let first = points.first().map(|(d, _)| *d);
+    let xy: Vec<(f64, f64)> = points
+        .iter()
+        .filter_map(|(d, v)| first.map(|f| ((*d - f).num_days() as f64, *v)))
+        .collect();
+    let regression = linear_regression(&xy);
+    MetricTrend {
+        metric: metric.to_string(),
+        points,
+        regression,
+        change_per_month: regression.map(|r| r.slope * DAYS_PER_MONTH),
+    }
+}
+
+fn metric_points(
+    metrics: &[ClimbMetricsEntry],
+    range: &DateRange,
+    value: fn(&ClimbMetricsEntry) -> Option<f32>) -> Vec<(NaiveDate, f64)> {
+
+    let mut points: Vec<(NaiveDate, f64)> = metrics
+        .iter()
+        .filter(|m| range.contains(&m.date))
+        .filter_map(|m| Some((parse_date(&m.date)?, value(m)? as f64)))
+        .collect();
+    points.sort_by_key(|(d, _)| *d);
+    points
+}
+
+fn correlate(
+    discipline: &str,
+    strength: &BTreeMap<NaiveDate, Vec<f64>>,
+    max_grades: &BTreeMap<NaiveDate, Grade>,
+    period: Period) -> Correlation {
+
+    let matched: Vec<(NaiveDate, f64, Grade)> = strength
+        .iter()
+        .filter_map(|(start, values)| {
+            let grade = max_grades.get(start)?;
+            Some((*start, values.iter().sum::<f64>() / values.len() as f64, *grade))
+        })
+        .collect();
+    let pairs: Vec<(f64, f64)> = matched
+        .iter()
+        .map(|(_, strength, grade)| (*strength, grade.rank() as f64))
+        .collect();
+    let windows = matched
+        .into_iter()
+        .map(|(start, finger_strength, grade)| CorrelationWindow {
+            period: period.label(start),
+            finger_strength,
+            max_grade: grade.to_string(),
+        })
+        .collect();
+
+    Correlation {
+        discipline: discipline.to_string(),
+        windows,
+        pearson_r: pearson(&pairs),
+    }
+}
+
+pub fn build_trends(
+    metrics: &[ClimbMetricsEntry],
+    sessions: &[ClimbingSession],
+    range: &DateRange,
+    period: Period) -> TrendReport {
+
+    let finger_points = metric_points(metrics, range, |m| m.finger_strength_percent_bw);
+    let pullup_points = metric_points(metrics, range, |m| m.max_pullup_percent_bw);
+
+    let mut strength: BTreeMap<NaiveDate, Vec<f64>> = BTreeMap::new();
+    for (date, value) in &finger_points {
+        strength.entry(period.start(*date)).or_default().push(*value);
+    }
+
+    let mut max_boulder: BTreeMap<NaiveDate, Grade> = BTreeMap::new();
+    let mut max_rope: BTreeMap<NaiveDate, Grade> = BTreeMap::new();
+    for session in sessions.iter().filter(|s| range.contains(&s.date)) {
+        let Some(date) = parse_date(&session.date) else {
+            continue;
+        };
+        for climb in session.climbs.iter().filter(|c| c.sent) {
+            let maxes = match climb.grade {
+                Grade::Boulder(_) => &mut max_boulder,
+                Grade::Rope(_) => &mut max_rope,
+            };
+            let best = maxes.entry(period.start(date)).or_insert(climb.grade);
+            *best = (*best).max(climb.grade);
+        }
+    }
+
+    TrendReport {
+        finger_strength: metric_trend("fingerStrengthPercentBw", finger_points),
+        max_pullup: metric_trend("maxPullupPercentBw", pullup_points),
+        correlations: vec![
+            correlate("boulder", &strength, &max_boulder, period),
+            correlate("rope", &strength, &max_rope, period),
+        ],
+    }
+}
+
+fn format_trend(out: &mut String, title: &str, trend: &MetricTrend) {
+    match (trend.points.first(), trend.points.last(), trend.regression, trend.change_per_month) {
+        (Some(first), Some(last), Some(regression), Some(change)) => out.push_str(&format!(
+            "{}: {:.1}% -> {:.1}% BW over {} tests, {:+.2}% BW per month (r² {:.2})\n",
+            title, first.1, last.1, trend.points.len(), change, regression.r_squared
+        )),
+        _ => out.push_str(&format!("{}: not enough tests for a trend\n", title)),
+    }
+}
+
+pub fn format_trends(report: &TrendReport) -> String {
+    let mut out = String::new();
+    format_trend(&mut out, "Finger strength", &report.finger_strength);
+    format_trend(&mut out, "Max pull-up", &report.max_pullup);
+    for correlation in &report.correlations {
+        match correlation.pearson_r {
+            Some(r) => out.push_str(&format!(
+                "Finger strength vs max {} grade: r = {:.2} over {} windows\n",
+                correlation.discipline, r, correlation.windows.len()
+            )),
+            None => out.push_str(&format!(
+                "Finger strength vs max {} grade: not enough matching windows\n",
+                correlation.discipline
+            )),
+        }
+    }
+    out
+}
+
+pub fn print_trends(user: Option<Uuid>, range: &DateRange, period: Period, json: bool) {
+    let metrics: Vec<ClimbMetricsEntry> = load_logs(user, is_metrics);
+    let sessions: Vec<ClimbingSession> = load_logs(user, is_climb);
+    let report = build_trends(&metrics, &sessions, range, period);
+    if json {
+        match serde_json::to_string_pretty(&report) {
+            Ok(out) => println!("{}", out),
+            Err(e) => error!("error {e} serializing trends"),
+        }
+    } else {
+        print!("{}", format_trends(&report));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::climblib::models::{BoulderGrade, ClimbStyle};
+    use crate::climblib::test_support::{climb, session};
+
+    fn metrics(date: &str, finger: f32) -> ClimbMetricsEntry {
+        ClimbMetricsEntry {
+            id: None,
+            date: date.to_string(),
+            finger_strength_percent_bw: Some(finger),
+            max_pullup_percent_bw: None,
+            notes: None,
+        }
+    }
+
+    #[test]
+    fn test_linear_regression() {
+        let regression = linear_regression(&[(0.0, 1.0), (1.0, 3.0), (2.0, 5.0)]).unwrap();
+        assert!((regression.slope - 2.0).abs() < 1e-9);
+        assert!((regression.intercept - 1.0).abs() < 1e-9);
+        assert!((regression.r_squared - 1.0).abs() < 1e-9);
+        assert_eq!(linear_regression(&[(0.0, 1.0)]), None);
+        assert!((pearson(&[(0.0, 3.0), (1.0, 2.0), (2.0, 1.0)]).unwrap() + 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_build_trends() {
+        let metrics = vec![
+            metrics("2024-01-01", 120.0),
+            metrics("2024-02-01", 125.0),
+            metrics("2024-03-02", 130.0),
+        ];
+        let sessions = vec![
+            session("2024-01-10", ClimbStyle::Boulder, vec![climb(Grade::Boulder(BoulderGrade::V4), 1, true, false)]),
+            session("2024-02-10", ClimbStyle::Boulder, vec![climb(Grade::Boulder(BoulderGrade::V5), 1, true, false)]),
+            session("2024-03-10", ClimbStyle::Boulder, vec![climb(Grade::Boulder(BoulderGrade::V6), 1, true, false)]),
+        ];
+
+        let report = build_trends(&metrics, &sessions, &DateRange::default(), Period::Month);
+
+        let change = report.finger_strength.change_per_month.unwrap();
+        assert!((change - 5.0).abs() < 0.1);
+        assert!(report.max_pullup.regression.is_none());
+        let boulder = &report.correlations[0];
+        assert_eq!(boulder.windows.len(), 3);
+        assert!((boulder.pearson_r.unwrap() - 1.0).abs() < 1e-9);
+        assert_eq!(report.correlations[1].pearson_r, None);
+    }
+}
@@ -1,23 +1,24 @@
 use std::path::Path;
 use validator::ValidationError;
-use chrono::NaiveDate;
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::Deserialize;
 
 pub fn is_climb(path: &Path) -> bool {
     path.file_name()
         .and_then(|f| f.to_str())
-        .map_or(false, |name| name.contains("climb"))
+        .is_some_and(|name| name.contains("climb"))
 }
 
 pub fn is_workout(path: &Path) -> bool {
     path.file_name()
         .and_then(|f| f.to_str())
-        .map_or(false, |name| name.contains("workout"))
+        .is_some_and(|name| name.contains("workout"))
 }
 
 pub fn is_metrics(path: &Path) -> bool {
     path.file_name()
         .and_then(|f| f.to_str())
-        .map_or(false, |name| name.contains("metrics"))
+        .is_some_and(|name| name.contains("metrics"))
 }
 
 pub fn infer_log_type(path: &Path) -> Option<&'static str> {
@@ -32,6 +33,67 @@ pub fn infer_log_type(path: &Path) -> Option<&'static str> {
     }
 }
 
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DateRange {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl DateRange {
+    pub fn new(from: Option<NaiveDate>, to: Option<NaiveDate>) -> Self {
+        DateRange { from, to }
+    }
+
+    // Both ends are inclusive, and logs with unparseable dates never match a bounded range
+    pub fn contains(&self, date: &str) -> bool {
+        if self.from.is_none() && self.to.is_none() {
+            return true;
+        }
+        match parse_date(date) {
+            Some(d) => self.from.is_none_or(|f| d >= f) && self.to.is_none_or(|t| d <= t),
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Week,
+    Month,
+    Year,
+}
+
+impl Period {
+    pub fn start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Period::Month => date.with_day(1).unwrap_or(date),
+            Period::Year => date.with_ordinal(1).unwrap_or(date),
+        }
+    }
+
+    pub fn next(&self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Week => start + Duration::days(7),
+            Period::Month => start.checked_add_months(chrono::Months::new(1)).unwrap_or(start),
+            Period::Year => start.checked_add_months(chrono::Months::new(12)).unwrap_or(start),
+        }
+    }
+
+    pub fn label(&self, start: NaiveDate) -> String {
+        match self {
+            Period::Week => start.format("%G-W%V").to_string(),
+            Period::Month => start.format("%Y-%m").to_string(),
+            Period::Year => start.format("%Y").to_string(),
+        }
+    }
+}
+
+pub fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
 pub fn validate_date_format(date: &str) -> Result<(), ValidationError> {
     match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
         Ok(_) => Ok(()),
@@ -73,4 +135,31 @@ mod tests {
         assert_eq!(infer_log_type(&path("2024-04-01_metrics.json")), Some("metrics"));
         assert_eq!(infer_log_type(&path("2024-04-01_unknown.json")), None);
     }
+
+    #[test]
+    fn test_date_range_contains() {
+        let april = DateRange::new(
+            NaiveDate::from_ymd_opt(2024, 4, 1),
+            NaiveDate::from_ymd_opt(2024, 4, 30),
+        );
+        assert!(april.contains("2024-04-01"));
+        assert!(april.contains("2024-04-30"));
+        assert!(!april.contains("2024-05-01"));
+        assert!(!april.contains("04-06-2024"));
+        assert!(DateRange::default().contains("04-06-2024"));
+    }
+
+    #[test]
+    fn test_period_buckets() {
+        let date = NaiveDate::from_ymd_opt(2024, 4, 18).unwrap();
+        let week = Period::Week.start(date);
+        assert_eq!(week, NaiveDate::from_ymd_opt(2024, 4, 15).unwrap());
+        assert_eq!(Period::Week.label(week), "2024-W16");
+        assert_eq!(Period::Month.label(Period::Month.start(date)), "2024-04");
+        assert_eq!(
+            Period::Month.next(Period::Month.start(date)),
+            NaiveDate::from_ymd_opt(2024, 5, 1).unwrap()
+        );
+        assert_eq!(Period::Year.start(date), NaiveDate::from_ymd_opt(2024, 1, 1).unwrap());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,149 @@
+use super::io::{LOG_DIR, USER_LOG_DIR};
+use super::manifest::log_path;
+use super::status::{FileInfo, SyncState, load_sync_state, local_file_info};
+use super::sync::{bucket_key, sync_with_client, AwsActions, SyncMode};
+
+use aws_config::defaults;
+use aws_config::BehaviorVersion;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use tokio::time::{self, Duration, Instant};
+use tracing::{info, error};
+
+pub const DEFAULT_DEBOUNCE_SECS: u64 = 2;
+pub const DEFAULT_PULL_INTERVAL_SECS: u64 = 300;
+
+pub struct WatchOptions {
+    pub dry_run: bool,
+    pub mode: SyncMode,
+    pub debounce: Duration,
+    pub pull_interval: Duration,
+    pub retention_days: i64,
+}
+
+// Logs directly in logs/ or in a user's directory are synced, so anything under
+// logs/.trash is ignored. Returns the log's name as `log_name` gives it.
+fn watched_log(path: &Path) -> Option<String> {
+    let filename = path.file_name()?.to_str()?;
+    let dir = path.parent()?;
+    let name = if dir.file_name()? == LOG_DIR {
+        filename.to_string()
+    } else {
+        let users = dir.parent()?;
+        if users.file_name()? != USER_LOG_DIR || users.parent()?.file_name()? != LOG_DIR {
+            return None;
+        }
+        format!("{}/{}/{}", USER_LOG_DIR, dir.file_name()?.to_str()?, filename)
+    };
+    bucket_key(&name).map(|_| name)
+}
+
+// A file that still matches its last recorded transfer was written by a pull, not edited
+fn changed_since_transfer(local: &FileInfo, base: Option<&FileInfo>) -> bool {
+    base.is_none_or(|base| local.size != base.size || local.modified != base.modified)
+}
+
+fn edited_logs(state: &SyncState, filenames: HashSet<String>) -> HashSet<String> {
+    filenames
+        .into_iter()
+        .filter(|filename| {
+            local_file_info(&log_path(filename)).is_ok_and(|info| changed_since_transfer(&info, state.files.get(filename)))
+        })
+        .collect()
+}
+
+pub async fn watch_logs(bucket_name: String, options: WatchOptions) {
+    let config = defaults(BehaviorVersion::latest()).load().await;
+    let client = aws_sdk_s3::Client::new(&config);
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<PathBuf>();
+    let mut watcher = match notify::recommended_watcher(move |res: notify::Result<Event>| {
+        match res {
+            Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                for path in event.paths {
+                    let _ = tx.send(path);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => error!("error {e} watching logs"),
+        }
+    }) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            error!("error {e} creating log watcher");
+            return;
+        }
+    };
+    // Recursive so users' directories are covered, including ones created while watching
+    if let Err(e) = watcher.watch(Path::new(LOG_DIR), RecursiveMode::Recursive) {
+        error!("error {e} watching logs");
+        return;
+    }
+    info!("Watching logs for changes");
+
+    let mut pending: HashSet<String> = HashSet::new();
+    let mut deadline = Instant::now();
+    let mut pull_timer = time::interval(options.pull_interval);
+
+    loop {
+        tokio::select! {
+            Some(path) = rx.recv() => {
+                if let Some(filename) = watched_log(&path) {
+                    pending.insert(filename);
+                    deadline = Instant::now() + options.debounce;
+                }
+            }
+            _ = time::sleep_until(deadline), if !pending.is_empty() => {
+                let state = load_sync_state().unwrap_or_else(|e| {
+                    error!("error {e} loading sync state");
+                    SyncState::default()
+                });
+                let changed = edited_logs(&state, std::mem::take(&mut pending));
+                if !changed.is_empty() {
+                    sync_with_client(AwsActions::Sync, &bucket_name, &client, options.dry_run, options.mode, options.retention_days, &changed).await;
+                }
+            }
+            _ = pull_timer.tick() => {
+                sync_with_client(AwsActions::Pull, &bucket_name, &client, options.dry_run, options.mode, options.retention_days, &HashSet::new()).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watched_log() {
+        assert_eq!(
+            watched_log(Path::new("logs/climb-2024-04-01.json")),
+            Some("climb-2024-04-01.json".to_string())
+        );
+        assert_eq!(
+            watched_log(Path::new("/home/me/logs/workout-2024-04-02.json")),
+            Some("workout-2024-04-02.json".to_string())
+        );
+        assert_eq!(
+            watched_log(Path::new("/home/me/logs/users/6f1c/climb-2024-04-01.json")),
+            Some("users/6f1c/climb-2024-04-01.json".to_string())
+        );
+        assert_eq!(watched_log(Path::new("logs/.trash/climb-2024-04-01.json")), None);
+        assert_eq!(watched_log(Path::new("logs/.trash/users/6f1c/climb-2024-04-01.json")), None);
+        assert_eq!(watched_log(Path::new("logs/.manifest.json")), None);
+    }
+
+    #[test]
+    fn test_pulled_files_are_not_pushed_back() {
+        let modified = chrono::DateTime::from_timestamp(1_712_000_000, 0);
+        let pulled = FileInfo { size: 120, modified, e_tag: None };
+        let recorded = FileInfo { e_tag: Some("\"abc\"".to_string()), ..pulled.clone() };
+        assert!(!changed_since_transfer(&pulled, Some(&recorded)));
+
+        let edited = FileInfo { size: 140, ..pulled.clone() };
+        assert!(changed_since_transfer(&edited, Some(&recorded)));
+        assert!(changed_since_transfer(&pulled, None));
+    }
+}
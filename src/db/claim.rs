@@ -0,0 +1,61 @@
+use crate::climblib::manifest::claim_logs;
+use crate::db::queries::{claim_unowned_rows_db, fetch_user_by_username_db};
+use crate::db::snapshot::connect;
+
+use sqlx::PgPool;
+use tracing::{info, error};
+use uuid::Uuid;
+
+async fn lookup_user(pool: &PgPool, username: &str) -> Option<Uuid> {
+    match fetch_user_by_username_db(pool, username).await {
+        Ok(Some((user_id, _))) => Some(user_id),
+        Ok(None) => {
+            error!("No user named {}", username);
+            None
+        }
+        Err(e) => {
+            error!("error {e} looking up {}", username);
+            None
+        }
+    }
+}
+
+// The id behind --user, whose directory the stats read along with the top-level logs
+pub async fn find_user(db_connection_str: &str, username: &str) -> Option<Uuid> {
+    match connect(db_connection_str).await {
+        Ok(pool) => lookup_user(&pool, username).await,
+        Err(e) => {
+            error!("error {e} connecting to database");
+            None
+        }
+    }
+}
+
+// A one-time move for data logged before accounts existed: the top-level log files and the
+// rows without an owner all go to `username`, after which the API shows them to that user
+pub async fn claim_entrypoint(db_connection_str: &str, username: &str) {
+    let pool = match connect(db_connection_str).await {
+        Ok(pool) => pool,
+        Err(e) => {
+            error!("error {e} connecting to database");
+            return;
+        }
+    };
+    let Some(user_id) = lookup_user(&pool, username).await else {
+        return;
+    };
+
+    match claim_logs(user_id) {
+        Ok(claimed) => {
+            for filename in &claimed {
+                info!("Moved {} to {}", filename, username);
+            }
+            info!("Claimed {} log files for {}, run --sync to move them in the bucket too", claimed.len(), username);
+        }
+        Err(e) => error!("error {e} claiming log files"),
+    }
+    match claim_unowned_rows_db(&pool, user_id).await {
+        Ok(rows) => info!("Claimed {} database rows for {}", rows, username),
+        Err(e) => error!("error {e} claiming database rows"),
+    }
+}
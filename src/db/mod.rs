@@ -1 +1,3 @@
-pub mod queries;
\ No newline at end of file
+pub mod claim;
+pub mod queries;
+pub mod snapshot;
\ No newline at end of file
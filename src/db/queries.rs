@@ -1,25 +1,65 @@
-use crate::climblib::models::{ClimbMetricsEntry, ClimbingSession, Grade, WorkoutSession};
+use crate::climblib::models::{
+    ClimbEntry, ClimbMetricsEntry, ClimbingSession, ExerciseEntry, Goal, GoalStatus, GoalTarget, Grade, SessionComment,
+    TeamInvitation, TeamMember, TeamMembership, TeamRole, WorkoutSession,
+};
 use chrono::NaiveDate;
-use sqlx::postgres::PgPool;
+use serde_json::Value;
+use sqlx::postgres::{PgConnection, PgPool};
+use std::collections::HashMap;
 use uuid::Uuid;
 
 
-pub async fn insert_climb_db(pool: &PgPool, session: ClimbingSession) -> Result<(), sqlx::Error> {
-    let session_id = Uuid::new_v4();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveOutcome {
+    Created(Uuid),
+    // The same user already saved a log with this id, so nothing was written
+    Replayed(Uuid),
+    // The id belongs to another user's log
+    Taken(Uuid),
+}
 
-    sqlx::query!(
+impl SaveOutcome {
+    fn existing(owner: Option<Uuid>, user_id: Uuid, id: Uuid) -> Self {
+        if owner == Some(user_id) { SaveOutcome::Replayed(id) } else { SaveOutcome::Taken(id) }
+    }
+}
+
+// Logs keep a client-supplied id, so a retried save finds the first one instead of adding
+// a duplicate. The session and its entries are written together or not at all. The `_in` variants
+// run on a caller's transaction so a batch can share one.
+pub async fn insert_climb_db(pool: &PgPool, user_id: Uuid, session: ClimbingSession) -> Result<SaveOutcome, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let outcome = insert_climb_in(&mut tx, user_id, session).await?;
+    tx.commit().await?;
+    Ok(outcome)
+}
+
+pub async fn insert_climb_in(conn: &mut PgConnection, user_id: Uuid, session: ClimbingSession) -> Result<SaveOutcome, sqlx::Error> {
+    let session_id = session.id.unwrap_or_else(Uuid::new_v4);
+
+    let inserted = sqlx::query!(
         r#"
-        INSERT INTO climbing_sessions (id, date, location, style, notes)
-        VALUES ($1, $2, $3, $4, $5)
+        INSERT INTO climbing_sessions (id, date, location, style, notes, user_id, private)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (id) DO NOTHING
+        RETURNING id
         "#,
         session_id,
         NaiveDate::parse_from_str(&session.date, "%Y-%m-%d").unwrap(), //validated as date in ClimbingSession struct
         session.location,
         session.style.to_string(),
-        session.notes
+        session.notes,
+        user_id,
+        session.private
     )
-    .execute(pool)
+    .fetch_optional(&mut *conn)
     .await?;
+    if inserted.is_none() {
+        let owner = sqlx::query_scalar!("SELECT user_id FROM climbing_sessions WHERE id = $1", session_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+        return Ok(SaveOutcome::existing(owner.flatten(), user_id, session_id));
+    }
 
     for climb in session.climbs {
         let climb_id = Uuid::new_v4();
@@ -42,27 +82,43 @@ pub async fn insert_climb_db(pool: &PgPool, session: ClimbingSession) -> Result<
             climb.lead,
             climb.rests.map(|r| r as i16)
         )
-        .execute(pool)
+        .execute(&mut *conn)
         .await?;
     }
 
-    Ok(())
+    Ok(SaveOutcome::Created(session_id))
 }
 
-pub async fn insert_workout_db(pool: &PgPool, session: WorkoutSession) -> Result<(), sqlx::Error> {
-    let session_id = Uuid::new_v4();
+pub async fn insert_workout_db(pool: &PgPool, user_id: Uuid, session: WorkoutSession) -> Result<SaveOutcome, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let outcome = insert_workout_in(&mut tx, user_id, session).await?;
+    tx.commit().await?;
+    Ok(outcome)
+}
 
-    sqlx::query!(
+pub async fn insert_workout_in(conn: &mut PgConnection, user_id: Uuid, session: WorkoutSession) -> Result<SaveOutcome, sqlx::Error> {
+    let session_id = session.id.unwrap_or_else(Uuid::new_v4);
+
+    let inserted = sqlx::query!(
         r#"
-        INSERT INTO workout_sessions (id, date, notes)
-        VALUES ($1, $2, $3)
+        INSERT INTO workout_sessions (id, date, notes, user_id)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (id) DO NOTHING
+        RETURNING id
         "#,
         session_id,
         NaiveDate::parse_from_str(&session.date, "%Y-%m-%d").unwrap(), //validated as date in WorkoutSession struct
-        session.notes
+        session.notes,
+        user_id
     )
-    .execute(pool)
+    .fetch_optional(&mut *conn)
     .await?;
+    if inserted.is_none() {
+        let owner = sqlx::query_scalar!("SELECT user_id FROM workout_sessions WHERE id = $1", session_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+        return Ok(SaveOutcome::existing(owner.flatten(), user_id, session_id));
+    }
 
     for exercise in session.exercises {
         let exercise_id = Uuid::new_v4();
@@ -81,29 +137,825 @@ pub async fn insert_workout_db(pool: &PgPool, session: WorkoutSession) -> Result
             exercise.rpe.map(|r| r as i16),
             exercise.is_main_lift
         )
-        .execute(pool)
+        .execute(&mut *conn)
         .await?;
     }
 
-    Ok(())
+    Ok(SaveOutcome::Created(session_id))
 }
 
-pub async fn insert_metrics_db(pool: &PgPool, metrics: ClimbMetricsEntry) -> Result<(), sqlx::Error> {
-    let metrics_id = Uuid::new_v4();
+pub async fn insert_metrics_db(pool: &PgPool, user_id: Uuid, metrics: ClimbMetricsEntry) -> Result<SaveOutcome, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let outcome = insert_metrics_in(&mut tx, user_id, metrics).await?;
+    tx.commit().await?;
+    Ok(outcome)
+}
 
-    sqlx::query!(
+pub async fn insert_metrics_in(conn: &mut PgConnection, user_id: Uuid, metrics: ClimbMetricsEntry) -> Result<SaveOutcome, sqlx::Error> {
+    let metrics_id = metrics.id.unwrap_or_else(Uuid::new_v4);
+
+    let inserted = sqlx::query!(
         r#"
-        INSERT INTO climbing_metrics (id, date, finger_strength_percent_bw, max_pullup_percent_bw, notes)
-        VALUES ($1, $2, $3, $4, $5)
+        INSERT INTO climbing_metrics (id, date, finger_strength_percent_bw, max_pullup_percent_bw, notes, user_id)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (id) DO NOTHING
+        RETURNING id
         "#,
         metrics_id,
         NaiveDate::parse_from_str(&metrics.date, "%Y-%m-%d").unwrap(), //validated as date in ClimbMetricsEntry struct
         metrics.finger_strength_percent_bw,
         metrics.max_pullup_percent_bw,
-        metrics.notes
+        metrics.notes,
+        user_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    if inserted.is_none() {
+        let owner = sqlx::query_scalar!("SELECT user_id FROM climbing_metrics WHERE id = $1", metrics_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+        return Ok(SaveOutcome::existing(owner.flatten(), user_id, metrics_id));
+    }
+
+    Ok(SaveOutcome::Created(metrics_id))
+}
+
+// What the first save of a log id sent and got back. Later saves with the id are checked
+// against it, so a retry gets the same answer and a different log can't reuse the id.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedResponse {
+    pub endpoint: String,
+    pub request: Value,
+    pub status: u16,
+    pub response: Value,
+}
+
+pub async fn fetch_saved_responses_in(conn: &mut PgConnection, user_id: Uuid, log_ids: &[Uuid]) -> Result<HashMap<Uuid, SavedResponse>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT log_id, endpoint, request, status, response
+        FROM saved_responses
+        WHERE user_id = $1 AND log_id = ANY($2)
+        "#,
+        user_id,
+        log_ids
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| {
+            let saved = SavedResponse { endpoint: r.endpoint, request: r.request, status: r.status as u16, response: r.response };
+            (r.log_id, saved)
+        })
+        .collect())
+}
+
+pub async fn fetch_saved_response_db(pool: &PgPool, user_id: Uuid, log_id: Uuid) -> Result<Option<SavedResponse>, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    Ok(fetch_saved_responses_in(&mut conn, user_id, &[log_id]).await?.remove(&log_id))
+}
+
+// The first save wins, so a later one through another endpoint doesn't replace it
+pub async fn insert_saved_response_in(conn: &mut PgConnection, user_id: Uuid, log_id: Uuid, saved: &SavedResponse) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO saved_responses (user_id, log_id, endpoint, request, status, response)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (user_id, log_id) DO NOTHING
+        "#,
+        user_id,
+        log_id,
+        saved.endpoint,
+        saved.request,
+        saved.status as i16,
+        saved.response
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn insert_saved_response_db(pool: &PgPool, user_id: Uuid, log_id: Uuid, saved: &SavedResponse) -> Result<(), sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    insert_saved_response_in(&mut conn, user_id, log_id, saved).await
+}
+
+pub async fn fetch_climbing_sessions_db(pool: &PgPool, user_id: Uuid) -> Result<Vec<ClimbingSession>, sqlx::Error> {
+    let sessions = sqlx::query!(
+        r#"
+        SELECT id, date, location, style, notes, private
+        FROM climbing_sessions
+        WHERE user_id = $1
+        ORDER BY date
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let entries = sqlx::query!(
+        r#"
+        SELECT e.session_id, e.name, e.grade, e.attempts, e.sent, e.reached_top, e.lead, e.rests
+        FROM climb_entries e
+        JOIN climbing_sessions s ON s.id = e.session_id
+        WHERE s.user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut climbs: HashMap<Uuid, Vec<ClimbEntry>> = HashMap::new();
+    for entry in entries {
+        let Some(session_id) = entry.session_id else {
+            continue;
+        };
+        climbs.entry(session_id).or_default().push(ClimbEntry {
+            name: entry.name,
+            grade: entry.grade.parse().map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+            attempts: entry.attempts as u8,
+            sent: entry.sent,
+            reached_top: entry.reached_top,
+            lead: entry.lead,
+            rests: entry.rests.map(|r| r as u8),
+        });
+    }
+
+    sessions
+        .into_iter()
+        .map(|s| Ok(ClimbingSession {
+            id: Some(s.id),
+            date: s.date.format("%Y-%m-%d").to_string(),
+            location: s.location,
+            style: s.style.parse().map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+            notes: s.notes,
+            climbs: climbs.remove(&s.id).unwrap_or_default(),
+            private: s.private,
+        }))
+        .collect()
+}
+
+pub async fn fetch_workouts_db(pool: &PgPool, user_id: Uuid) -> Result<Vec<WorkoutSession>, sqlx::Error> {
+    let sessions = sqlx::query!(
+        r#"
+        SELECT id, date, notes
+        FROM workout_sessions
+        WHERE user_id = $1
+        ORDER BY date
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let entries = sqlx::query!(
+        r#"
+        SELECT e.workout_session_id, e.name, e.sets, e.reps, e.weight_lb, e.rpe, e.is_main_lift
+        FROM exercise_entries e
+        JOIN workout_sessions s ON s.id = e.workout_session_id
+        WHERE s.user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut exercises: HashMap<Uuid, Vec<ExerciseEntry>> = HashMap::new();
+    for entry in entries {
+        let Some(session_id) = entry.workout_session_id else {
+            continue;
+        };
+        exercises.entry(session_id).or_default().push(ExerciseEntry {
+            name: entry.name,
+            sets: entry.sets as u8,
+            reps: entry.reps as u8,
+            weight_lb: entry.weight_lb,
+            rpe: entry.rpe.map(|r| r as u8),
+            is_main_lift: Some(entry.is_main_lift),
+        });
+    }
+
+    Ok(sessions
+        .into_iter()
+        .map(|s| WorkoutSession {
+            id: Some(s.id),
+            date: s.date.format("%Y-%m-%d").to_string(),
+            notes: s.notes,
+            exercises: exercises.remove(&s.id).unwrap_or_default(),
+        })
+        .collect())
+}
+
+pub async fn fetch_metrics_db(pool: &PgPool, user_id: Uuid) -> Result<Vec<ClimbMetricsEntry>, sqlx::Error> {
+    let metrics = sqlx::query!(
+        r#"
+        SELECT id, date, finger_strength_percent_bw, max_pullup_percent_bw, notes
+        FROM climbing_metrics
+        WHERE user_id = $1
+        ORDER BY date
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(metrics
+        .into_iter()
+        .map(|m| ClimbMetricsEntry {
+            id: Some(m.id),
+            date: m.date.format("%Y-%m-%d").to_string(),
+            finger_strength_percent_bw: m.finger_strength_percent_bw,
+            max_pullup_percent_bw: m.max_pullup_percent_bw,
+            notes: m.notes,
+        })
+        .collect())
+}
+
+pub async fn insert_goal_db(pool: &PgPool, user_id: Uuid, goal: &Goal) -> Result<Uuid, sqlx::Error> {
+    let goal_id = Uuid::new_v4();
+    let (kind, grade, exercise, target, bodyweight_lb) = match &goal.target {
+        GoalTarget::Send { grade } => ("send", Some(grade.to_string()), None, None, None),
+        GoalTarget::FingerStrength { percent_bw } => ("fingerStrength", None, None, Some(*percent_bw), None),
+        GoalTarget::MaxPullup { percent_bw } => ("maxPullup", None, None, Some(*percent_bw), None),
+        GoalTarget::Lift { exercise, bodyweight_multiple, bodyweight_lb } => {
+            ("lift", None, Some(exercise.clone()), Some(*bodyweight_multiple), Some(*bodyweight_lb))
+        }
+    };
+
+    sqlx::query!(
+        r#"
+        INSERT INTO goals (id, title, kind, grade, exercise, target, bodyweight_lb, deadline, progress, achieved_date, user_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        "#,
+        goal_id,
+        goal.title,
+        kind,
+        grade,
+        exercise,
+        target,
+        bodyweight_lb,
+        goal.deadline.as_deref().and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()),
+        goal.progress,
+        goal.achieved_date.as_deref().and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()),
+        user_id,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(goal_id)
+}
+
+pub async fn fetch_goals_db(pool: &PgPool, user_id: Uuid) -> Result<Vec<Goal>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, title, kind, grade, exercise, target, bodyweight_lb, deadline, progress, achieved_date
+        FROM goals
+        WHERE user_id = $1
+        ORDER BY created_at
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            let target = match (row.kind.as_str(), row.grade, row.exercise, row.target, row.bodyweight_lb) {
+                ("send", Some(grade), _, _, _) => GoalTarget::Send {
+                    grade: grade.parse().map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+                },
+                ("fingerStrength", _, _, Some(percent_bw), _) => GoalTarget::FingerStrength { percent_bw },
+                ("maxPullup", _, _, Some(percent_bw), _) => GoalTarget::MaxPullup { percent_bw },
+                ("lift", _, Some(exercise), Some(bodyweight_multiple), Some(bodyweight_lb)) => {
+                    GoalTarget::Lift { exercise, bodyweight_multiple, bodyweight_lb }
+                }
+                (kind, ..) => return Err(sqlx::Error::Decode(format!("invalid goal of kind {}", kind).into())),
+            };
+            Ok(Goal {
+                id: Some(row.id),
+                title: row.title,
+                target,
+                deadline: row.deadline.map(|d| d.format("%Y-%m-%d").to_string()),
+                progress: row.progress,
+                achieved_date: row.achieved_date.map(|d| d.format("%Y-%m-%d").to_string()),
+                // Depends on today's date, so callers fill it in with goal_status
+                status: GoalStatus::default(),
+            })
+        })
+        .collect()
+}
+
+pub async fn update_goal_progress_db(pool: &PgPool, goal: &Goal) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE goals SET progress = $2, achieved_date = $3
+        WHERE id = $1
+        "#,
+        goal.id,
+        goal.progress,
+        goal.achieved_date.as_deref().and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()),
     )
     .execute(pool)
     .await?;
 
     Ok(())
 }
+
+pub async fn insert_user_db(pool: &PgPool, username: &str, password_hash: &str) -> Result<Uuid, sqlx::Error> {
+    let user_id = Uuid::new_v4();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO users (id, username, password_hash)
+        VALUES ($1, $2, $3)
+        "#,
+        user_id,
+        username,
+        password_hash
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(user_id)
+}
+
+pub async fn fetch_user_by_username_db(pool: &PgPool, username: &str) -> Result<Option<(Uuid, String)>, sqlx::Error> {
+    let user = sqlx::query!(
+        r#"
+        SELECT id, password_hash
+        FROM users
+        WHERE username = $1
+        "#,
+        username
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(user.map(|u| (u.id, u.password_hash)))
+}
+
+// Gives every row logged before accounts existed to one user, in a single transaction.
+// Returns how many rows changed owner.
+pub async fn claim_unowned_rows_db(pool: &PgPool, user_id: Uuid) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let mut claimed = 0;
+    claimed += sqlx::query!(
+        r#"
+        UPDATE climbing_sessions SET user_id = $1
+        WHERE user_id IS NULL
+        "#,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    claimed += sqlx::query!(
+        r#"
+        UPDATE workout_sessions SET user_id = $1
+        WHERE user_id IS NULL
+        "#,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    claimed += sqlx::query!(
+        r#"
+        UPDATE climbing_metrics SET user_id = $1
+        WHERE user_id IS NULL
+        "#,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    claimed += sqlx::query!(
+        r#"
+        UPDATE goals SET user_id = $1
+        WHERE user_id IS NULL
+        "#,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    tx.commit().await?;
+    Ok(claimed)
+}
+
+pub async fn insert_api_token_db(pool: &PgPool, user_id: Uuid, token_hash: &str, name: Option<&str>) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO api_tokens (id, user_id, token_hash, name)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        token_hash,
+        name
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Looks up the owner of a token and marks the token as used
+pub async fn fetch_token_user_db(pool: &PgPool, token_hash: &str) -> Result<Option<(Uuid, String)>, sqlx::Error> {
+    let user = sqlx::query!(
+        r#"
+        UPDATE api_tokens t SET last_used_at = now()
+        FROM users u
+        WHERE t.token_hash = $1 AND u.id = t.user_id
+        RETURNING u.id, u.username
+        "#,
+        token_hash
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(user.map(|u| (u.id, u.username)))
+}
+
+pub async fn insert_team_db(pool: &PgPool, name: &str, coach_id: Uuid) -> Result<Uuid, sqlx::Error> {
+    let team_id = Uuid::new_v4();
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO teams (id, name)
+        VALUES ($1, $2)
+        "#,
+        team_id,
+        name
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO team_members (team_id, user_id, role)
+        VALUES ($1, $2, $3)
+        "#,
+        team_id,
+        coach_id,
+        TeamRole::Coach.to_string()
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(team_id)
+}
+
+pub async fn fetch_teams_db(pool: &PgPool, user_id: Uuid) -> Result<Vec<TeamMembership>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT t.id, t.name, m.role
+        FROM teams t
+        JOIN team_members m ON m.team_id = t.id
+        WHERE m.user_id = $1
+        ORDER BY t.created_at
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| Ok(TeamMembership {
+            team_id: row.id,
+            name: row.name,
+            role: row.role.parse().map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+        }))
+        .collect()
+}
+
+pub async fn fetch_team_role_db(pool: &PgPool, team_id: Uuid, user_id: Uuid) -> Result<Option<TeamRole>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT role
+        FROM team_members
+        WHERE team_id = $1 AND user_id = $2
+        "#,
+        team_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    row.map(|r| r.role.parse().map_err(|e| sqlx::Error::Decode(Box::new(e)))).transpose()
+}
+
+pub enum InviteOutcome {
+    Invited(TeamInvitation),
+    NoSuchUser,
+    AlreadyMember,
+}
+
+// Invites a user to a team by username. Inviting someone again replaces their pending
+// invitation; members are never touched, so their role can't change through an invite.
+pub async fn insert_team_invitation_db(pool: &PgPool, team_id: Uuid, username: &str, role: TeamRole, invited_by: Uuid) -> Result<InviteOutcome, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let Some(user) = sqlx::query!(
+        r#"
+        SELECT id
+        FROM users
+        WHERE username = $1
+        "#,
+        username
+    )
+        .fetch_optional(&mut *tx)
+        .await? else {
+        return Ok(InviteOutcome::NoSuchUser);
+    };
+    let member = sqlx::query!(
+        r#"
+        SELECT 1 AS one
+        FROM team_members
+        WHERE team_id = $1 AND user_id = $2
+        "#,
+        team_id,
+        user.id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    if member.is_some() {
+        return Ok(InviteOutcome::AlreadyMember);
+    }
+
+    let row = sqlx::query!(
+        r#"
+        WITH invited AS (
+            INSERT INTO team_invitations (id, team_id, user_id, role, invited_by)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (team_id, user_id) DO UPDATE SET role = EXCLUDED.role, invited_by = EXCLUDED.invited_by
+            RETURNING id, team_id, role, invited_by
+        )
+        SELECT i.id, i.team_id, t.name, i.role, u.username
+        FROM invited i
+        JOIN teams t ON t.id = i.team_id
+        JOIN users u ON u.id = i.invited_by
+        "#,
+        Uuid::new_v4(),
+        team_id,
+        user.id,
+        role.to_string(),
+        invited_by
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(InviteOutcome::Invited(TeamInvitation {
+        id: row.id,
+        team_id: row.team_id,
+        team_name: row.name,
+        role: row.role.parse().map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+        invited_by: row.username,
+    }))
+}
+
+pub async fn fetch_invitations_db(pool: &PgPool, user_id: Uuid) -> Result<Vec<TeamInvitation>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT i.id, i.team_id, t.name, i.role, u.username
+        FROM team_invitations i
+        JOIN teams t ON t.id = i.team_id
+        JOIN users u ON u.id = i.invited_by
+        WHERE i.user_id = $1
+        ORDER BY i.created_at
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| Ok(TeamInvitation {
+            id: row.id,
+            team_id: row.team_id,
+            team_name: row.name,
+            role: row.role.parse().map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+            invited_by: row.username,
+        }))
+        .collect()
+}
+
+// Joins the team the invitation is for. Someone who joined meanwhile keeps the role they
+// have. Returns None when the invitation doesn't exist or isn't for this user.
+pub async fn accept_invitation_db(pool: &PgPool, invitation_id: Uuid, user_id: Uuid) -> Result<Option<TeamMembership>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let Some(invitation) = sqlx::query!(
+        r#"
+        DELETE FROM team_invitations
+        WHERE id = $1 AND user_id = $2
+        RETURNING team_id, role
+        "#,
+        invitation_id,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await? else {
+        return Ok(None);
+    };
+
+    sqlx::query!(
+        r#"
+        INSERT INTO team_members (team_id, user_id, role)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (team_id, user_id) DO NOTHING
+        "#,
+        invitation.team_id,
+        user_id,
+        invitation.role
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let row = sqlx::query!(
+        r#"
+        SELECT t.name, m.role
+        FROM teams t
+        JOIN team_members m ON m.team_id = t.id
+        WHERE t.id = $1 AND m.user_id = $2
+        "#,
+        invitation.team_id,
+        user_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(Some(TeamMembership {
+        team_id: invitation.team_id,
+        name: row.name,
+        role: row.role.parse().map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+    }))
+}
+
+// Returns false when the invitation doesn't exist or isn't for this user
+pub async fn decline_invitation_db(pool: &PgPool, invitation_id: Uuid, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM team_invitations
+        WHERE id = $1 AND user_id = $2
+        "#,
+        invitation_id,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn fetch_team_members_db(pool: &PgPool, team_id: Uuid) -> Result<Vec<TeamMember>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT u.id, u.username, m.role
+        FROM team_members m
+        JOIN users u ON u.id = m.user_id
+        WHERE m.team_id = $1
+        ORDER BY u.username
+        "#,
+        team_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| Ok(TeamMember {
+            user_id: row.id,
+            username: row.username,
+            role: row.role.parse().map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+        }))
+        .collect()
+}
+
+// True when `coach_id` coaches a team that `athlete_id` is an athlete on
+pub async fn is_coach_of_db(pool: &PgPool, coach_id: Uuid, athlete_id: Uuid) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1
+            FROM team_members c
+            JOIN team_members a ON a.team_id = c.team_id
+            WHERE c.user_id = $1 AND c.role = 'coach' AND a.user_id = $2 AND a.role = 'athlete'
+        ) AS "coaches!"
+        "#,
+        coach_id,
+        athlete_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.coaches)
+}
+
+pub async fn fetch_coached_athletes_db(pool: &PgPool, coach_id: Uuid) -> Result<Vec<Uuid>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT DISTINCT a.user_id
+        FROM team_members c
+        JOIN team_members a ON a.team_id = c.team_id
+        WHERE c.user_id = $1 AND c.role = 'coach' AND a.role = 'athlete'
+        "#,
+        coach_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(|r| r.user_id).collect())
+}
+
+pub async fn fetch_session_owner_db(pool: &PgPool, session_id: Uuid) -> Result<Option<(Uuid, bool)>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT user_id, private
+        FROM climbing_sessions
+        WHERE id = $1
+        "#,
+        session_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.and_then(|r| r.user_id.map(|owner| (owner, r.private))))
+}
+
+// Returns the session's date, or None when the user doesn't own it
+pub async fn set_session_private_db(pool: &PgPool, session_id: Uuid, user_id: Uuid, private: bool) -> Result<Option<NaiveDate>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        UPDATE climbing_sessions SET private = $3
+        WHERE id = $1 AND user_id = $2
+        RETURNING date
+        "#,
+        session_id,
+        user_id,
+        private
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|r| r.date))
+}
+
+pub async fn insert_comment_db(pool: &PgPool, session_id: Uuid, author_id: Uuid, body: &str) -> Result<SessionComment, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        WITH inserted AS (
+            INSERT INTO session_comments (id, session_id, author_id, body)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, session_id, author_id, body, created_at
+        )
+        SELECT i.id, i.session_id, u.username, i.body, i.created_at
+        FROM inserted i
+        JOIN users u ON u.id = i.author_id
+        "#,
+        Uuid::new_v4(),
+        session_id,
+        author_id,
+        body
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(SessionComment {
+        id: row.id,
+        session_id: row.session_id,
+        author: row.username,
+        body: row.body,
+        created_at: row.created_at,
+    })
+}
+
+pub async fn fetch_comments_db(pool: &PgPool, session_id: Uuid) -> Result<Vec<SessionComment>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT c.id, c.session_id, u.username, c.body, c.created_at
+        FROM session_comments c
+        JOIN users u ON u.id = c.author_id
+        WHERE c.session_id = $1
+        ORDER BY c.created_at
+        "#,
+        session_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| SessionComment {
+            id: row.id,
+            session_id: row.session_id,
+            author: row.username,
+            body: row.body,
+            created_at: row.created_at,
+        })
+        .collect())
+}
@@ -0,0 +1,260 @@
+use crate::climblib::sync::{get_object_bytes, list_aws_files, put_object_bytes};
+
+use aws_config::defaults;
+use aws_config::BehaviorVersion;
+use chrono::{DateTime, Utc};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::{self, Read, Write};
+use tracing::{info, error};
+
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+pub const SNAPSHOT_PREFIX: &str = "snapshots/";
+
+// Parents come before children so restored rows satisfy the foreign keys
+pub const SNAPSHOT_TABLES: &[&str] = &[
+    "users",
+    "api_tokens",
+    "climbing_sessions",
+    "climb_entries",
+    "workout_sessions",
+    "exercise_entries",
+    "climbing_metrics",
+    "goals",
+    "teams",
+    "team_members",
+    "team_invitations",
+    "session_comments",
+    "saved_responses",
+];
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Snapshot {
+    pub format_version: u32,
+    pub migration_version: i64,
+    pub created_at: DateTime<Utc>,
+    pub tables: BTreeMap<String, Value>,
+}
+
+pub fn snapshot_key(snapshot: &Snapshot) -> String {
+    format!(
+        "{}{}-m{}.json.gz",
+        SNAPSHOT_PREFIX,
+        snapshot.created_at.format("%Y%m%dT%H%M%SZ"),
+        snapshot.migration_version
+    )
+}
+
+pub fn encode_snapshot(snapshot: &Snapshot) -> io::Result<Vec<u8>> {
+    let json = serde_json::to_vec(snapshot).map_err(io::Error::other)?;
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&json)?;
+    encoder.finish()
+}
+
+pub fn decode_snapshot(data: &[u8]) -> io::Result<Snapshot> {
+    let mut json = String::new();
+    GzDecoder::new(data).read_to_string(&mut json)?;
+    serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn check_compatible(snapshot: &Snapshot, migration_version: i64) -> Result<(), Box<dyn Error>> {
+    if snapshot.format_version != SNAPSHOT_FORMAT_VERSION {
+        return Err(format!(
+            "snapshot format {} is not supported (expected {})",
+            snapshot.format_version, SNAPSHOT_FORMAT_VERSION
+        ).into());
+    }
+    if snapshot.migration_version != migration_version {
+        return Err(format!(
+            "snapshot was taken at migration {} but the database is at {}",
+            snapshot.migration_version, migration_version
+        ).into());
+    }
+    Ok(())
+}
+
+pub async fn latest_migration(pool: &PgPool) -> Result<i64, sqlx::Error> {
+    let version: Option<i64> = sqlx::query_scalar(
+        "SELECT MAX(version) FROM _sqlx_migrations WHERE success"
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(version.unwrap_or(0))
+}
+
+pub async fn dump_database(pool: &PgPool) -> Result<Snapshot, sqlx::Error> {
+    let mut tables = BTreeMap::new();
+    for table in SNAPSHOT_TABLES {
+        let rows: String = sqlx::query_scalar(&format!(
+            "SELECT COALESCE(json_agg(t), '[]'::json)::text FROM {} t", table
+        ))
+        .fetch_one(pool)
+        .await?;
+        let rows = serde_json::from_str(&rows).map_err(|e| sqlx::Error::Decode(e.into()))?;
+        tables.insert(table.to_string(), rows);
+    }
+
+    Ok(Snapshot {
+        format_version: SNAPSHOT_FORMAT_VERSION,
+        migration_version: latest_migration(pool).await?,
+        created_at: Utc::now(),
+        tables,
+    })
+}
+
+pub async fn restore_database(pool: &PgPool, snapshot: &Snapshot) -> Result<(), Box<dyn Error>> {
+    check_compatible(snapshot, latest_migration(pool).await?)?;
+
+    let mut tx = pool.begin().await?;
+    for table in SNAPSHOT_TABLES {
+        let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+            .fetch_one(&mut *tx)
+            .await?;
+        if count > 0 {
+            return Err(format!("refusing to restore into non-empty table {}", table).into());
+        }
+    }
+    for table in SNAPSHOT_TABLES {
+        let Some(rows) = snapshot.tables.get(*table) else {
+            continue;
+        };
+        sqlx::query(&format!(
+            "INSERT INTO {0} SELECT * FROM json_populate_recordset(NULL::{0}, $1::json)", table
+        ))
+        .bind(rows.to_string())
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+pub(crate) async fn connect(db_connection_str: &str) -> Result<PgPool, sqlx::Error> {
+    let pool = PgPoolOptions::new()
+    .max_connections(5)
+    .acquire_timeout(tokio::time::Duration::from_secs(3))
+    .connect(db_connection_str)
+    .await?;
+    sqlx::migrate!().run(&pool).await?;
+    Ok(pool)
+}
+
+pub async fn snapshot_entrypoint(bucket_name: &str, db_connection_str: &str) {
+    let config = defaults(BehaviorVersion::latest()).load().await;
+    let client = aws_sdk_s3::Client::new(&config);
+
+    let pool = match connect(db_connection_str).await {
+        Ok(pool) => pool,
+        Err(e) => {
+            error!("error {e} connecting to database");
+            return;
+        }
+    };
+    let snapshot = match dump_database(&pool).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            error!("error {e} dumping database");
+            return;
+        }
+    };
+    let key = snapshot_key(&snapshot);
+    let body = match encode_snapshot(&snapshot) {
+        Ok(body) => body,
+        Err(e) => {
+            error!("error {e} encoding snapshot");
+            return;
+        }
+    };
+    match put_object_bytes(bucket_name, &key, body, &client).await {
+        Ok(_) => info!("Uploaded snapshot {}", key),
+        Err(e) => error!("error {e} uploading snapshot {}", key),
+    }
+}
+
+pub async fn restore_entrypoint(bucket_name: &str, db_connection_str: &str, key: Option<&str>) {
+    let config = defaults(BehaviorVersion::latest()).load().await;
+    let client = aws_sdk_s3::Client::new(&config);
+
+    let key = match key {
+        Some(key) => key.to_string(),
+        None => {
+            let latest = list_aws_files(bucket_name, Some(SNAPSHOT_PREFIX), &client).await.map(|objects| {
+                objects
+                    .iter()
+                    .filter_map(|o| o.key())
+                    .max()
+                    .map(|k| k.to_string())
+            });
+            match latest {
+                Ok(Some(key)) => key,
+                Ok(None) => {
+                    error!("No snapshots found in {}", bucket_name);
+                    return;
+                }
+                Err(e) => {
+                    error!("Error listing S3 keys: {e}");
+                    return;
+                }
+            }
+        }
+    };
+
+    let snapshot = match get_object_bytes(bucket_name, &key, &client).await.and_then(|d| Ok(decode_snapshot(&d)?)) {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            error!("error {e} downloading snapshot {}", key);
+            return;
+        }
+    };
+    let pool = match connect(db_connection_str).await {
+        Ok(pool) => pool,
+        Err(e) => {
+            error!("error {e} connecting to database");
+            return;
+        }
+    };
+    match restore_database(&pool, &snapshot).await {
+        Ok(()) => info!("Restored snapshot {}", key),
+        Err(e) => error!("error {e} restoring snapshot {}", key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            format_version: SNAPSHOT_FORMAT_VERSION,
+            migration_version: 20250426185922,
+            created_at: Utc.with_ymd_and_hms(2025, 5, 1, 8, 30, 0).unwrap(),
+            tables: BTreeMap::from([(
+                "climbing_metrics".to_string(),
+                json!([{ "id": "6f1c", "date": "2025-04-30", "finger_strength_percent_bw": 140.0 }]),
+            )]),
+        }
+    }
+
+    #[test]
+    fn test_encode_and_decode_snapshot_roundtrip() {
+        let snapshot = snapshot();
+        let encoded = encode_snapshot(&snapshot).expect("Failed to encode");
+        assert_eq!(decode_snapshot(&encoded).expect("Failed to decode"), snapshot);
+        assert_eq!(snapshot_key(&snapshot), "snapshots/20250501T083000Z-m20250426185922.json.gz");
+    }
+
+    #[test]
+    fn test_check_compatible() {
+        let snapshot = snapshot();
+        assert!(check_compatible(&snapshot, 20250426185922).is_ok());
+        assert!(check_compatible(&snapshot, 20250601000000).is_err());
+        assert!(check_compatible(&Snapshot { format_version: 2, ..snapshot }, 20250426185922).is_err());
+    }
+}
@@ -1,3 +1,4 @@
 pub mod climblib;
 pub mod api;
-pub mod db;
\ No newline at end of file
+pub mod db;
+pub mod tui;
\ No newline at end of file
@@ -0,0 +1,335 @@
+use crate::climblib::calendar::{daily_activity, DayActivity};
+use crate::climblib::io::load_logs;
+use crate::climblib::load::{climbing_daily_load, lifting_daily_load, weekly_loads, WeeklyLoad};
+use crate::climblib::models::{ClimbMetricsEntry, ClimbingSession, WorkoutSession};
+use crate::climblib::pyramid::{build_pyramid, Pyramid, PyramidRow};
+use crate::climblib::utils::{is_climb, is_metrics, is_workout, DateRange, Period};
+use chrono::{Duration, NaiveDate, Utc};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::symbols;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Axis, Block, Borders, Chart, Dataset, GraphType, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::BTreeMap;
+use std::io;
+use uuid::Uuid;
+
+pub const HEATMAP_WEEKS: usize = 26;
+pub const LOAD_WEEKS: usize = 12;
+
+#[derive(Debug, PartialEq)]
+pub struct RecentSession {
+    pub date: String,
+    pub title: String,
+    pub details: Vec<String>,
+}
+
+pub struct Dashboard {
+    pub today: NaiveDate,
+    pub activity: BTreeMap<NaiveDate, DayActivity>,
+    pub pyramid: Pyramid,
+    pub loads: Vec<WeeklyLoad>,
+    pub recent: Vec<RecentSession>,
+    pub list: ListState,
+    pub open: bool,
+    pub quit: bool,
+}
+
+fn climbing_entry(session: &ClimbingSession) -> RecentSession {
+    RecentSession {
+        date: session.date.clone(),
+        title: format!("{:?} at {} ({} climbs)", session.style, session.location, session.climbs.len()),
+        details: session
+            .climbs
+            .iter()
+            .map(|c| format!(
+                "{:<6} {:<20} {} in {} attempts{}",
+                c.grade.to_string(),
+                c.name.as_deref().unwrap_or("-"),
+                if c.sent { "sent" } else if c.reached_top { "topped" } else { "worked" },
+                c.attempts,
+                if c.lead { format!(", lead with {} rests", c.rests.unwrap_or(0)) } else { String::new() },
+            ))
+            .chain(session.notes.iter().map(|n| format!("Notes: {}", n)))
+            .collect(),
+    }
+}
+
+fn workout_entry(workout: &WorkoutSession) -> RecentSession {
+    RecentSession {
+        date: workout.date.clone(),
+        title: format!("Workout ({} exercises)", workout.exercises.len()),
+        details: workout
+            .exercises
+            .iter()
+            .map(|e| format!(
+                "{:<20} {}x{} @ {} lb{}",
+                e.name,
+                e.sets,
+                e.reps,
+                e.weight_lb,
+                e.rpe.map_or(String::new(), |r| format!(" RPE {}", r)),
+            ))
+            .chain(workout.notes.iter().map(|n| format!("Notes: {}", n)))
+            .collect(),
+    }
+}
+
+pub fn recent_sessions(sessions: &[ClimbingSession], workouts: &[WorkoutSession]) -> Vec<RecentSession> {
+    let mut recent: Vec<RecentSession> = sessions
+        .iter()
+        .map(climbing_entry)
+        .chain(workouts.iter().map(workout_entry))
+        .collect();
+    recent.sort_by(|a, b| b.date.cmp(&a.date));
+    recent
+}
+
+// Rows are Monday to Sunday and columns are weeks, oldest first, ending with the week of `today`
+pub fn heatmap_cells(activity: &BTreeMap<NaiveDate, DayActivity>, today: NaiveDate, weeks: usize) -> Vec<Vec<Option<u32>>> {
+    let first = Period::Week.start(today) - Duration::weeks(weeks as i64 - 1);
+    (0..7)
+        .map(|weekday| {
+            (0..weeks)
+                .map(|week| {
+                    let date = first + Duration::days(week as i64 * 7 + weekday);
+                    (date <= today).then(|| activity.get(&date).map_or(0, |a| a.total()))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+impl Dashboard {
+    pub fn new(
+        sessions: &[ClimbingSession],
+        workouts: &[WorkoutSession],
+        metrics: &[ClimbMetricsEntry],
+        today: NaiveDate) -> Self {
+
+        let activity = daily_activity(sessions, workouts, metrics);
+        let mut loads = weekly_loads(&climbing_daily_load(sessions), &lifting_daily_load(workouts));
+        loads.drain(..loads.len().saturating_sub(LOAD_WEEKS));
+        let recent = recent_sessions(sessions, workouts);
+        let mut list = ListState::default();
+        list.select((!recent.is_empty()).then_some(0));
+
+        Dashboard {
+            today,
+            activity,
+            pyramid: build_pyramid(sessions, &DateRange::default()),
+            loads,
+            recent,
+            list,
+            open: false,
+            quit: false,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Esc | KeyCode::Backspace | KeyCode::Left if self.open => self.open = false,
+            KeyCode::Esc => self.quit = true,
+            KeyCode::Enter | KeyCode::Right => self.open = self.list.selected().is_some(),
+            KeyCode::Down | KeyCode::Char('j') if !self.open => {
+                let last = self.recent.len().saturating_sub(1);
+                self.list.select(self.list.selected().map(|i| (i + 1).min(last)));
+            }
+            KeyCode::Up | KeyCode::Char('k') if !self.open => {
+                self.list.select(self.list.selected().map(|i| i.saturating_sub(1)));
+            }
+            _ => {}
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(9), Constraint::Min(8), Constraint::Percentage(40)])
+            .split(frame.area());
+        let top = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(HEATMAP_WEEKS as u16 * 2 + 6), Constraint::Min(20)])
+            .split(rows[0]);
+
+        self.draw_heatmap(frame, top[0]);
+        self.draw_pyramid(frame, top[1]);
+        self.draw_load(frame, rows[1]);
+        if self.open {
+            self.draw_details(frame, rows[2]);
+        } else {
+            self.draw_recent(frame, rows[2]);
+        }
+    }
+
+    fn draw_heatmap(&self, frame: &mut Frame, area: Rect) {
+        let days = ["M", "T", "W", "T", "F", "S", "S"];
+        let lines: Vec<Line> = heatmap_cells(&self.activity, self.today, HEATMAP_WEEKS)
+            .into_iter()
+            .zip(days)
+            .map(|(row, day)| {
+                let mut spans = vec![Span::raw(format!("{} ", day))];
+                spans.extend(row.into_iter().map(|cell| {
+                    let color = match cell {
+                        None => Color::Reset,
+                        Some(0) => Color::DarkGray,
+                        Some(1) => Color::Green,
+                        Some(_) => Color::LightGreen,
+                    };
+                    Span::styled(if cell.is_some() { "■ " } else { "  " }, Style::default().fg(color))
+                }));
+                Line::from(spans)
+            })
+            .collect();
+        let title = format!(" Sessions, last {} weeks to {} ", HEATMAP_WEEKS, self.today.format("%b %d"));
+        frame.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title)), area);
+    }
+
+    fn draw_pyramid(&self, frame: &mut Frame, area: Rect) {
+        let row_line = |discipline: &str, row: &PyramidRow| {
+            Line::from(format!("{:<9}{:<7}{:>3} {}", discipline, row.grade, row.sends, "#".repeat(row.sends as usize)))
+        };
+        let lines: Vec<Line> = self.pyramid.boulder.iter().map(|r| row_line("boulder", r))
+            .chain(self.pyramid.lead.iter().map(|r| row_line("lead", r)))
+            .chain(self.pyramid.top_rope.iter().map(|r| row_line("top rope", r)))
+            .collect();
+        frame.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(" Grade pyramid ")), area);
+    }
+
+    fn draw_load(&self, frame: &mut Frame, area: Rect) {
+        let climbing: Vec<(f64, f64)> = self.loads.iter().enumerate().map(|(i, w)| (i as f64, w.climbing_load)).collect();
+        let lifting: Vec<(f64, f64)> = self.loads.iter().enumerate().map(|(i, w)| (i as f64, w.lifting_load)).collect();
+        let max = self.loads.iter().map(|w| w.climbing_load.max(w.lifting_load)).fold(1.0, f64::max);
+        let labels: Vec<Span> = [self.loads.first(), self.loads.last()]
+            .into_iter()
+            .flatten()
+            .map(|w| Span::raw(w.week.clone()))
+            .collect();
+        let datasets = vec![
+            Dataset::default().name("climbing").marker(symbols::Marker::Braille).graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::Cyan)).data(&climbing),
+            Dataset::default().name("lifting").marker(symbols::Marker::Braille).graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::Magenta)).data(&lifting),
+        ];
+        let chart = Chart::new(datasets)
+            .block(Block::default().borders(Borders::ALL).title(" Weekly training load "))
+            .x_axis(Axis::default().bounds([0.0, self.loads.len().saturating_sub(1).max(1) as f64]).labels(labels))
+            .y_axis(Axis::default().bounds([0.0, max]).labels(["0".to_string(), format!("{:.0}", max)]));
+        frame.render_widget(chart, area);
+    }
+
+    fn draw_recent(&mut self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self.recent
+            .iter()
+            .map(|r| ListItem::new(format!("{}  {}", r.date, r.title)))
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(" Recent sessions (↑/↓ select, enter open, q quit) "))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.list);
+    }
+
+    fn draw_details(&self, frame: &mut Frame, area: Rect) {
+        let Some(session) = self.list.selected().and_then(|i| self.recent.get(i)) else {
+            return;
+        };
+        let lines: Vec<Line> = session.details.iter().map(|d| Line::from(d.as_str())).collect();
+        let title = format!(" {} {} (esc back) ", session.date, session.title);
+        frame.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title)), area);
+    }
+}
+
+fn run(terminal: &mut DefaultTerminal, dashboard: &mut Dashboard) -> io::Result<()> {
+    while !dashboard.quit {
+        terminal.draw(|frame| dashboard.draw(frame))?;
+        if let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            dashboard.handle_key(key.code);
+        }
+    }
+    Ok(())
+}
+
+pub fn run_dashboard(user: Option<Uuid>) -> io::Result<()> {
+    let sessions: Vec<ClimbingSession> = load_logs(user, is_climb);
+    let workouts: Vec<WorkoutSession> = load_logs(user, is_workout);
+    let metrics: Vec<ClimbMetricsEntry> = load_logs(user, is_metrics);
+    let mut dashboard = Dashboard::new(&sessions, &workouts, &metrics, Utc::now().date_naive());
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut dashboard);
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Datelike;
+    use crate::climblib::test_support::{climb, exercise, session, workout};
+    use crate::climblib::models::{BoulderGrade, ClimbStyle, Grade};
+
+    fn date(m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, m, d).unwrap()
+    }
+
+    fn dashboard() -> Dashboard {
+        let sessions = vec![session("2024-04-08", ClimbStyle::Boulder, vec![
+            climb(Grade::Boulder(BoulderGrade::V4), 2, true, false),
+        ])];
+        let workouts = vec![
+            workout("2024-04-10", vec![exercise("Squat", 5, 200, Some(8))]),
+            workout("2024-04-08", vec![exercise("Bench", 5, 150, None)]),
+        ];
+        Dashboard::new(&sessions, &workouts, &[], date(4, 10))
+    }
+
+    #[test]
+    fn test_heatmap_cells() {
+        let dashboard = dashboard();
+        let cells = heatmap_cells(&dashboard.activity, dashboard.today, 2);
+        assert_eq!(cells.len(), 7);
+        assert_eq!(cells[date(4, 8).weekday().num_days_from_monday() as usize][1], Some(2));
+        assert_eq!(cells[2][1], Some(1));
+        assert_eq!(cells[3][1], None);
+        assert_eq!(cells[0][0], Some(0));
+    }
+
+    #[test]
+    fn test_navigation() {
+        let mut dashboard = dashboard();
+        assert_eq!(dashboard.recent[0].title, "Workout (1 exercises)");
+        assert_eq!(dashboard.list.selected(), Some(0));
+
+        dashboard.handle_key(KeyCode::Down);
+        dashboard.handle_key(KeyCode::Down);
+        dashboard.handle_key(KeyCode::Down);
+        assert_eq!(dashboard.list.selected(), Some(2));
+
+        dashboard.handle_key(KeyCode::Enter);
+        assert!(dashboard.open);
+        dashboard.handle_key(KeyCode::Up);
+        assert_eq!(dashboard.list.selected(), Some(2));
+        dashboard.handle_key(KeyCode::Esc);
+        assert!(!dashboard.open && !dashboard.quit);
+        dashboard.handle_key(KeyCode::Char('q'));
+        assert!(dashboard.quit);
+    }
+
+    #[test]
+    fn test_draw_renders_panels() {
+        let mut dashboard = dashboard();
+        let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(100, 40)).unwrap();
+        terminal.draw(|frame| dashboard.draw(frame)).unwrap();
+        dashboard.handle_key(KeyCode::Enter);
+        terminal.draw(|frame| dashboard.draw(frame)).unwrap();
+
+        let screen: String = terminal.backend().buffer().content().iter().map(|c| c.symbol()).collect();
+        assert!(screen.contains("Grade pyramid"));
+        assert!(screen.contains("Squat"));
+    }
+}
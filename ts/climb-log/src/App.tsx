@@ -1,12 +1,32 @@
-import React from 'react';
+import React, { useState } from 'react';
 import TabbedWorkoutLogger from './components/TabbedLogger';
+import LoginForm from './components/LoginForm';
+import { clearApiToken, hasApiToken } from './utils/storage';
 
 function App() {
+  const [signedIn, setSignedIn] = useState(hasApiToken());
+
+  function signOut() {
+    clearApiToken();
+    setSignedIn(false);
+  }
+
   return (
     <div className="bg-gray-50 min-h-screen">
-      <TabbedWorkoutLogger />
+      {signedIn ? (
+        <>
+          <div className="max-w-4xl mx-auto px-4 pt-4 flex justify-end">
+            <button onClick={signOut} className="text-sm text-gray-600 hover:underline">
+              Sign out
+            </button>
+          </div>
+          <TabbedWorkoutLogger />
+        </>
+      ) : (
+        <LoginForm onLogin={() => setSignedIn(true)} />
+      )}
     </div>
   );
 }
 
-export default App;
\ No newline at end of file
+export default App;
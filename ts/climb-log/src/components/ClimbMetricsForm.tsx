@@ -1,10 +1,11 @@
 import React, { useState, useEffect } from 'react';
 import { ClimbMetricsEntry } from '../types/types';
+import { authHeaders } from '../utils/storage';
 
 const getDefaultSession = (): ClimbMetricsEntry => ({
   date: new Date().toISOString().split("T")[0],
-  fingerStrengthPercentBW: 0,
-  maxPullupPercentBW: 0,
+  fingerStrengthPercentBw: 0,
+  maxPullupPercentBw: 0,
   notes: ''
 });
 
@@ -33,6 +34,7 @@ const ClimbingMetricsForm: React.FC = () => {
         method: "POST",
         headers: {
           "Content-Type": "application/json",
+          ...authHeaders(),
         },
         body: JSON.stringify(session),
       });
@@ -82,8 +84,8 @@ const ClimbingMetricsForm: React.FC = () => {
             <label className="font-medium text-sm">Finger Strength (% Bodyweight)</label>
             <input
               type="number"
-              value={session.fingerStrengthPercentBW}
-              onChange={(e) => setSession({ ...session, fingerStrengthPercentBW: Number(e.target.value) })}
+              value={session.fingerStrengthPercentBw}
+              onChange={(e) => setSession({ ...session, fingerStrengthPercentBw: Number(e.target.value) })}
               className="border border-gray-300 rounded px-4 py-2 focus:outline-none focus:ring-2 focus:ring-blue-500"
             />
           </div>
@@ -92,8 +94,8 @@ const ClimbingMetricsForm: React.FC = () => {
             <label className="font-medium text-sm">Max Pullup (% Bodyweight)</label>
             <input
               type="number"
-              value={session.maxPullupPercentBW}
-              onChange={(e) => setSession({ ...session, maxPullupPercentBW: Number(e.target.value)  })}
+              value={session.maxPullupPercentBw}
+              onChange={(e) => setSession({ ...session, maxPullupPercentBw: Number(e.target.value)  })}
               className="border border-gray-300 rounded px-4 py-2 focus:outline-none focus:ring-2 focus:ring-blue-500"
             />
           </div>
@@ -1,5 +1,6 @@
 import React, { useState, useEffect } from 'react';
 import { ClimbingSession, ClimbEntry } from '../types/types';
+import { authHeaders } from '../utils/storage';
 
 const ropeGrades = [
   "5.intro", "5.6", "5.7", "5.8", "5.9",
@@ -21,6 +22,7 @@ const getDefaultSession = (): ClimbingSession => ({
   style: 'boulder',
   notes: '',
   climbs: [],
+  private: false,
 });
 
 const ClimbingSessionForm: React.FC = () => {
@@ -68,6 +70,7 @@ const ClimbingSessionForm: React.FC = () => {
         method: "POST",
         headers: {
           "Content-Type": "application/json",
+          ...authHeaders(),
         },
         body: JSON.stringify(session),
       });
@@ -157,6 +160,15 @@ const ClimbingSessionForm: React.FC = () => {
             />
           </div>
 
+          <label className="flex items-center gap-2 text-sm">
+            <input
+              type="checkbox"
+              checked={session.private ?? false}
+              onChange={(e) => setSession({ ...session, private: e.target.checked })}
+            />
+            Private (hidden from coaches)
+          </label>
+
           <div className="flex gap-4 pt-2">
             <button
               type="button"
@@ -0,0 +1,107 @@
+import React, { useState } from 'react';
+import { Credentials, TokenResponse } from '../types/types';
+import { saveApiToken } from '../utils/storage';
+
+interface LoginFormProps {
+  onLogin: () => void;
+}
+
+const LoginForm: React.FC<LoginFormProps> = ({ onLogin }) => {
+  const [mode, setMode] = useState<'login' | 'register'>('login');
+  const [credentials, setCredentials] = useState<Credentials>({ username: '', password: '' });
+  const [error, setError] = useState<string | null>(null);
+
+  async function submit() {
+    setError(null);
+    if (!credentials.username || !credentials.password) {
+      setError("Please enter a username and password.");
+      return;
+    }
+
+    try {
+      const res = await fetch(`http://localhost:3000/api/auth/${mode}`, {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+        body: JSON.stringify({ ...credentials, token_name: "web" }),
+      });
+
+      if (res.ok) {
+        const { token, userId }: TokenResponse = await res.json();
+        saveApiToken(token, userId);
+        onLogin();
+      } else if (res.status === 401) {
+        setError("Wrong username or password.");
+      } else if (res.status === 409) {
+        setError("That username is taken.");
+      } else if (res.status === 422) {
+        setError("Usernames need 3 to 50 characters and passwords at least 8.");
+      } else {
+        setError(mode === 'login' ? "Failed to sign in." : "Failed to create the account.");
+      }
+    } catch (err) {
+      setError("Network or server error. Please try again.");
+      console.error("Error with network or server");
+    }
+  }
+
+  return (
+    <div className="max-w-md mx-auto p-10 bg-white text-gray-800">
+      {error && (
+        <div className="mb-6 p-4 bg-red-100 border border-red-300 text-red-800 rounded">
+          {error}
+        </div>
+      )}
+      <form
+        className="space-y-6"
+        onSubmit={(e) => {
+          e.preventDefault();
+          submit();
+        }}
+      >
+        <h2 className="text-xl font-bold text-gray-700">
+          {mode === 'login' ? 'Sign in' : 'Create an account'}
+        </h2>
+
+        <div className="flex flex-col gap-1">
+          <label className="font-medium text-sm">Username</label>
+          <input
+            type="text"
+            autoComplete="username"
+            value={credentials.username}
+            onChange={(e) => setCredentials({ ...credentials, username: e.target.value })}
+            className="border border-gray-300 rounded px-4 py-2 focus:outline-none focus:ring-2 focus:ring-blue-500"
+          />
+        </div>
+
+        <div className="flex flex-col gap-1">
+          <label className="font-medium text-sm">Password</label>
+          <input
+            type="password"
+            autoComplete={mode === 'login' ? 'current-password' : 'new-password'}
+            value={credentials.password}
+            onChange={(e) => setCredentials({ ...credentials, password: e.target.value })}
+            className="border border-gray-300 rounded px-4 py-2 focus:outline-none focus:ring-2 focus:ring-blue-500"
+          />
+        </div>
+
+        <div className="flex items-center gap-4">
+          <button
+            type="submit"
+            className="px-5 py-2 bg-blue-600 text-white rounded-md hover:bg-blue-700 shadow"
+          >
+            {mode === 'login' ? 'Sign in' : 'Register'}
+          </button>
+          <button
+            type="button"
+            onClick={() => setMode(mode === 'login' ? 'register' : 'login')}
+            className="text-sm text-blue-600 hover:underline"
+          >
+            {mode === 'login' ? 'Need an account? Register' : 'Have an account? Sign in'}
+          </button>
+        </div>
+      </form>
+    </div>
+  );
+};
+
+export default LoginForm;
@@ -1,6 +1,6 @@
 import React, { useState, useEffect } from 'react';
 import { WorkoutSession, ExerciseEntry } from '../types/types';
-import { safeJSONParse } from '../utils/storage';
+import { authHeaders, safeJSONParse } from '../utils/storage';
 
 const defaultExercises = [
   "deadlift", "squat", "pullups", "dips", "bench press",
@@ -102,6 +102,7 @@ const WorkoutSessionForm: React.FC = () => {
         method: "POST",
         headers: {
           "Content-Type": "application/json",
+          ...authHeaders(),
         },
         body: JSON.stringify(session),
       });
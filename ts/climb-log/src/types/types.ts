@@ -1,3 +1,5 @@
+// Mirrors the schemas served at /api/openapi.json; api::openapi tests fail when the two drift apart
+
 export type ClimbStyle = "boulder" | "rope";
 
 export interface ClimbEntry {
@@ -11,17 +13,21 @@ export interface ClimbEntry {
 }
 
 export interface ClimbingSession {
+  // Reuse the same id when retrying a save so the server can recognise the replay
+  id?: string;
   date: string;
   location: string;
   style: ClimbStyle;
   notes?: string;
   climbs: ClimbEntry[];
+  private?: boolean;
 }
 
 export interface ClimbMetricsEntry {
+  id?: string;
   date: string;
-  fingerStrengthPercentBW?: number;
-  maxPullupPercentBW?: number;
+  fingerStrengthPercentBw?: number;
+  maxPullupPercentBw?: number;
   notes?: string;
 }
 
@@ -35,6 +41,7 @@ export interface ExerciseEntry {
 }
 
 export interface WorkoutSession {
+  id?: string;
   date: string;
   notes?: string;
   exercises: ExerciseEntry[];
@@ -42,4 +49,29 @@ export interface WorkoutSession {
 
 export type WorkoutTemplate = {
   [templateName: string]: ExerciseEntry[];
-};
\ No newline at end of file
+};
+export type LogType = "climb" | "workout" | "metrics";
+
+// "visibility" means a session was made private or shared again; refetch or drop it
+export type ChangeKind = "created" | "updated" | "visibility";
+
+export interface LogEvent {
+  change: ChangeKind;
+  logType: LogType;
+  userId: string;
+  username: string;
+  date: string;
+  sessionId?: string;
+  private: boolean;
+}
+
+export interface Credentials {
+  username: string;
+  password: string;
+  token_name?: string;
+}
+
+export interface TokenResponse {
+  userId: string;
+  token: string;
+}
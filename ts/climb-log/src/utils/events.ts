@@ -0,0 +1,24 @@
+import { ChangeKind, LogEvent, LogType } from "../types/types";
+
+const CHANGES: ChangeKind[] = ["created", "updated", "visibility"];
+
+// EventSource can't send an Authorization header, so the token goes in the query string.
+// `onLagged` fires when the server dropped events and the caller should refetch.
+export function subscribeToLogEvents(
+  onEvent: (event: LogEvent) => void,
+  filters: { userId?: string; logType?: LogType } = {},
+  onLagged?: () => void,
+): () => void {
+  const params = new URLSearchParams();
+  const token = localStorage.getItem("apiToken");
+  if (token) params.set("access_token", token);
+  if (filters.userId) params.set("user_id", filters.userId);
+  if (filters.logType) params.set("log_type", filters.logType);
+
+  const source = new EventSource(`http://localhost:3000/api/events?${params}`);
+  for (const change of CHANGES) {
+    source.addEventListener(change, (e) => onEvent(JSON.parse((e as MessageEvent).data)));
+  }
+  if (onLagged) source.addEventListener("lagged", onLagged);
+  return () => source.close();
+}
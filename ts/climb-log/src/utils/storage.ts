@@ -8,4 +8,23 @@ export function safeJSONParse<T>(key: string, fallback: T): T {
       localStorage.removeItem(key);
       return fallback;
     }
-  }
\ No newline at end of file
+  }
+
+export function authHeaders(): Record<string, string> {
+    const token = localStorage.getItem("apiToken");
+    return token ? { Authorization: `Bearer ${token}` } : {};
+  }
+
+export function hasApiToken(): boolean {
+    return localStorage.getItem("apiToken") !== null;
+  }
+
+export function saveApiToken(token: string, userId: string) {
+    localStorage.setItem("apiToken", token);
+    localStorage.setItem("userId", userId);
+  }
+
+export function clearApiToken() {
+    localStorage.removeItem("apiToken");
+    localStorage.removeItem("userId");
+  }